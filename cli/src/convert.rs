@@ -0,0 +1,172 @@
+use std::fs::{self, File};
+use std::io::{BufWriter, Cursor, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use clap::ValueEnum;
+use indicatif::{ProgressBar, ProgressBarIter, ProgressStyle};
+use tsfile::prelude::*;
+
+use crate::Result;
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum Target {
+    V3,
+    Parquet,
+    Csv,
+}
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// The TsFile to convert
+    file: PathBuf,
+    /// The file to write for `v3`, otherwise the directory to write a file per device to
+    output: PathBuf,
+    /// `v3` upgrades a version 2 file, `parquet` and `csv` write the rows of each device
+    #[arg(long, value_enum)]
+    to: Target,
+    /// Devices to convert in parallel, by default one per CPU
+    #[arg(long)]
+    jobs: Option<usize>,
+    /// Only these sensors of each device, e.g. `s1,s2`; the chunks of the others are not read
+    #[arg(long, value_delimiter = ',')]
+    sensors: Option<Vec<String>>,
+}
+
+pub fn run(args: &Args) -> Result<()> {
+    match args.to {
+        Target::V3 => upgrade(args),
+        Target::Parquet | Target::Csv => convert_devices(args),
+    }
+}
+
+fn upgrade(args: &Args) -> Result<()> {
+    if args.output == args.file {
+        return Err("the output must not be the input file".into());
+    }
+    let input = File::open(&args.file)?;
+    let bar = ProgressBar::new(input.len()).with_style(ProgressStyle::with_template(
+        "{bar:40} {bytes}/{total_bytes} {eta}",
+    )?);
+    let input = Progress {
+        inner: input,
+        bar: bar.clone(),
+    };
+    let output = BufWriter::new(File::create(&args.output)?);
+    let (output, summary) = upgrade_v2(input, output)?;
+    output
+        .into_inner()
+        .map_err(|e| e.into_error())?
+        .sync_all()?;
+    bar.finish_and_clear();
+    println!(
+        "upgraded {} chunk groups with {} chunks, {} re-encoded",
+        summary.chunk_groups(),
+        summary.chunks(),
+        summary.reencoded_chunks()
+    );
+    println!("wrote {}", args.output.display());
+    Ok(())
+}
+
+/// Converts the devices of the file on `--jobs` threads, each with its own reader.
+fn convert_devices(args: &Args) -> Result<()> {
+    let devices = TsFileSearchReader::open(&args.file, ReaderOptions::new())?.devices()?;
+    fs::create_dir_all(&args.output)?;
+    let jobs = args
+        .jobs
+        .unwrap_or_else(|| thread::available_parallelism().map_or(1, |n| n.get()))
+        .clamp(1, devices.len().max(1));
+    let bar = ProgressBar::new(devices.len() as u64).with_style(ProgressStyle::with_template(
+        "{bar:40} {pos}/{len} devices",
+    )?);
+
+    let next = AtomicUsize::new(0);
+    let worker = || -> std::result::Result<(), String> {
+        let reader = TsFileSearchReader::open(&args.file, ReaderOptions::new())
+            .map_err(|e| e.to_string())?;
+        while let Some(device) = devices.get(next.fetch_add(1, Ordering::Relaxed)) {
+            if let Err(e) = convert_device(&reader, device, args) {
+                // let the other workers stop after their current device
+                next.store(devices.len(), Ordering::Relaxed);
+                return Err(format!("{}: {}", device, e));
+            }
+            bar.inc(1);
+        }
+        Ok(())
+    };
+    let results: Vec<_> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..jobs).map(|_| scope.spawn(worker)).collect();
+        workers
+            .into_iter()
+            .map(|w| w.join().unwrap_or_else(|_| Err("worker panicked".into())))
+            .collect()
+    });
+    bar.finish_and_clear();
+    results
+        .into_iter()
+        .collect::<std::result::Result<(), _>>()?;
+    println!(
+        "wrote {} devices to {}",
+        devices.len(),
+        args.output.display()
+    );
+    Ok(())
+}
+
+fn convert_device<F: FileReader + ?Sized>(reader: &F, device: &str, args: &Args) -> Result<()> {
+    let name = device.replace(['/', '\\'], "_");
+    let extension = if args.to == Target::Parquet {
+        "parquet"
+    } else {
+        "csv"
+    };
+    let path = args.output.join(format!("{}.{}", name, extension));
+    let out = BufWriter::new(File::create(&path)?);
+    let mut out = match (args.to, &args.sensors) {
+        (Target::Parquet, Some(sensors)) => write_sensors(reader, device, sensors, out)?,
+        (Target::Parquet, None) => write_device(reader, device, out)?,
+        (_, sensors) => {
+            let pattern = match sensors {
+                Some(sensors) => PathPattern::sensors(device, sensors),
+                None => PathPattern::new(&format!("{}.*", device)),
+            };
+            let options = CsvOptions::new().with_layout(Layout::Wide);
+            write_csv(reader, &pattern, None, &options, out)?
+        }
+    };
+    out.flush()?;
+    Ok(())
+}
+
+/// Advances a progress bar by the bytes read from a file.
+struct Progress<R> {
+    inner: R,
+    bar: ProgressBar,
+}
+
+impl<R: Length> Length for Progress<R> {
+    fn len(&self) -> u64 {
+        self.inner.len()
+    }
+}
+
+impl<R: SectionReader> SectionReader for Progress<R> {
+    type T = ProgressBarIter<R::T>;
+
+    fn get_read(&self, start: u64, len: usize) -> Self::T {
+        self.bar.wrap_read(self.inner.get_read(start, len))
+    }
+
+    /// Reads through `get_read`, so the bytes are counted like those of any other read.
+    fn get_cursor(
+        &self,
+        start: u64,
+        len: usize,
+    ) -> std::result::Result<Cursor<Vec<u8>>, ReadError> {
+        let mut data = Vec::new();
+        self.read_into(start, len, &mut data)?;
+        Ok(Cursor::new(data))
+    }
+}
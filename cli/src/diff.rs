@@ -0,0 +1,135 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use tsfile::prelude::*;
+
+use crate::Result;
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// The first TsFile
+    a: PathBuf,
+    /// The second TsFile
+    b: PathBuf,
+    /// Also compare every point of the series both files have
+    #[arg(long)]
+    data: bool,
+}
+
+pub fn run(args: &Args) -> Result<()> {
+    let a = TsFileSearchReader::open(&args.a, ReaderOptions::new())?;
+    let b = TsFileSearchReader::open(&args.b, ReaderOptions::new())?;
+    let a_series = series(&a)?;
+    let b_series = series(&b)?;
+
+    let mut differences = 0;
+    let mut report = |line: String| {
+        println!("{}", line);
+        differences += 1;
+    };
+    for path in a_series.keys().filter(|p| !b_series.contains_key(*p)) {
+        report(format!("- {}: only in {}", path, args.a.display()));
+    }
+    for path in b_series.keys().filter(|p| !a_series.contains_key(*p)) {
+        report(format!("+ {}: only in {}", path, args.b.display()));
+    }
+    for (path, (device, a_meta)) in &a_series {
+        let b_meta = match b_series.get(path) {
+            Some((_, meta)) => meta,
+            None => continue,
+        };
+        if a_meta.data_type() != b_meta.data_type() {
+            report(format!(
+                "~ {}: type {:?} != {:?}",
+                path,
+                a_meta.data_type(),
+                b_meta.data_type()
+            ));
+            continue;
+        }
+        let (a_stat, b_stat) = (a_meta.statistic(), b_meta.statistic());
+        if a_stat.count() != b_stat.count() {
+            report(format!(
+                "~ {}: points {} != {}",
+                path,
+                a_stat.count(),
+                b_stat.count()
+            ));
+        }
+        if a_stat.time_range() != b_stat.time_range() {
+            report(format!(
+                "~ {}: time range {:?} != {:?}",
+                path,
+                a_stat.time_range(),
+                b_stat.time_range()
+            ));
+        }
+        if args.data {
+            let sensor = a_meta.measurement_id();
+            let a_points = SeriesPoints::new(a.get_sensor_reader(device, sensor)?);
+            let b_points = SeriesPoints::new(b.get_sensor_reader(device, sensor)?);
+            if let Some(difference) = first_difference(a_points, b_points)? {
+                report(format!("~ {}: {}", path, difference));
+            }
+        }
+    }
+
+    if differences > 0 {
+        return Err(format!("{} differences", differences).into());
+    }
+    println!("no differences");
+    Ok(())
+}
+
+/// The series of a file by `device.sensor` path, with their device.
+fn series<F: FileReader + ?Sized>(
+    reader: &F,
+) -> Result<BTreeMap<String, (String, TimeseriesMetadata)>> {
+    Ok(reader
+        .match_paths(&PathPattern::new("**"))?
+        .into_iter()
+        .map(|(device, meta)| {
+            (
+                format!("{}.{}", device, meta.measurement_id()),
+                (device, meta),
+            )
+        })
+        .collect())
+}
+
+/// Describes the first point where the two series differ, if any.
+fn first_difference(a: SeriesPoints, b: SeriesPoints) -> Result<Option<String>> {
+    let mut a = a.fuse();
+    let mut b = b.fuse();
+    loop {
+        match (a.next().transpose()?, b.next().transpose()?) {
+            (None, None) => return Ok(None),
+            (Some((time, _)), None) => {
+                return Ok(Some(format!("extra point at {} in the first", time)))
+            }
+            (None, Some((time, _))) => {
+                return Ok(Some(format!("extra point at {} in the second", time)))
+            }
+            (Some((a_time, a_value)), Some((b_time, b_value))) => {
+                if a_time != b_time {
+                    return Ok(Some(format!("time {} != {}", a_time, b_time)));
+                }
+                if !same_value(&a_value, &b_value) {
+                    return Ok(Some(format!(
+                        "value at {}: {} != {}",
+                        a_time, a_value, b_value
+                    )));
+                }
+            }
+        }
+    }
+}
+
+/// Equality that also matches NaN with NaN, so rewritten files compare equal.
+fn same_value(a: &Field, b: &Field) -> bool {
+    match (a, b) {
+        (Field::FLOAT(a), Field::FLOAT(b)) => a.to_bits() == b.to_bits() || a == b,
+        (Field::DOUBLE(a), Field::DOUBLE(b)) => a.to_bits() == b.to_bits() || a == b,
+        _ => a == b,
+    }
+}
@@ -0,0 +1,90 @@
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+
+use clap::ValueEnum;
+use serde_json::{json, Value};
+use tsfile::prelude::*;
+
+use crate::Result;
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Format {
+    Csv,
+    Jsonl,
+}
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// The TsFile to read
+    file: PathBuf,
+    /// Path pattern of the series to dump, e.g. `root.sg.d1.s1` or `root.sg.**`
+    #[arg(long, default_value = "**")]
+    path: String,
+    /// First timestamp to dump
+    #[arg(long)]
+    start: Option<i64>,
+    /// Timestamp to stop before
+    #[arg(long)]
+    end: Option<i64>,
+    /// `csv` lines of time,device,sensor,value or one JSON object per line
+    #[arg(long, value_enum, default_value = "csv")]
+    format: Format,
+}
+
+pub fn run(args: &Args) -> Result<()> {
+    let reader = TsFileSearchReader::open(&args.file, ReaderOptions::new())?;
+    let pattern = PathPattern::new(&args.path);
+    let filter = time_filter(args.start, args.end);
+    let mut out = BufWriter::new(io::stdout().lock());
+    match args.format {
+        Format::Csv => {
+            let options = CsvOptions::new().with_layout(Layout::Long);
+            write_csv(&reader, &pattern, filter.as_ref(), &options, &mut out)?;
+        }
+        Format::Jsonl => {
+            for (device, meta) in reader.match_paths(&pattern)? {
+                let sensor = meta.measurement_id();
+                let mut sensor_reader = reader.get_sensor_reader(&device, sensor)?;
+                if let (Some(r), Some(filter)) = (sensor_reader.as_mut(), &filter) {
+                    r.set_filter(filter.clone());
+                }
+                for point in SeriesPoints::new(sensor_reader) {
+                    let (time, value) = point?;
+                    let line = json!({
+                        "time": time,
+                        "device": device,
+                        "sensor": sensor,
+                        "value": json_value(value),
+                    });
+                    writeln!(out, "{}", line)?;
+                }
+            }
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// `start <= time < end`, either bound optional.
+pub fn time_filter(start: Option<i64>, end: Option<i64>) -> Option<Filter> {
+    match (start, end) {
+        (Some(start), Some(end)) => Some(Filter::time_between(start, end)),
+        (Some(start), None) => Some(Filter::time_gt_eq(start)),
+        (None, Some(end)) => Some(Filter::time_lt(end)),
+        (None, None) => None,
+    }
+}
+
+/// Non-finite floats, which JSON cannot represent, become `null`.
+pub fn json_value(field: Field) -> Value {
+    match field {
+        Field::Boolean(v) => Value::from(v),
+        Field::Int32(v) => Value::from(v),
+        Field::Int64(v) => Value::from(v),
+        Field::FLOAT(v) => {
+            serde_json::Number::from_f64(v as f64).map_or(Value::Null, Value::Number)
+        }
+        Field::DOUBLE(v) => serde_json::Number::from_f64(v).map_or(Value::Null, Value::Number),
+        Field::TEXT(v) => Value::from(String::from_utf8_lossy(&v).into_owned()),
+    }
+}
@@ -0,0 +1,27 @@
+use std::path::PathBuf;
+
+use tsfile::prelude::*;
+
+use crate::Result;
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// The TsFile whose metadata index to print
+    file: PathBuf,
+    /// Also read and print the timeseries under each measurement leaf entry
+    #[arg(long)]
+    timeseries: bool,
+}
+
+pub fn run(args: &Args) -> Result<()> {
+    // unreadable nodes are reported after the rest of the tree
+    let options = ReaderOptions::new().with_parse_mode(ParseMode::Lenient);
+    let reader = TsFileSearchReader::open(&args.file, options)?;
+    let mut printer = TreePrinter::new().with_timeseries(args.timeseries);
+    reader.visit_index(&mut printer)?;
+    print!("{}", printer.output());
+    for warning in reader.take_warnings() {
+        eprintln!("warning: {}", warning);
+    }
+    Ok(())
+}
@@ -0,0 +1,114 @@
+use std::fmt::Debug;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use tsfile::prelude::*;
+
+use crate::Result;
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// The TsFile to inspect
+    file: PathBuf,
+    /// Also list every chunk with its offset, encoding, compression and statistics
+    #[arg(long)]
+    chunks: bool,
+}
+
+pub fn run(args: &Args) -> Result<()> {
+    let version = head_version(&args.file)?;
+    let reader = TsFileSearchReader::open(&args.file, ReaderOptions::new())?;
+    let devices = reader.devices()?;
+    let summary = reader.summary()?;
+    println!("file: {}", args.file.display());
+    println!("size: {} bytes", std::fs::metadata(&args.file)?.len());
+    println!("version: {}", version);
+    println!("devices: {}", devices.len());
+    println!("points: {}", summary.points());
+    println!("chunk data: {} bytes", summary.data_size());
+    println!("compression ratio: {}", ratio(summary.compression_ratio()));
+
+    let mut device = None;
+    for series in summary.series() {
+        if device != Some(series.device()) {
+            device = Some(series.device());
+            println!();
+            println!("{}", series.device());
+        }
+        println!(
+            "  {} {:?} {} {} chunks: {} pages: {} bytes: {} ratio: {} {}",
+            series.sensor(),
+            series.data_type(),
+            join(series.encodings()),
+            join(series.compressions()),
+            series.chunks(),
+            series.pages(),
+            series.data_size(),
+            ratio(series.compression_ratio()),
+            statistics(series.statistic())
+        );
+        if args.chunks {
+            print_chunks(&reader, series.device(), series.sensor())?;
+        }
+    }
+    Ok(())
+}
+
+fn print_chunks<F: FileReader>(reader: &F, device: &str, sensor: &str) -> Result<()> {
+    let sensor_reader = match reader.get_sensor_reader(device, sensor)? {
+        Some(sensor_reader) => sensor_reader,
+        None => return Ok(()),
+    };
+    for (i, chunk) in sensor_reader.metadata().iter().enumerate() {
+        let chunk_reader = sensor_reader.get_chunk_reader(i)?;
+        if let Some(header) = chunk_reader.header() {
+            println!(
+                "    chunk {} at {}: {} bytes {:?} {:?} {}",
+                i,
+                chunk.offset_chunk_header(),
+                header.data_size(),
+                header.encoding_type(),
+                header.compression_type(),
+                statistics(&chunk.statistic())
+            );
+        }
+    }
+    Ok(())
+}
+
+/// The version byte after the head magic.
+fn head_version(path: &Path) -> Result<u8> {
+    let mut head = [0_u8; 7];
+    File::open(path)?.read_exact(&mut head)?;
+    if &head[..6] != b"TsFile" {
+        return Err(format!("{} does not start with the TsFile magic", path.display()).into());
+    }
+    Ok(head[6])
+}
+
+fn statistics(statistic: &Statistic) -> String {
+    let (start, end) = statistic.time_range();
+    let mut line = format!("points: {} time: [{}, {}]", statistic.count(), start, end);
+    if let Some((min, max)) = statistic.value_range() {
+        line.push_str(&format!(" min: {} max: {}", min, max));
+    }
+    line.push_str(&format!(
+        " first: {} last: {}",
+        statistic.first_value(),
+        statistic.last_value()
+    ));
+    line
+}
+
+fn join<T: Debug>(names: &[T]) -> String {
+    names
+        .iter()
+        .map(|name| format!("{:?}", name))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn ratio(ratio: Option<f64>) -> String {
+    ratio.map_or_else(|| "-".to_string(), |ratio| format!("{:.2}", ratio))
+}
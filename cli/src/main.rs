@@ -0,0 +1,63 @@
+mod convert;
+mod diff;
+mod dump;
+mod index;
+mod info;
+mod query;
+mod repair;
+mod validate;
+
+use std::error::Error;
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand};
+
+type Result<T, E = Box<dyn Error>> = std::result::Result<T, E>;
+
+/// Inspect and export Apache IoTDB TsFiles.
+#[derive(Parser)]
+#[command(name = "tsfile-cli", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print the version, schema, chunks and statistics of a file
+    Info(info::Args),
+    /// Upgrade a version 2 file to version 3, or write each device to Parquet or CSV
+    Convert(convert::Args),
+    /// Write the points of a file's series to stdout
+    Dump(dump::Args),
+    /// Print the metadata index tree of a file with the offsets of its nodes
+    Index(index::Args),
+    /// Compare the schemas, point counts, time ranges and optionally points of two files
+    Diff(diff::Args),
+    /// Print the rows of a device matching a `select ... from ... where ...` query
+    Query(query::Args),
+    /// Write a sealed copy of a crashed file, keeping its complete chunk groups
+    Repair(repair::Args),
+    /// Check a file's structure, pages and statistics, reporting the first corruption
+    Validate(validate::Args),
+}
+
+fn main() -> ExitCode {
+    let result = match Cli::parse().command {
+        Command::Info(args) => info::run(&args),
+        Command::Convert(args) => convert::run(&args),
+        Command::Dump(args) => dump::run(&args),
+        Command::Index(args) => index::run(&args),
+        Command::Diff(args) => diff::run(&args),
+        Command::Query(args) => query::run(&args),
+        Command::Repair(args) => repair::run(&args),
+        Command::Validate(args) => validate::run(&args),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+
+use tsfile::prelude::*;
+
+use crate::Result;
+
+/// Narrowest column, wide enough for millisecond timestamps.
+const MIN_WIDTH: usize = 13;
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// The TsFile to query
+    file: PathBuf,
+    /// e.g. "select s1,s2 from root.sg.d1 where time >= 1637893124311 and s1 > 30"
+    query: String,
+}
+
+pub fn run(args: &Args) -> Result<()> {
+    let query = Query::parse(&args.query)?;
+    let reader = TsFileSearchReader::open(&args.file, ReaderOptions::new())?;
+    let (sensors, rows) = query.rows(&reader)?;
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    let widths: Vec<usize> = sensors.iter().map(|s| s.len().max(MIN_WIDTH)).collect();
+    write!(out, "{:>width$}", "time", width = MIN_WIDTH)?;
+    for (sensor, width) in sensors.iter().zip(&widths) {
+        write!(out, "  {:>width$}", sensor, width = width)?;
+    }
+    writeln!(out)?;
+    let mut count = 0;
+    for row in rows {
+        let (time, values) = row?;
+        write!(out, "{:>width$}", time, width = MIN_WIDTH)?;
+        for (value, width) in values.iter().zip(&widths) {
+            match value {
+                Some(value) => write!(out, "  {:>width$}", value.to_string(), width = width),
+                None => write!(out, "  {:>width$}", "null", width = width),
+            }?;
+        }
+        writeln!(out)?;
+        count += 1;
+    }
+    writeln!(out, "{} rows", count)?;
+    out.flush()?;
+    Ok(())
+}
@@ -0,0 +1,37 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+
+use tsfile::prelude::*;
+
+use crate::Result;
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// The damaged TsFile
+    file: PathBuf,
+    /// Where to write the sealed copy
+    output: PathBuf,
+}
+
+pub fn run(args: &Args) -> Result<()> {
+    if args.output == args.file {
+        return Err("the output must not be the damaged file".into());
+    }
+    let input = File::open(&args.file)?;
+    let output = BufWriter::new(File::create(&args.output)?);
+    let (output, summary) = repair(input, output)?;
+    output
+        .into_inner()
+        .map_err(|e| e.into_error())?
+        .sync_all()?;
+    println!(
+        "kept {} chunk groups with {} chunks in {} bytes, dropped {} bytes",
+        summary.chunk_groups(),
+        summary.chunks(),
+        summary.data_len(),
+        summary.dropped_len()
+    );
+    println!("wrote {}", args.output.display());
+    Ok(())
+}
@@ -0,0 +1,236 @@
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::PathBuf;
+
+use tsfile::prelude::*;
+
+use crate::Result;
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// The TsFile to check
+    file: PathBuf,
+}
+
+/// The first problem found and where it is.
+struct Corruption {
+    offset: u64,
+    message: String,
+}
+
+impl Corruption {
+    fn at(offset: impl TryInto<u64>, message: impl Into<String>) -> Self {
+        Self {
+            offset: offset.try_into().unwrap_or(0),
+            message: message.into(),
+        }
+    }
+}
+
+/// What was checked.
+#[derive(Default)]
+struct Counts {
+    chunks: usize,
+    pages: usize,
+    points: usize,
+}
+
+pub fn run(args: &Args) -> Result<()> {
+    match validate(args) {
+        Ok(counts) => {
+            println!(
+                "ok: {} chunks, {} pages, {} points",
+                counts.chunks, counts.pages, counts.points
+            );
+            Ok(())
+        }
+        Err(Corruption { offset, message }) => {
+            Err(format!("corrupt at offset {}: {}", offset, message).into())
+        }
+    }
+}
+
+fn validate(args: &Args) -> Result<Counts, Corruption> {
+    let open = |e: std::io::Error| Corruption::at(0, format!("unable to open: {}", e));
+    check_tail(&mut File::open(&args.file).map_err(open)?)?;
+
+    // the structure of the data section, independent of the index
+    let mut scanner = TsFileScanner::new(File::open(&args.file).map_err(open)?)
+        .map_err(|e| Corruption::at(0, e.to_string()))?;
+    let mut chunk_offsets = BTreeSet::new();
+    let mut separator = false;
+    loop {
+        match scanner.next() {
+            Some(Ok(ScanItem::Chunk { offset, .. })) => {
+                chunk_offsets.insert(offset);
+            }
+            Some(Ok(ScanItem::Separator { .. })) => separator = true,
+            Some(Ok(_)) => {}
+            Some(Err(e)) => return Err(Corruption::at(scanner.position(), e.to_string())),
+            None => break,
+        }
+    }
+    if !separator {
+        return Err(Corruption::at(
+            scanner.position(),
+            "data section ends without the metadata separator",
+        ));
+    }
+
+    let reader = TsFileSearchReader::open(&args.file, ReaderOptions::new()).map_err(|e| {
+        Corruption::at(
+            scanner.position(),
+            format!("unable to read the metadata index: {}", e),
+        )
+    })?;
+    let mut counts = Counts::default();
+    let index = |e: TsFileError| Corruption::at(e.offset().unwrap_or(0), e.to_string());
+    for device in reader.devices().map_err(index)? {
+        for series in reader.sensor_meta_iter(&device) {
+            let series = series.map_err(index)?;
+            if series.is_time_column() {
+                continue;
+            }
+            let sensor = series.measurement_id();
+            let path = format!("{}.{}", device, sensor);
+            let sensor_reader = match reader.get_sensor_reader(&device, sensor).map_err(index)? {
+                Some(sensor_reader) => sensor_reader,
+                None => continue,
+            };
+            for (i, chunk) in sensor_reader.metadata().iter().enumerate() {
+                let offset = chunk.offset_chunk_header();
+                if !chunk_offsets.contains(&(offset as u64)) {
+                    return Err(Corruption::at(
+                        offset,
+                        format!("{} chunk {} is not at a chunk header", path, i),
+                    ));
+                }
+                let chunk_reader = sensor_reader
+                    .get_chunk_reader(i)
+                    .map_err(|e| Corruption::at(offset, format!("{} chunk {}: {}", path, i, e)))?;
+                let mut decoded = Decoded::default();
+                let page_error = |j, e: &dyn std::fmt::Display| {
+                    Corruption::at(offset, format!("{} chunk {} page {}: {}", path, i, j, e))
+                };
+                for (j, page) in chunk_reader.enumerate() {
+                    let page = page.map_err(|e| page_error(j, &e))?;
+                    let (times, values) = page.batch().map_err(|e| page_error(j, &e))?;
+                    let page_decoded = Decoded::of(&times, &values);
+                    page_decoded
+                        .check(page.header().statistics())
+                        .map_err(|e| page_error(j, &e))?;
+                    decoded.merge(&page_decoded);
+                    counts.pages += 1;
+                }
+                decoded
+                    .check(&chunk.statistic())
+                    .map_err(|e| Corruption::at(offset, format!("{} chunk {}: {}", path, i, e)))?;
+                counts.chunks += 1;
+                counts.points += decoded.count;
+            }
+        }
+    }
+    Ok(counts)
+}
+
+/// Checks that the file ends with the magic string.
+fn check_tail(file: &mut File) -> Result<(), Corruption> {
+    let len = file
+        .metadata()
+        .map_err(|e| Corruption::at(0, e.to_string()))?
+        .len();
+    if len < 6 {
+        return Err(Corruption::at(0, "too short for the tail magic"));
+    }
+    let mut tail = [0_u8; 6];
+    file.seek(SeekFrom::Start(len - 6))
+        .and_then(|_| file.read_exact(&mut tail))
+        .map_err(|e| Corruption::at(len - 6, e.to_string()))?;
+    if &tail != b"TsFile" {
+        return Err(Corruption::at(len - 6, "missing the tail magic"));
+    }
+    Ok(())
+}
+
+/// What the decoded points of a page or chunk add up to.
+#[derive(Default)]
+struct Decoded {
+    count: usize,
+    time_range: Option<(i64, i64)>,
+    value_range: Option<(f64, f64)>,
+}
+
+impl Decoded {
+    fn of(times: &[i64], values: &ColumnBatch) -> Self {
+        let time_range = times
+            .iter()
+            .fold(None, |range, &t| Some(widen(range, t, t)));
+        let value_range = (0..values.len())
+            .filter_map(|i| values.get(i)?.as_f64())
+            .filter(|v| !v.is_nan())
+            .fold(None, |range: Option<(f64, f64)>, v| match range {
+                Some((min, max)) => Some((min.min(v), max.max(v))),
+                None => Some((v, v)),
+            });
+        Self {
+            count: times.len(),
+            time_range,
+            value_range,
+        }
+    }
+
+    fn merge(&mut self, other: &Decoded) {
+        self.count += other.count;
+        if let Some((start, end)) = other.time_range {
+            self.time_range = Some(widen(self.time_range, start, end));
+        }
+        if let Some((min, max)) = other.value_range {
+            self.value_range = Some(match self.value_range {
+                Some((a, b)) => (a.min(min), b.max(max)),
+                None => (min, max),
+            });
+        }
+    }
+
+    /// Compares the points with the statistics recorded for them.
+    fn check(&self, statistic: &Statistic) -> Result<(), String> {
+        if self.count != statistic.count() as usize {
+            return Err(format!(
+                "decoded {} points, statistics record {}",
+                self.count,
+                statistic.count()
+            ));
+        }
+        if let Some(time_range) = self.time_range {
+            if time_range != statistic.time_range() {
+                return Err(format!(
+                    "decoded times {:?}, statistics record {:?}",
+                    time_range,
+                    statistic.time_range()
+                ));
+            }
+        }
+        if let (Some(decoded), Some(min), Some(max)) = (
+            self.value_range,
+            statistic.min_as_f64(),
+            statistic.max_as_f64(),
+        ) {
+            if decoded != (min, max) {
+                return Err(format!(
+                    "decoded values {:?}, statistics record {:?}",
+                    decoded,
+                    (min, max)
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn widen(range: Option<(i64, i64)>, start: i64, end: i64) -> (i64, i64) {
+    match range {
+        Some((a, b)) => (a.min(start), b.max(end)),
+        None => (start, end),
+    }
+}
@@ -0,0 +1,116 @@
+//! Python bindings: `tsfile.TsFile(path)` lists the devices and paths of a file and reads
+//! a series into numpy arrays. Build with `maturin build` from this directory.
+
+use std::fs::File;
+
+use numpy::IntoPyArray;
+use pyo3::exceptions::{PyIOError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyList;
+
+use tsfile::prelude::*;
+
+#[pyclass(name = "TsFile")]
+struct PyTsFile {
+    reader: TsFileSearchReader<File>,
+}
+
+#[pymethods]
+impl PyTsFile {
+    #[new]
+    fn open(path: &str) -> PyResult<Self> {
+        let reader =
+            TsFileSearchReader::try_from(path).map_err(|e| PyIOError::new_err(e.to_string()))?;
+        Ok(Self { reader })
+    }
+
+    /// The devices of the file.
+    fn devices(&self) -> PyResult<Vec<String>> {
+        self.reader
+            .devices()
+            .map_err(|e| PyIOError::new_err(e.to_string()))
+    }
+
+    /// The `device.sensor` paths matching an IoTDB path pattern, all paths by default.
+    #[pyo3(signature = (pattern = "**"))]
+    fn paths(&self, pattern: &str) -> PyResult<Vec<String>> {
+        let paths = self
+            .reader
+            .match_paths(&PathPattern::new(pattern))
+            .map_err(|e| PyIOError::new_err(e.to_string()))?;
+        Ok(paths
+            .into_iter()
+            .map(|(device, meta)| format!("{}.{}", device, meta.measurement_id()))
+            .collect())
+    }
+
+    /// The `(times, values)` of a series with `start <= time < end`, as numpy arrays; text
+    /// values are returned as a list of str.
+    #[pyo3(signature = (device, sensor, start = None, end = None))]
+    fn read_series<'py>(
+        &self,
+        py: Python<'py>,
+        device: &str,
+        sensor: &str,
+        start: Option<i64>,
+        end: Option<i64>,
+    ) -> PyResult<(Bound<'py, PyAny>, Bound<'py, PyAny>)> {
+        let mut sensor_reader = self
+            .reader
+            .get_sensor_reader(device, sensor)
+            .map_err(|e| PyIOError::new_err(e.to_string()))?
+            .ok_or_else(|| PyValueError::new_err(format!("no series {}.{}", device, sensor)))?;
+        let filter = match (start, end) {
+            (Some(start), Some(end)) => Some(Filter::time_between(start, end)),
+            (Some(start), None) => Some(Filter::time_gt_eq(start)),
+            (None, Some(end)) => Some(Filter::time_lt(end)),
+            (None, None) => None,
+        };
+        if let Some(filter) = filter {
+            sensor_reader.set_filter(filter);
+        }
+
+        let (times, values) = read_all(sensor_reader.as_ref()).map_err(PyIOError::new_err)?;
+        let values = match values {
+            ColumnBatch::Boolean(v) => v.into_pyarray(py).into_any(),
+            ColumnBatch::Int32(v) => v.into_pyarray(py).into_any(),
+            ColumnBatch::Int64(v) => v.into_pyarray(py).into_any(),
+            ColumnBatch::FLOAT(v) => v.into_pyarray(py).into_any(),
+            ColumnBatch::DOUBLE(v) => v.into_pyarray(py).into_any(),
+            ColumnBatch::TEXT(v) => PyList::new(
+                py,
+                v.iter()
+                    .map(|text| String::from_utf8_lossy(text).into_owned()),
+            )?
+            .into_any(),
+        };
+        Ok((times.into_pyarray(py).into_any(), values))
+    }
+}
+
+/// Decodes every page of the series into one batch.
+fn read_all(reader: &dyn SensorReader) -> Result<(Vec<i64>, ColumnBatch), String> {
+    let mut times = Vec::new();
+    let mut values = match reader.metadata().first() {
+        Some(chunk) => ColumnBatch::new(chunk.ts_data_type()),
+        None => ColumnBatch::DOUBLE(Vec::new()),
+    };
+    for i in 0..reader.number_of_chunks() {
+        for page in reader.get_chunk_reader(i).map_err(|e| e.to_string())? {
+            let (page_times, page_values) = page
+                .and_then(|page| page.batch())
+                .map_err(|e| e.to_string())?;
+            if !values.append(page_values) {
+                return Err(format!("chunk {} holds values of another type", i));
+            }
+            times.extend(page_times);
+        }
+    }
+    Ok((times, values))
+}
+
+#[pymodule]
+#[pyo3(name = "tsfile")]
+fn tsfile_python(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyTsFile>()
+}
@@ -1,24 +1,38 @@
-use std::borrow::BorrowMut;
-use std::io::{Cursor, Read};
-use std::sync::Arc;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io::{self, Cursor};
+use std::iter;
+use std::ops::Range;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::mpsc;
+use std::sync::mpsc::{Receiver, Sender};
+use std::sync::{Arc, Mutex};
+#[cfg(not(target_arch = "wasm32"))]
+use std::thread;
 
-use crate::encoding::decoder::{Decoder, Field, IntPlainDecoder, LongBinaryDecoder};
-use byteorder::ReadBytesExt;
-use varint::VarintRead;
+use crate::encoding::decoder::{value_decoder, ColumnBatch, Decoder, Field};
+use byteorder::{BigEndian, ReadBytesExt};
+use bytes::Bytes;
 
 use crate::chunk;
 use crate::encoding::decoder;
-use crate::file::compress::Snappy;
+use crate::error::{TsFileError, Warnings};
+use crate::file::compress::{decompressor, Decompressor};
 use crate::file::metadata::{ChunkMetadata, TSDataType, TimeseriesMetadata};
-use crate::file::reader::{ChunkReader, PageReader, SectionReader, SensorReader};
+use crate::file::mods::Deletion;
+use crate::file::options::{TimeOrder, DEFAULT_MAX_ALLOCATION};
+use crate::file::reader::{ChunkReader, PageReader, PageResult, SectionReader, SensorReader};
 use crate::file::statistics::{
     BinaryStatistics, BooleanStatistics, DoubleStatistics, FloatStatistics, IntegerStatistics,
-    LongStatistics, Statistic,
+    LongStatistics, Statistic, TimeStatistics,
 };
 use crate::file::{compress, statistics};
+use crate::query::filter::Filter;
+use crate::query::merge::DuplicatePolicy;
 use crate::utils::cursor;
 use crate::utils::cursor::VarIntReader;
-use snafu::{ensure, ResultExt, Snafu};
+use crate::utils::pool::BufferPool;
+use snafu::{ensure, OptionExt, ResultExt, Snafu};
 
 #[derive(Debug, Snafu)]
 pub enum Error {
@@ -39,14 +53,86 @@ pub enum Error {
         s_type: String,
         source: statistics::Error,
     },
+    #[snafu(display("Time column of {} bytes exceeds page of {} bytes", time_len, page_len))]
+    TruncatedPage { time_len: usize, page_len: usize },
+    #[snafu(display("Null bitmap of {} rows exceeds page of {} bytes", rows, page_len))]
+    TruncatedBitmap { rows: usize, page_len: usize },
+    #[snafu(display("Page {} of the value chunk has no page in the time chunk", page))]
+    UnpairedValuePage { page: usize },
+    #[snafu(display("The {:?} time decoder yields values other than INT64", encoding))]
+    NonInt64Times { encoding: TSEncoding },
+    #[snafu(display(
+        "Chunk data of {} bytes runs past the next chunk, {} bytes from the chunk start",
+        data_size,
+        max_size
+    ))]
+    ChunkPastBound { data_size: u32, max_size: u64 },
+    #[snafu(display("{} of {} bytes exceeds the limit of {} bytes", field, len, limit))]
+    LengthTooLarge {
+        field: String,
+        len: u64,
+        limit: usize,
+    },
+    #[snafu(display(
+        "Page {} of the chunk at offset {} is corrupt: {}",
+        page,
+        offset,
+        detail
+    ))]
+    CorruptPage {
+        offset: u64,
+        page: usize,
+        detail: String,
+    },
 }
 
 type Result<T, E = Error> = std::result::Result<T, E>;
 
+/// A chunk to read ahead: its index, header offset and size.
+type ChunkRequest = (usize, i64, Option<u64>);
+
+/// A chunk read ahead, tagged with its index.
+type FetchedChunk = (usize, Result<(ChunkHeader, Vec<u8>)>);
+
+/// The chunks a sensor reader reads ahead, on one thread started with the first of them
+/// that ends once the reader is dropped.
+#[derive(Debug, Default)]
+struct Prefetcher {
+    /// The thread's queue of chunks to read and the chunks it read.
+    worker: Option<(Sender<ChunkRequest>, Receiver<FetchedChunk>)>,
+    /// The chunk asked for last, whose result has not been taken.
+    pending: Option<usize>,
+}
+
+impl Prefetcher {
+    /// The i-th chunk if it is the one read ahead, waiting for it if it is still being
+    /// read. Chunks read ahead but never asked for are dropped on the way.
+    fn take(&mut self, i: usize) -> Option<Result<(ChunkHeader, Vec<u8>)>> {
+        if self.pending.take()? != i {
+            return None;
+        }
+        let (_, chunks) = self.worker.as_ref()?;
+        chunks.iter().find(|(j, _)| *j == i).map(|(_, chunk)| chunk)
+    }
+}
+
 #[derive(Debug)]
 pub struct TsFileSensorReader<R: SectionReader> {
     reader: Arc<R>,
     meta: Vec<ChunkMetadata>,
+    filter: Option<Arc<Filter>>,
+    read_ahead: bool,
+    prefetch: Mutex<Prefetcher>,
+    pool: Option<Arc<BufferPool>>,
+    max_allocation: usize,
+    coalesce_limit: usize,
+    coalesced: Mutex<HashMap<usize, (ChunkHeader, Vec<u8>)>>,
+    warnings: Warnings,
+    deletions: Vec<Deletion>,
+    verify_pages: bool,
+    time_encoding: TSEncoding,
+    time_order: TimeOrder,
+    time_column: Vec<ChunkMetadata>,
 }
 
 impl<R: SectionReader> TsFileSensorReader<R> {
@@ -59,11 +145,92 @@ impl<R: SectionReader> TsFileSensorReader<R> {
         Self {
             reader,
             meta: chunks,
+            filter: None,
+            read_ahead: false,
+            prefetch: Mutex::new(Prefetcher::default()),
+            pool: None,
+            max_allocation: DEFAULT_MAX_ALLOCATION,
+            coalesce_limit: 0,
+            coalesced: Mutex::new(HashMap::new()),
+            warnings: Warnings::default(),
+            deletions: Vec::new(),
+            verify_pages: false,
+            time_encoding: TSEncoding::Ts2diff,
+            time_order: TimeOrder::AsStored,
+            time_column: Vec::new(),
         }
     }
+
+    /// Reads consecutive chunks spanning at most `limit` bytes in a single request, keeping
+    /// the ones not asked for yet until they are. `0` reads every chunk on its own.
+    pub fn with_coalesce_limit(mut self, limit: usize) -> Self {
+        self.coalesce_limit = limit;
+        self
+    }
+
+    /// Fails reads of chunks or pages larger than `max_allocation` bytes.
+    pub fn with_max_allocation(mut self, max_allocation: usize) -> Self {
+        self.max_allocation = max_allocation;
+        self
+    }
+
+    /// Reads chunks into buffers taken from `pool`. Header reads that turn out not to cover
+    /// the whole chunk are returned to it.
+    pub fn with_buffer_pool(mut self, pool: Option<Arc<BufferPool>>) -> Self {
+        self.pool = pool;
+        self
+    }
+
+    /// Reads chunk i + 1 in the background whenever chunk i is read.
+    pub fn with_read_ahead(mut self, read_ahead: bool) -> Self {
+        self.read_ahead = read_ahead;
+        self
+    }
+
+    /// Leaves out the points removed by `deletions`, which must all apply to this series.
+    pub fn with_deletions(mut self, deletions: Vec<Deletion>) -> Self {
+        self.deletions = deletions;
+        self
+    }
+
+    /// Checks every page decoded against its header, see `DefaultChunkReader::with_verification`.
+    pub fn with_verify_pages(mut self, verify_pages: bool) -> Self {
+        self.verify_pages = verify_pages;
+        self
+    }
+
+    /// Decodes the time columns of the pages as `encoding`, see
+    /// `DefaultChunkReader::with_time_encoding`.
+    pub fn with_time_encoding(mut self, encoding: TSEncoding) -> Self {
+        self.time_encoding = encoding;
+        self
+    }
+
+    /// Orders the points of each page as `order` asks, see `DefaultChunkReader::with_time_order`.
+    pub fn with_time_order(mut self, order: TimeOrder) -> Self {
+        self.time_order = order;
+        self
+    }
+
+    /// The chunks of the time column of an aligned device, whose value chunks this reader
+    /// reads. Each value chunk takes its timestamps from the time chunk last before it.
+    pub fn with_time_column(mut self, time_column: Vec<TimeseriesMetadata>) -> Self {
+        self.time_column = time_column
+            .into_iter()
+            .flat_map(|x| x.chunk_metadata_list())
+            .collect();
+        self.time_column.sort_by_key(|c| c.offset_chunk_header());
+        self
+    }
+
+    /// Skips unreadable chunks and pages, recording their errors, if `warnings` is lenient.
+    pub(crate) fn with_warnings(mut self, warnings: Warnings) -> Self {
+        self.warnings = warnings;
+        self
+    }
 }
 
-impl<R: 'static + SectionReader> SensorReader for TsFileSensorReader<R> {
+impl<R: 'static + SectionReader + Send + Sync> SensorReader for TsFileSensorReader<R> {
     fn metadata(&self) -> &Vec<ChunkMetadata> {
         &self.meta
     }
@@ -75,42 +242,386 @@ impl<R: 'static + SectionReader> SensorReader for TsFileSensorReader<R> {
     fn get_chunk_reader(
         &self,
         i: usize,
-    ) -> std::result::Result<Box<dyn ChunkReader<Item = Box<dyn PageReader>>>, chunk::reader::Error>
-    {
-        let chunk_meta = &self.meta.get(i);
-        ensure!(
-            chunk_meta.is_some(),
-            GetChunkReaderI {
-                i,
-                len: self.meta.len()
+    ) -> std::result::Result<Box<dyn ChunkReader<Item = PageResult>>, chunk::reader::Error> {
+        let filter = self.chunk_filter(i);
+        if let (Some(filter), Some(chunk)) = (&filter, self.meta.get(i)) {
+            if !filter.may_satisfy(&chunk.statistic()) || self.is_deleted(chunk) {
+                return Ok(Box::new(iter::empty()));
             }
-        );
-        let chunk = chunk_meta.unwrap();
-        let offset = chunk.offset_chunk_header();
-        //TODO 多读取了一部分数据
-        let mut header_reader = self
-            .reader
-            .get_cursor(offset as u64, 1 * 1024)
-            .context(GetCursor {})?;
-        let chunk_header = ChunkHeader::try_from(header_reader.borrow_mut())?;
+        }
+        let pages = self.read_chunk(i).and_then(|(chunk_header, data)| {
+            let pages = DefaultChunkReader::new(
+                Cursor::new(data),
+                chunk_header,
+                self.meta[i].statistic(),
+                filter,
+                self.max_allocation,
+            )?
+            .with_time_encoding(&self.time_encoding)
+            .with_time_order(self.time_order);
+            let pages = match PageLayout::of(pages.header.chunk_type) {
+                PageLayout::Value => match self.read_time_chunk(i)? {
+                    Some(time) => pages.with_time_column(time),
+                    None => pages,
+                },
+                _ => pages,
+            };
+            Ok(match self.verify_pages {
+                true => pages.with_verification(self.meta[i].offset_chunk_header() as u64),
+                false => pages,
+            })
+        });
+        if !self.warnings.is_lenient() {
+            return Ok(Box::new(pages?));
+        }
+        let offset = self
+            .meta
+            .get(i)
+            .map_or(0, |c| c.offset_chunk_header() as u64);
+        match pages {
+            Ok(mut pages) => {
+                if let Some(e) = pages.error.take() {
+                    self.warnings.push(TsFileError::from(e).at_offset(offset));
+                }
+                Ok(Box::new(pages))
+            }
+            Err(e) => {
+                self.warnings.push(TsFileError::from(e).at_offset(offset));
+                Ok(Box::new(iter::empty()))
+            }
+        }
+    }
+
+    fn set_filter(&mut self, filter: Filter) {
+        self.filter = Some(Arc::new(filter));
+    }
 
-        let first_page = header_reader.position() + offset as u64;
+    fn get_raw_chunk(&self, i: usize) -> Result<(ChunkHeader, Vec<u8>)> {
+        self.read_chunk(i)
+    }
+}
 
-        Ok(Box::new(DefaultChunkReader::new(
-            self.reader
-                .get_cursor(first_page, chunk_header.data_size as usize)
-                .context(GetCursor {})?,
-            chunk_header,
+impl<R: 'static + SectionReader + Send + Sync> TsFileSensorReader<R> {
+    /// Reads the time chunk the i-th chunk, an aligned value chunk, takes its timestamps
+    /// from. Without one its pages fail to decode.
+    fn read_time_chunk(&self, i: usize) -> Result<Option<DefaultChunkReader>> {
+        let offset = self.meta[i].offset_chunk_header();
+        let before = self
+            .time_column
+            .partition_point(|c| c.offset_chunk_header() < offset);
+        let chunk = match before.checked_sub(1).map(|j| &self.time_column[j]) {
+            Some(chunk) => chunk,
+            None => return Ok(None),
+        };
+        let (header, data) = fetch_chunk(
+            self.reader.as_ref(),
+            chunk.offset_chunk_header(),
+            chunk.max_size(),
+            self.pool.as_deref(),
+            self.max_allocation,
+        )?;
+        DefaultChunkReader::new(
+            Cursor::new(data),
+            header,
             chunk.statistic(),
-        )?))
+            None,
+            self.max_allocation,
+        )
+        .map(Some)
+    }
+
+    /// Reads the header and the still compressed page bytes of the i-th chunk.
+    fn read_chunk(&self, i: usize) -> Result<(ChunkHeader, Vec<u8>)> {
+        let chunk = self.meta.get(i).context(GetChunkReaderI {
+            i,
+            len: self.meta.len(),
+        })?;
+
+        let cached = self.coalesced.lock().unwrap().remove(&i);
+        let mut prefetch = self.prefetch.lock().unwrap();
+        let prefetched = prefetch.take(i);
+        if self.read_ahead {
+            self.prefetch_chunk(&mut prefetch, i + 1);
+        }
+        drop(prefetch);
+
+        if let Some(chunk) = cached {
+            return Ok(chunk);
+        }
+        match prefetched {
+            Some(result) => result,
+            None if self.coalesce_limit > 0 => self.fetch_coalesced(i),
+            None => fetch_chunk(
+                self.reader.as_ref(),
+                chunk.offset_chunk_header(),
+                chunk.max_size(),
+                self.pool.as_deref(),
+                self.max_allocation,
+            ),
+        }
     }
+
+    /// Queues the i-th chunk to be read on the prefetch thread, unless the filter skips it.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn prefetch_chunk(&self, prefetch: &mut Prefetcher, i: usize) {
+        let Some(chunk) = self.meta.get(i) else {
+            return;
+        };
+        if self.coalesced.lock().unwrap().contains_key(&i) {
+            return;
+        }
+        if let Some(filter) = &self.filter {
+            if !filter.may_satisfy(&chunk.statistic()) {
+                return;
+            }
+        }
+        let (requests, _) = prefetch.worker.get_or_insert_with(|| {
+            let (requests, queue) = mpsc::channel::<ChunkRequest>();
+            let (done, chunks) = mpsc::channel();
+            let (reader, pool) = (self.reader.clone(), self.pool.clone());
+            let max_allocation = self.max_allocation;
+            thread::spawn(move || {
+                for (i, offset, max_size) in queue {
+                    let chunk = fetch_chunk(
+                        reader.as_ref(),
+                        offset,
+                        max_size,
+                        pool.as_deref(),
+                        max_allocation,
+                    );
+                    if done.send((i, chunk)).is_err() {
+                        break;
+                    }
+                }
+            });
+            (requests, chunks)
+        });
+        match requests.send((i, chunk.offset_chunk_header(), chunk.max_size())) {
+            Ok(()) => prefetch.pending = Some(i),
+            // the thread is gone; the next chunk read ahead starts another
+            Err(_) => prefetch.worker = None,
+        }
+    }
+
+    /// Nothing is read ahead without threads: every chunk is read when asked for.
+    #[cfg(target_arch = "wasm32")]
+    fn prefetch_chunk(&self, _prefetch: &mut Prefetcher, _i: usize) {}
 }
 
+impl<R: SectionReader> TsFileSensorReader<R> {
+    /// The filter of the i-th chunk: the reader's filter, also rejecting the points deleted
+    /// from the chunk.
+    fn chunk_filter(&self, i: usize) -> Option<Arc<Filter>> {
+        let offset = match self.meta.get(i) {
+            Some(chunk) => chunk.offset_chunk_header(),
+            None => return self.filter.clone(),
+        };
+        let kept = self
+            .deletions
+            .iter()
+            .filter(|d| d.covers_chunk(offset))
+            .map(|d| !Filter::time_gt_eq(d.start()).and(Filter::time_lt_eq(d.end())))
+            .reduce(Filter::and);
+        match (kept, &self.filter) {
+            (None, filter) => filter.clone(),
+            (Some(kept), None) => Some(Arc::new(kept)),
+            (Some(kept), Some(filter)) => Some(Arc::new(filter.as_ref().clone().and(kept))),
+        }
+    }
+
+    /// Whether a single deletion removes every point of `chunk`.
+    fn is_deleted(&self, chunk: &ChunkMetadata) -> bool {
+        let statistic = chunk.statistic();
+        self.deletions.iter().any(|d| {
+            d.covers_chunk(chunk.offset_chunk_header())
+                && d.start() <= statistic.start_time()
+                && statistic.end_time() <= d.end()
+        })
+    }
+
+    /// Reads chunk i and the chunks after it that end within `coalesce_limit` bytes of its
+    /// start in one request. The following chunks are kept for the next `read_chunk` calls.
+    fn fetch_coalesced(&self, i: usize) -> Result<(ChunkHeader, Vec<u8>)> {
+        let start = self.meta[i].offset_chunk_header();
+        let mut spans = Vec::new();
+        for chunk in &self.meta[i..] {
+            let from = chunk.offset_chunk_header() - start;
+            let to = match chunk.max_size() {
+                Some(size) if from >= 0 => from as u64 + size,
+                _ => break,
+            };
+            let skipped = match &self.filter {
+                Some(filter) => !spans.is_empty() && !filter.may_satisfy(&chunk.statistic()),
+                None => false,
+            };
+            if to > self.coalesce_limit as u64 || skipped {
+                break;
+            }
+            spans.push(from as usize..to as usize);
+        }
+        let fetch_one = || {
+            fetch_chunk(
+                self.reader.as_ref(),
+                start,
+                self.meta[i].max_size(),
+                self.pool.as_deref(),
+                self.max_allocation,
+            )
+        };
+        let end = match spans.last() {
+            Some(last) if spans.len() > 1 => last.end,
+            _ => return fetch_one(),
+        };
+
+        let mut buf = Vec::new();
+        self.reader
+            .read_into(start as u64, end, &mut buf)
+            .context(GetCursor {})?;
+        trace_event!(
+            offset = start,
+            bytes = end,
+            chunks = spans.len(),
+            "read chunks"
+        );
+        let mut first = None;
+        let mut coalesced = self.coalesced.lock().unwrap();
+        for (k, span) in spans.into_iter().enumerate() {
+            let mut data = buf[span].to_vec();
+            let (header, _, complete) = split_chunk(&mut data)?;
+            match (k, complete) {
+                (0, true) => first = Some((header, data)),
+                (_, true) => {
+                    coalesced.insert(i + k, (header, data));
+                }
+                // a chunk larger than its size bound is read on its own
+                (_, false) => {}
+            }
+        }
+        drop(coalesced);
+        match first {
+            Some(chunk) => Ok(chunk),
+            None => fetch_one(),
+        }
+    }
+}
+
+/// Reads the chunk whose header starts at `offset`, in one request when its size bound
+/// is known. Buffers are taken from `pool` if given.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip(reader, pool, max_allocation))
+)]
+fn fetch_chunk<R: SectionReader>(
+    reader: &R,
+    offset: i64,
+    max_size: Option<u64>,
+    pool: Option<&BufferPool>,
+    max_allocation: usize,
+) -> Result<(ChunkHeader, Vec<u8>)> {
+    let take = || pool.map_or_else(Vec::new, |p| p.take());
+    let mut buf = take();
+    reader
+        .read_into(
+            offset as u64,
+            first_read_len(offset, max_size, reader.len(), max_allocation),
+            &mut buf,
+        )
+        .context(GetCursor {})?;
+    trace_event!(bytes = buf.len(), "read chunk");
+    let (chunk_header, header_len, complete) = split_chunk(&mut buf)?;
+    if complete {
+        return Ok((chunk_header, buf));
+    }
+    if let Some(max_size) = max_size.filter(|&size| size <= max_allocation as u64) {
+        return ChunkPastBound {
+            data_size: chunk_header.data_size,
+            max_size,
+        }
+        .fail();
+    }
+    check_len("chunk data", chunk_header.data_size as u64, max_allocation)?;
+    let mut data = take();
+    reader
+        .read_into(
+            offset as u64 + header_len,
+            chunk_header.data_size as usize,
+            &mut data,
+        )
+        .context(GetCursor {})?;
+    trace_event!(bytes = data.len(), "read chunk data");
+    if let Some(pool) = pool {
+        pool.give(buf);
+    }
+    Ok((chunk_header, data))
+}
+
+/// Bytes read to parse the header of a chunk without a size bound, which is at most a few
+/// hundred bytes long.
+const HEADER_WINDOW: u64 = 1024;
+
+pub(crate) const CHUNK_HEADER: u8 = 1;
+pub(crate) const ONLY_ONE_PAGE_CHUNK_HEADER: u8 = 5;
+/// Bits of the chunk marker set for the time (0x80) and value (0x40) columns of aligned
+/// devices.
+pub(crate) const ALIGNED_COLUMN_MASK: u8 = 0xc0;
+
+/// The number of bytes to read at the start of a chunk: up to its size bound, which holds
+/// the whole chunk, unless the bound is unknown or over `max_allocation`, when a window
+/// holding the header is read first. Never reads past the file end.
+pub(crate) fn first_read_len(
+    offset: i64,
+    max_size: Option<u64>,
+    file_len: u64,
+    max_allocation: usize,
+) -> usize {
+    let remaining = file_len.saturating_sub(offset as u64);
+    let len = match max_size {
+        Some(size) if size <= max_allocation as u64 => size,
+        _ => HEADER_WINDOW,
+    };
+    len.min(remaining) as usize
+}
+
+/// Parses the chunk header at the start of `buf` and returns it with its length. When
+/// `buf` holds the whole chunk it is cut down to the chunk data in place and `true` is
+/// returned as well.
+pub(crate) fn split_chunk(buf: &mut Vec<u8>) -> Result<(ChunkHeader, u64, bool)> {
+    let mut cursor = Cursor::new(std::mem::take(buf));
+    let header = ChunkHeader::try_from(&mut cursor)?;
+    let header_len = cursor.position();
+    let end = header_len as usize + header.data_size as usize;
+    *buf = cursor.into_inner();
+    let complete = end <= buf.len();
+    if complete {
+        buf.truncate(end);
+        buf.drain(..header_len as usize);
+    }
+    Ok((header, header_len, complete))
+}
+
+/// Fails if `len`, a size read from the file, exceeds `limit`.
+pub(crate) fn check_len(field: &str, len: u64, limit: usize) -> Result<()> {
+    ensure!(len <= limit as u64, LengthTooLarge { field, len, limit });
+    Ok(())
+}
+
+/// The range of the next `len` bytes of `cursor`, which is moved past them.
+fn take_range(cursor: &mut Cursor<Vec<u8>>, len: u32) -> Result<Range<usize>> {
+    let start = cursor.position() as usize;
+    let end = start + len as usize;
+    if end > cursor.get_ref().len() {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof)).context(ReadCursorData);
+    }
+    cursor.set_position(end as u64);
+    Ok(start..end)
+}
+
+/// Splits a chunk into its pages. Pages are slices of the chunk buffer, so nothing is
+/// copied until a page is decompressed.
 pub struct DefaultChunkReader {
-    cursor: Cursor<Vec<u8>>,
     header: ChunkHeader,
-    pages: Vec<Box<dyn PageReader>>,
-    statistic: Arc<Statistic>,
+    pages: Vec<DefaultPageReader>,
+    error: Option<Error>,
 }
 
 impl DefaultChunkReader {
@@ -118,120 +629,211 @@ impl DefaultChunkReader {
         mut cursor: Cursor<Vec<u8>>,
         header: ChunkHeader,
         statistic: Arc<Statistic>,
+        filter: Option<Arc<Filter>>,
+        max_allocation: usize,
     ) -> Result<Self> {
-        let mut pages: Vec<Box<dyn PageReader>> = Vec::new();
-        while cursor.position() < header.data_size as u64 {
-            //pages
-            match header.chunk_type {
-                //chunk only have one page
-                5 => {
-                    let uncompressed_size =
-                        cursor.read_unsigned_varint_32().context(ReadCursorData)?;
-                    let compressed_size =
-                        cursor.read_unsigned_varint_32().context(ReadCursorData)?;
-
-                    let mut data = vec![0; compressed_size as usize];
-                    cursor.read_exact(&mut data).context(ReadCursorData)?;
-                    pages.push(Box::new(DefaultPageReader {
-                        header: PageHeader::new(
-                            uncompressed_size,
-                            compressed_size,
-                            statistic.clone(),
-                        ),
-                        value_decoder: match header.data_type {
-                            TSDataType::Int32 => Box::new(IntPlainDecoder::new()),
-                            _ => Box::new(IntPlainDecoder::new()),
-                        },
-                        data: Cursor::new(data),
-                    }));
-                }
-                _ => {
-                    let uncompressed_size =
-                        cursor.read_unsigned_varint_32().context(ReadCursorData)?;
-                    let compressed_size =
-                        cursor.read_unsigned_varint_32().context(ReadCursorData)?;
-                    let page_statistic = Arc::new(match *statistic {
-                        Statistic::Boolean(_) => Statistic::Boolean(
-                            BooleanStatistics::try_from(cursor.borrow_mut()).context(
-                                ReadStatistics {
-                                    s_type: "boolean".to_string(),
-                                },
-                            )?,
-                        ),
-                        Statistic::Int32(_) => Statistic::Int32(
-                            IntegerStatistics::try_from(cursor.borrow_mut()).context(
-                                ReadStatistics {
-                                    s_type: "Int32".to_string(),
-                                },
-                            )?,
-                        ),
-                        Statistic::Int64(_) => Statistic::Int64(
-                            LongStatistics::try_from(cursor.borrow_mut()).context(
-                                ReadStatistics {
-                                    s_type: "Int64".to_string(),
-                                },
-                            )?,
-                        ),
-                        Statistic::FLOAT(_) => Statistic::FLOAT(
-                            FloatStatistics::try_from(cursor.borrow_mut()).context(
-                                ReadStatistics {
-                                    s_type: "FLOAT".to_string(),
-                                },
-                            )?,
-                        ),
-                        Statistic::DOUBLE(_) => Statistic::DOUBLE(
-                            DoubleStatistics::try_from(cursor.borrow_mut()).context(
-                                ReadStatistics {
-                                    s_type: "DOUBLE".to_string(),
-                                },
-                            )?,
-                        ),
-                        Statistic::TEXT(_) => Statistic::TEXT(
-                            BinaryStatistics::try_from(cursor.borrow_mut()).context(
-                                ReadStatistics {
-                                    s_type: "TEXT".to_string(),
-                                },
-                            )?,
-                        ),
-                    });
-
-                    let mut data = vec![0; compressed_size as usize];
-                    cursor.read_exact(&mut data).context(ReadCursorData)?;
-
-                    pages.push(Box::new(DefaultPageReader {
-                        header: PageHeader::new(uncompressed_size, compressed_size, page_statistic),
-                        value_decoder: match header.data_type {
-                            TSDataType::Int32 => Box::new(IntPlainDecoder::new()),
-                            _ => Box::new(IntPlainDecoder::new()),
-                        },
-                        data: Cursor::new(data),
-                    }));
-                }
-            }
-        }
+        let mut pages = Vec::new();
+        let decompressor: Arc<dyn Decompressor> = decompressor(&header.compression_type)
+            .context(DecompressChunkData)?
+            .into();
+        // pages read before a corrupt page header are still returned
+        let error = read_page_headers(
+            &mut cursor,
+            &header,
+            &statistic,
+            &filter,
+            max_allocation,
+            &mut pages,
+        )
+        .err();
+
+        let chunk = Bytes::from(cursor.into_inner());
+        let layout = PageLayout::of(header.chunk_type);
+        // time chunks record the encoding of their timestamps
+        let time_encoding = match layout {
+            PageLayout::Time => header.encoding_type,
+            _ => TSEncoding::Ts2diff,
+        };
+        // kept last page first, so `next` pops them in file order
+        let pages = pages
+            .into_iter()
+            .rev()
+            .map(|(page_header, range, index)| DefaultPageReader {
+                header: page_header,
+                time_decoder: value_decoder(&time_encoding, &TSDataType::Int64),
+                time_encoding,
+                value_decoder: value_decoder(&header.encoding_type, &header.data_type),
+                decompressor: decompressor.clone(),
+                data: chunk.slice(range),
+                filter: filter.clone(),
+                index,
+                verify_at: None,
+                order: TimeOrder::AsStored,
+                layout,
+                time_page: None,
+            })
+            .collect();
 
         Ok(Self {
-            cursor,
             header,
             pages,
-            statistic,
+            error,
         })
     }
+
+    /// Decodes the time columns of the pages as `encoding` rather than TS_2DIFF. Pages do not
+    /// record it: IoTDB writes them with its configured time encoder. The time chunks of
+    /// aligned devices, which record it, keep theirs.
+    pub fn with_time_encoding(mut self, encoding: &TSEncoding) -> Self {
+        for page in &mut self.pages {
+            if page.layout == PageLayout::Series {
+                page.time_decoder = value_decoder(encoding, &TSDataType::Int64);
+                page.time_encoding = *encoding;
+            }
+        }
+        self
+    }
+
+    /// Pairs the pages of an aligned value chunk with the pages of `time`, the time chunk
+    /// of its chunk group: the n-th page of each holds the same rows. A value page without
+    /// a time page fails to decode.
+    pub fn with_time_column(mut self, time: DefaultChunkReader) -> Self {
+        let mut times: Vec<_> = time.pages.into_iter().rev().map(Some).collect();
+        for page in &mut self.pages {
+            page.time_page = times
+                .get_mut(page.index)
+                .and_then(Option::take)
+                .map(Box::new);
+        }
+        if self.error.is_none() {
+            self.error = time.error;
+        }
+        self
+    }
+
+    /// Sorts the points of each page by time as it is decoded, stably, keeping one point
+    /// per timestamp. Verification, if on, checks the pages as stored.
+    pub fn with_time_order(mut self, order: TimeOrder) -> Self {
+        for page in &mut self.pages {
+            page.order = order;
+        }
+        self
+    }
+
+    /// Checks each page as it is decoded: its decompressed size against the page header,
+    /// and its timestamps against the page statistics, which must count them and bound
+    /// them, in increasing order. Files carry no page checksums, so this is what catches
+    /// corrupt data that still decodes. Failures name the page and `offset`, the offset of
+    /// the chunk header.
+    pub fn with_verification(mut self, offset: u64) -> Self {
+        for page in &mut self.pages {
+            page.verify_at = Some(offset);
+        }
+        self
+    }
+}
+
+/// Reads the page headers of a chunk, with the range of each page's data.
+fn read_page_headers(
+    cursor: &mut Cursor<Vec<u8>>,
+    header: &ChunkHeader,
+    statistic: &Arc<Statistic>,
+    filter: &Option<Arc<Filter>>,
+    max_allocation: usize,
+    pages: &mut Vec<(PageHeader, Range<usize>, usize)>,
+) -> Result<()> {
+    let mut next_index = 0;
+    while cursor.position() < header.data_size as u64 {
+        let index = next_index;
+        next_index += 1;
+        let uncompressed_size = cursor.read_unsigned_varint_32().context(ReadCursorData)?;
+        let compressed_size = cursor.read_unsigned_varint_32().context(ReadCursorData)?;
+        check_len("page", uncompressed_size as u64, max_allocation)?;
+        // IoTDB writes a value page without any value as two zero sizes, with no
+        // statistics; all its rows are null, so it yields no points
+        if uncompressed_size == 0 && PageLayout::of(header.chunk_type) == PageLayout::Value {
+            take_range(cursor, compressed_size)?;
+            continue;
+        }
+        // the single page of a one-page chunk shares the chunk's statistics
+        let page_statistic = match header.has_page_statistics() {
+            false => statistic.clone(),
+            true => Arc::new(match **statistic {
+                Statistic::Boolean(_) => Statistic::Boolean(
+                    BooleanStatistics::try_from(&mut *cursor).context(ReadStatistics {
+                        s_type: "boolean".to_string(),
+                    })?,
+                ),
+                Statistic::Int32(_) => Statistic::Int32(
+                    IntegerStatistics::try_from(&mut *cursor).context(ReadStatistics {
+                        s_type: "Int32".to_string(),
+                    })?,
+                ),
+                Statistic::Int64(_) => Statistic::Int64(
+                    LongStatistics::try_from(&mut *cursor).context(ReadStatistics {
+                        s_type: "Int64".to_string(),
+                    })?,
+                ),
+                Statistic::FLOAT(_) => Statistic::FLOAT(
+                    FloatStatistics::try_from(&mut *cursor).context(ReadStatistics {
+                        s_type: "FLOAT".to_string(),
+                    })?,
+                ),
+                Statistic::DOUBLE(_) => Statistic::DOUBLE(
+                    DoubleStatistics::try_from(&mut *cursor).context(ReadStatistics {
+                        s_type: "DOUBLE".to_string(),
+                    })?,
+                ),
+                Statistic::TEXT(_) => Statistic::TEXT(
+                    BinaryStatistics::try_from(&mut *cursor).context(ReadStatistics {
+                        s_type: "TEXT".to_string(),
+                    })?,
+                ),
+                Statistic::Time(_) => Statistic::Time(
+                    TimeStatistics::try_from(&mut *cursor).context(ReadStatistics {
+                        s_type: "time".to_string(),
+                    })?,
+                ),
+            }),
+        };
+
+        let range = take_range(cursor, compressed_size)?;
+        if let Some(filter) = filter {
+            if !filter.may_satisfy(&page_statistic) {
+                continue;
+            }
+        }
+        pages.push((
+            PageHeader::new(uncompressed_size, compressed_size, page_statistic),
+            range,
+            index,
+        ));
+    }
+    Ok(())
 }
 
 impl Iterator for DefaultChunkReader {
-    type Item = Box<dyn PageReader>;
+    type Item = PageResult;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.pages.is_empty() {
-            return None;
+        match self.pages.pop() {
+            Some(page) => Some(Ok(Box::new(page))),
+            None => self.error.take().map(Err),
         }
+    }
+}
 
-        self.pages.pop()
+impl ChunkReader for DefaultChunkReader {
+    fn header(&self) -> Option<&ChunkHeader> {
+        Some(&self.header)
     }
 }
 
-impl ChunkReader for DefaultChunkReader {}
+impl ChunkReader for iter::Empty<PageResult> {
+    fn header(&self) -> Option<&ChunkHeader> {
+        None
+    }
+}
 
 impl PageReader for DefaultPageReader {
     fn header(&self) -> &PageHeader {
@@ -239,29 +841,401 @@ impl PageReader for DefaultPageReader {
     }
 
     fn data(&self) -> Result<(Vec<Field>, Vec<Field>)> {
-        let mut data = Cursor::new(self.data.un_compress().context(DecompressChunkData)?);
-        let time_len = data.read_unsigned_varint_32().expect("123");
-
-        let mut time_data: Vec<u8> = vec![0; time_len as usize];
-        data.read_exact(&mut time_data);
-        let time = LongBinaryDecoder::new()
-            .decode(&mut Cursor::new(time_data))
-            .expect("123");
-        let data = self
+        let page = self.decompress()?;
+        self.decode_page(&page)
+    }
+
+    fn data_with(&self, scratch: &mut Vec<u8>) -> Result<(Vec<Field>, Vec<Field>)> {
+        self.decompress_into(scratch)?;
+        self.decode_page(scratch)
+    }
+
+    fn batch(&self) -> Result<(Vec<i64>, ColumnBatch)> {
+        let page = self.decompress()?;
+        self.decode_batch(&page)
+    }
+
+    fn point_iter(&self) -> Result<Box<dyn Iterator<Item = Result<(i64, Field)>>>> {
+        let page = match self.decompress()? {
+            Cow::Borrowed(_) => self.data.clone(),
+            Cow::Owned(page) => Bytes::from(page),
+        };
+        if self.order != TimeOrder::AsStored || self.layout != PageLayout::Series {
+            // points are reordered or matched with their timestamps as a whole, before any
+            // is yielded
+            let (times, values) = self.decode_batch(&page)?;
+            return Ok(Box::new(
+                times.into_iter().zip(values.into_fields()).map(Ok),
+            ));
+        }
+        if self.verify_at.is_some() {
+            // points are checked as a whole, before any is yielded
+            self.decode_batch(&page)?;
+        }
+        let time = time_range(&page)?;
+
+        let values = self.value_decoder.iter(page.slice(time.end..));
+        let time = self.time_decoder.iter(page.slice(time));
+        let (filter, encoding) = (self.filter.clone(), self.time_encoding);
+        Ok(Box::new(
+            time.zip(values)
+                .map(
+                    move |(t, v)| match (t.context(DecodePageData)?, v.context(DecodePageData)?) {
+                        (Field::Int64(t), v) => Ok((t, v)),
+                        _ => NonInt64Times { encoding }.fail(),
+                    },
+                )
+                .filter(move |point| match (&filter, point) {
+                    (Some(filter), Ok((t, v))) => filter.satisfy(*t, v),
+                    _ => true,
+                }),
+        ))
+    }
+}
+
+impl DefaultPageReader {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            skip_all,
+            fields(bytes = self.data.len(), uncompressed = self.header.uncompressed_size)
+        )
+    )]
+    fn decompress(&self) -> Result<Cow<'_, [u8]>> {
+        let page = self
+            .decompressor
+            .decompress(&self.data, self.header.uncompressed_size as usize)
+            .context(DecompressChunkData)?;
+        self.verify_size(page.len())?;
+        Ok(page)
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            skip_all,
+            fields(bytes = self.data.len(), uncompressed = self.header.uncompressed_size)
+        )
+    )]
+    fn decompress_into(&self, scratch: &mut Vec<u8>) -> Result<()> {
+        self.decompressor
+            .decompress_into(&self.data, self.header.uncompressed_size as usize, scratch)
+            .context(DecompressChunkData)?;
+        self.verify_size(scratch.len())
+    }
+
+    /// The error of a page failing verification, if it is verified.
+    fn corrupt(&self, detail: impl FnOnce() -> String) -> Result<()> {
+        match self.verify_at {
+            Some(offset) => CorruptPage {
+                offset,
+                page: self.index,
+                detail: detail(),
+            }
+            .fail(),
+            None => Ok(()),
+        }
+    }
+
+    fn verify_size(&self, len: usize) -> Result<()> {
+        if len == self.header.uncompressed_size as usize {
+            return Ok(());
+        }
+        self.corrupt(|| {
+            format!(
+                "decompressed to {} bytes, header records {}",
+                len, self.header.uncompressed_size
+            )
+        })
+    }
+
+    /// Compares the decoded timestamps and number of values with the page statistics.
+    fn verify_points(&self, times: impl Iterator<Item = i64>, values: usize) -> Result<()> {
+        if self.verify_at.is_none() {
+            return Ok(());
+        }
+        let statistics = &self.header.statistics;
+        let mut count = 0;
+        let mut last = None;
+        for t in times {
+            if count == 0 && t != statistics.start_time() {
+                return self.corrupt(|| {
+                    format!(
+                        "first time {}, statistics record {}",
+                        t,
+                        statistics.start_time()
+                    )
+                });
+            }
+            if let Some(last) = last.filter(|&last| t <= last) {
+                return self.corrupt(|| format!("time {} follows time {}", t, last));
+            }
+            last = Some(t);
+            count += 1;
+        }
+        if count != statistics.count() as usize || values != count {
+            return self.corrupt(|| {
+                format!(
+                    "decoded {} times and {} values, statistics record {} points",
+                    count,
+                    values,
+                    statistics.count()
+                )
+            });
+        }
+        match last {
+            Some(last) if last != statistics.end_time() => self.corrupt(|| {
+                format!(
+                    "last time {}, statistics record {}",
+                    last,
+                    statistics.end_time()
+                )
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// The positions of the points to keep, in increasing time, if the page is reordered and
+    /// its times do not already increase. Of the points sharing a time, the policy keeps the
+    /// first or last stored; times past the `values` decoded are left out.
+    fn increasing(&self, times: &[i64], values: usize) -> Option<Vec<usize>> {
+        let policy = match self.order {
+            TimeOrder::AsStored => return None,
+            TimeOrder::Increasing(policy) => policy,
+        };
+        let times = &times[..times.len().min(values)];
+        if times.windows(2).all(|w| w[0] < w[1]) {
+            return None;
+        }
+        let mut order: Vec<usize> = (0..times.len()).collect();
+        order.sort_by_key(|&i| times[i]);
+        let mut kept: Vec<usize> = Vec::with_capacity(order.len());
+        for i in order {
+            match kept.last_mut() {
+                Some(last) if times[*last] == times[i] => {
+                    if policy == DuplicatePolicy::LatestWins {
+                        *last = i;
+                    }
+                }
+                _ => kept.push(i),
+            }
+        }
+        Some(kept)
+    }
+
+    /// Decodes the timestamps and values of a page as laid out by its chunk. The rows of an
+    /// aligned value page left null by its bitmap are dropped, so the timestamps kept are
+    /// those of the values.
+    fn decode_columns(&self, page: &[u8]) -> Result<(Vec<i64>, ColumnBatch)> {
+        match self.layout {
+            PageLayout::Series => {
+                let time = time_range(page)?;
+                let values = self
+                    .value_decoder
+                    .decode_batch(&mut Cursor::new(&page[time.end..]))
+                    .context(DecodePageData)?;
+                Ok((self.decode_times(&page[time])?, values))
+            }
+            PageLayout::Time => {
+                let times = self.decode_times(page)?;
+                Ok((times.clone(), ColumnBatch::Int64(times)))
+            }
+            PageLayout::Value => {
+                let time_page = self
+                    .time_page
+                    .as_ref()
+                    .context(UnpairedValuePage { page: self.index })?;
+                let times = time_page.decode_times(&time_page.decompress()?)?;
+                let (rows, bitmap, values) = split_value_page(page)?;
+                let values = self
+                    .value_decoder
+                    .decode_batch(&mut Cursor::new(values))
+                    .context(DecodePageData)?;
+                let times = times
+                    .into_iter()
+                    .take(rows)
+                    .enumerate()
+                    .filter(|(row, _)| bitmap[row / 8] & (0x80 >> (row % 8)) != 0)
+                    .map(|(_, t)| t)
+                    .collect();
+                Ok((times, values))
+            }
+        }
+    }
+
+    /// Decodes a column of timestamps.
+    fn decode_times(&self, column: &[u8]) -> Result<Vec<i64>> {
+        match self
+            .time_decoder
+            .decode_batch(&mut Cursor::new(column))
+            .context(DecodePageData)?
+        {
+            ColumnBatch::Int64(times) => Ok(times),
+            _ => NonInt64Times {
+                encoding: self.time_encoding,
+            }
+            .fail(),
+        }
+    }
+
+    /// Decodes the time and value columns of a page into typed vectors, dropping points
+    /// the filter rejects and values without a timestamp.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(bytes = page.len()))
+    )]
+    fn decode_batch(&self, page: &[u8]) -> Result<(Vec<i64>, ColumnBatch)> {
+        let (mut times, mut values) = self.decode_columns(page)?;
+        self.verify_points(times.iter().copied(), values.len())?;
+        if let Some(order) = self.increasing(&times, values.len()) {
+            values.select(&order);
+            times = order.iter().map(|&i| times[i]).collect();
+        }
+
+        if self.filter.is_some() || times.len() != values.len() {
+            let keep: Vec<bool> = times
+                .iter()
+                .enumerate()
+                .map(|(i, t)| match (&self.filter, values.get(i)) {
+                    (Some(filter), Some(v)) => filter.satisfy(*t, &v),
+                    (None, Some(_)) => true,
+                    (_, None) => false,
+                })
+                .collect();
+            values.retain(&keep);
+            let mut flags = keep.iter();
+            times.retain(|_| *flags.next().unwrap());
+        }
+        Ok((times, values))
+    }
+
+    /// Decodes the time and value columns of a decompressed page.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(bytes = page.len()))
+    )]
+    fn decode_page(&self, page: &[u8]) -> Result<(Vec<Field>, Vec<Field>)> {
+        if self.layout != PageLayout::Series {
+            let (times, values) = self.decode_batch(page)?;
+            return Ok((
+                times.into_iter().map(Field::Int64).collect(),
+                values.into_fields(),
+            ));
+        }
+        let time = time_range(page)?;
+        let mut data = self
             .value_decoder
-            .decode(&mut data)
+            .decode(&mut Cursor::new(&page[time.end..]))
             .context(DecodePageData)?;
-        Ok((time, data))
+        let mut time = self
+            .time_decoder
+            .decode(&mut Cursor::new(&page[time]))
+            .context(DecodePageData)?;
+        let times = time
+            .iter()
+            .map(|t| match t {
+                Field::Int64(t) => Ok(*t),
+                _ => NonInt64Times {
+                    encoding: self.time_encoding,
+                }
+                .fail(),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        self.verify_points(times.iter().copied(), data.len())?;
+        if let Some(order) = self.increasing(&times, data.len()) {
+            let select = |fields: &[Field]| order.iter().map(|&i| fields[i].clone()).collect();
+            (time, data) = (select(&time), select(&data));
+        }
+        match &self.filter {
+            None => Ok((time, data)),
+            Some(filter) => Ok(time
+                .into_iter()
+                .zip(data)
+                .filter(|(t, v)| match t {
+                    Field::Int64(t) => filter.satisfy(*t, v),
+                    _ => false,
+                })
+                .unzip()),
+        }
+    }
+}
+
+/// The byte range of the time column of a decompressed page, which follows its varint
+/// length.
+fn time_range(page: &[u8]) -> Result<Range<usize>> {
+    let mut data = Cursor::new(page);
+    let time_len = data.read_unsigned_varint_32().context(ReadCursorData)? as usize;
+    let time_start = data.position() as usize;
+    ensure!(
+        time_start + time_len <= page.len(),
+        TruncatedPage {
+            time_len,
+            page_len: page.len()
+        }
+    );
+    Ok(time_start..time_start + time_len)
+}
+
+/// The row count, null bitmap and value bytes of a decompressed aligned value page. A set
+/// bit, most significant first, marks a row with a value.
+fn split_value_page(page: &[u8]) -> Result<(usize, &[u8], &[u8])> {
+    let mut data = Cursor::new(page);
+    let rows = data.read_i32::<BigEndian>().context(ReadCursorData)?.max(0) as usize;
+    let start = data.position() as usize;
+    let end = start + rows.div_ceil(8);
+    ensure!(
+        end <= page.len(),
+        TruncatedBitmap {
+            rows,
+            page_len: page.len()
+        }
+    );
+    Ok((rows, &page[start..end], &page[end..]))
+}
+
+/// How the columns of a page are laid out, which the chunk marker tells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PageLayout {
+    /// The varint length of the time column, the time column, then the values.
+    Series,
+    /// The timestamps of an aligned device only.
+    Time,
+    /// The row count, null bitmap and values of an aligned device's sensor, whose
+    /// timestamps are in the matching page of the time column.
+    Value,
+}
+
+impl PageLayout {
+    fn of(chunk_type: u8) -> Self {
+        match chunk_type & ALIGNED_COLUMN_MASK {
+            0x80 => PageLayout::Time,
+            0x40 => PageLayout::Value,
+            _ => PageLayout::Series,
+        }
     }
 }
 
 pub struct DefaultPageReader {
     header: PageHeader,
+    time_decoder: Box<dyn Decoder>,
+    time_encoding: TSEncoding,
     value_decoder: Box<dyn Decoder>,
-    data: Cursor<Vec<u8>>,
+    decompressor: Arc<dyn Decompressor>,
+    data: Bytes,
+    filter: Option<Arc<Filter>>,
+    // position in the chunk, and the chunk offset if the page is verified
+    index: usize,
+    verify_at: Option<u64>,
+    order: TimeOrder,
+    layout: PageLayout,
+    // the page of the time column holding the timestamps of a value page
+    time_page: Option<Box<DefaultPageReader>>,
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct PageHeader {
     uncompressed_size: u32,
     compressed_size: u32,
@@ -276,8 +1250,32 @@ impl PageHeader {
             statistics,
         }
     }
+
+    pub fn uncompressed_size(&self) -> u32 {
+        self.uncompressed_size
+    }
+
+    pub fn compressed_size(&self) -> u32 {
+        self.compressed_size
+    }
+
+    /// For chunks with a single page these are the chunk statistics.
+    pub fn statistics(&self) -> &Arc<Statistic> {
+        &self.statistics
+    }
+
+    pub fn num_points(&self) -> usize {
+        self.statistics.count().max(0) as usize
+    }
+
+    /// `(start_time, end_time)` of the page, both inclusive.
+    pub fn time_range(&self) -> (i64, i64) {
+        self.statistics.time_range()
+    }
 }
 
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ChunkHeader {
     chunk_type: u8,
     measurement_id: String,
@@ -310,6 +1308,59 @@ impl TryFrom<&mut Cursor<Vec<u8>>> for ChunkHeader {
     }
 }
 
+impl ChunkHeader {
+    pub(crate) fn new(
+        chunk_type: u8,
+        measurement_id: String,
+        data_size: u32,
+        data_type: TSDataType,
+        compression_type: CompressionType,
+        encoding_type: TSEncoding,
+    ) -> Self {
+        Self {
+            chunk_type,
+            measurement_id,
+            data_size,
+            data_type,
+            compression_type,
+            encoding_type,
+        }
+    }
+
+    /// The chunk marker, 5 for chunks holding a single page without page statistics, with
+    /// the high bits set for the time (0x80) and value (0x40) columns of aligned devices.
+    pub fn chunk_type(&self) -> u8 {
+        self.chunk_type
+    }
+
+    /// Whether the page headers carry statistics, which one-page chunks leave out.
+    pub fn has_page_statistics(&self) -> bool {
+        self.chunk_type & !ALIGNED_COLUMN_MASK != ONLY_ONE_PAGE_CHUNK_HEADER
+    }
+
+    pub fn measurement_id(&self) -> &str {
+        &self.measurement_id
+    }
+
+    pub fn data_size(&self) -> u32 {
+        self.data_size
+    }
+
+    pub fn data_type(&self) -> &TSDataType {
+        &self.data_type
+    }
+
+    pub fn compression_type(&self) -> &CompressionType {
+        &self.compression_type
+    }
+
+    pub fn encoding_type(&self) -> &TSEncoding {
+        &self.encoding_type
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum CompressionType {
     Uncompressed,
     Snappy,
@@ -319,6 +1370,9 @@ pub enum CompressionType {
     Paa,
     Pla,
     LZ4,
+    ZSTD,
+    /// A compression this crate does not know, readable with a registered decompressor.
+    Other(u8),
 }
 
 impl CompressionType {
@@ -331,11 +1385,31 @@ impl CompressionType {
             4 => Self::Sdt,
             5 => Self::Paa,
             6 => Self::Pla,
-            _ => Self::LZ4,
+            7 => Self::LZ4,
+            8 => Self::ZSTD,
+            id => Self::Other(id),
+        }
+    }
+
+    /// The id written in chunk headers.
+    pub fn id(&self) -> u8 {
+        match self {
+            Self::Uncompressed => 0,
+            Self::Snappy => 1,
+            Self::Gzip => 2,
+            Self::Lzo => 3,
+            Self::Sdt => 4,
+            Self::Paa => 5,
+            Self::Pla => 6,
+            Self::LZ4 => 7,
+            Self::ZSTD => 8,
+            Self::Other(id) => *id,
         }
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum TSEncoding {
     Plain,
     PlainDictionary,
@@ -346,6 +1420,15 @@ pub enum TSEncoding {
     GorillaV1,
     Regular,
     Gorilla,
+    Zigzag,
+    /// Lossy frequency-domain encoding; not decoded by this crate, its pages fail with
+    /// `UnsupportedEncoding` unless a decoder is registered for id 10.
+    Freq,
+    Chimp,
+    Sprintz,
+    Rlbe,
+    /// An encoding this crate does not know, readable with a registered decoder.
+    Other(u8),
 }
 
 impl TSEncoding {
@@ -359,7 +1442,277 @@ impl TSEncoding {
             5 => Self::Bitmap,
             6 => Self::GorillaV1,
             7 => Self::Regular,
-            _ => Self::Gorilla,
+            8 => Self::Gorilla,
+            9 => Self::Zigzag,
+            10 => Self::Freq,
+            11 => Self::Chimp,
+            12 => Self::Sprintz,
+            13 => Self::Rlbe,
+            id => Self::Other(id),
+        }
+    }
+
+    /// The id written in chunk headers.
+    pub fn id(&self) -> u8 {
+        match self {
+            Self::Plain => 0,
+            Self::PlainDictionary => 1,
+            Self::Rle => 2,
+            Self::Diff => 3,
+            Self::Ts2diff => 4,
+            Self::Bitmap => 5,
+            Self::GorillaV1 => 6,
+            Self::Regular => 7,
+            Self::Gorilla => 8,
+            Self::Zigzag => 9,
+            Self::Freq => 10,
+            Self::Chimp => 11,
+            Self::Sprintz => 12,
+            Self::Rlbe => 13,
+            Self::Other(id) => *id,
         }
     }
+
+    /// The name IoTDB gives the encoding, `UNKNOWN` for `Other`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Plain => "PLAIN",
+            Self::PlainDictionary => "DICTIONARY",
+            Self::Rle => "RLE",
+            Self::Diff => "DIFF",
+            Self::Ts2diff => "TS_2DIFF",
+            Self::Bitmap => "BITMAP",
+            Self::GorillaV1 => "GORILLA_V1",
+            Self::Regular => "REGULAR",
+            Self::Gorilla => "GORILLA",
+            Self::Zigzag => "ZIGZAG",
+            Self::Freq => "FREQ",
+            Self::Chimp => "CHIMP",
+            Self::Sprintz => "SPRINTZ",
+            Self::Rlbe => "RLBE",
+            Self::Other(_) => "UNKNOWN",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A one-page chunk with marker `chunk_type` and a single uncompressed page of the
+    /// points at times 1 and 2.
+    fn chunk_of_two_points(chunk_type: u8, statistic: Statistic) -> DefaultChunkReader {
+        let mut page = vec![24];
+        // one TS_2DIFF block: a delta of 1 from the first time, packed in 0 bits
+        for field in [1_i32, 0] {
+            page.extend(field.to_be_bytes());
+        }
+        for field in [1_i64, 1, 10, 20] {
+            page.extend(field.to_be_bytes());
+        }
+        let mut data = vec![page.len() as u8, page.len() as u8];
+        data.extend(page);
+        let header = ChunkHeader::new(
+            chunk_type,
+            "s1".to_string(),
+            data.len() as u32,
+            TSDataType::Int64,
+            CompressionType::Uncompressed,
+            TSEncoding::Plain,
+        );
+        DefaultChunkReader::new(
+            Cursor::new(data),
+            header,
+            Arc::new(statistic),
+            None,
+            DEFAULT_MAX_ALLOCATION,
+        )
+        .unwrap()
+    }
+
+    fn statistic(times: &[i64]) -> Statistic {
+        let mut statistic = Statistic::empty(&TSDataType::Int64);
+        statistic.update_batch(times, &ColumnBatch::Int64(vec![0; times.len()]));
+        statistic
+    }
+
+    #[test]
+    fn bounded_chunks_are_read_in_one_request() {
+        let mut file = vec![0; 7];
+        // a one-page chunk of s1 with three bytes of data, then the next chunk
+        file.extend([
+            ONLY_ONE_PAGE_CHUNK_HEADER,
+            4,
+            b's',
+            b'1',
+            3,
+            2,
+            0,
+            0,
+            1,
+            2,
+            3,
+        ]);
+        file.extend([0; 2000]);
+        let file = bytes::Bytes::from(file);
+        let len = file.len() as u64;
+        assert_eq!(first_read_len(7, Some(11), len, 1024), 11);
+        assert_eq!(first_read_len(7, Some(2000), len, 1024), 1024);
+        assert_eq!(first_read_len(7, None, len, 1024), 1024);
+
+        let (header, data) = fetch_chunk(&file, 7, Some(11), None, 1024).unwrap();
+        assert_eq!(header.data_size(), 3);
+        assert_eq!(data, [1, 2, 3]);
+        assert!(matches!(
+            fetch_chunk(&file, 7, Some(10), None, 1024),
+            Err(Error::ChunkPastBound {
+                data_size: 3,
+                max_size: 10
+            })
+        ));
+    }
+
+    #[test]
+    fn verification_checks_pages_against_statistics() {
+        let page = chunk_of_two_points(ONLY_ONE_PAGE_CHUNK_HEADER, statistic(&[1, 2]))
+            .with_verification(7)
+            .next();
+        let (times, _) = page.unwrap().unwrap().batch().unwrap();
+        assert_eq!(times, [1, 2]);
+
+        let page = chunk_of_two_points(ONLY_ONE_PAGE_CHUNK_HEADER, statistic(&[1, 3])).next();
+        assert!(page.unwrap().unwrap().batch().is_ok());
+        let page = chunk_of_two_points(ONLY_ONE_PAGE_CHUNK_HEADER, statistic(&[1, 3]))
+            .with_verification(7)
+            .next();
+        let e = page.unwrap().unwrap().point_iter().err().unwrap();
+        assert!(
+            matches!(
+                e,
+                Error::CorruptPage {
+                    offset: 7,
+                    page: 0,
+                    ..
+                }
+            ),
+            "{}",
+            e
+        );
+    }
+
+    #[test]
+    fn one_page_value_chunks_share_chunk_statistics() {
+        let mut chunk = chunk_of_two_points(ONLY_ONE_PAGE_CHUNK_HEADER | 0x40, statistic(&[1, 2]));
+        assert!(!chunk.header().unwrap().has_page_statistics());
+        let page = chunk.next().unwrap().unwrap();
+        assert_eq!(page.header().statistics().count(), 2);
+        // value pages take their timestamps from the time column
+        assert!(matches!(
+            page.batch(),
+            Err(Error::UnpairedValuePage { page: 0 })
+        ));
+    }
+
+    #[test]
+    fn time_columns_decode_with_the_configured_encoding() {
+        let mut page = vec![16];
+        for field in [1_i64, 2, 10, 20] {
+            page.extend(field.to_be_bytes());
+        }
+        let mut data = vec![page.len() as u8, page.len() as u8];
+        data.extend(page);
+        let header = ChunkHeader::new(
+            ONLY_ONE_PAGE_CHUNK_HEADER,
+            "s1".to_string(),
+            data.len() as u32,
+            TSDataType::Int64,
+            CompressionType::Uncompressed,
+            TSEncoding::Plain,
+        );
+        let mut chunk = DefaultChunkReader::new(
+            Cursor::new(data),
+            header,
+            Arc::new(statistic(&[1, 2])),
+            None,
+            DEFAULT_MAX_ALLOCATION,
+        )
+        .unwrap()
+        .with_time_encoding(&TSEncoding::Plain);
+        let (times, values) = chunk.next().unwrap().unwrap().batch().unwrap();
+        assert_eq!(times, [1, 2]);
+        assert_eq!(values, ColumnBatch::Int64(vec![10, 20]));
+    }
+
+    #[test]
+    fn time_decoders_must_yield_int64() {
+        use crate::encoding::decoder::{Decoder, IntPlainDecoder};
+        use crate::file::registry;
+
+        registry::register_decoder(201, |_| Some(Box::new(IntPlainDecoder::new())));
+        let chunk = || {
+            chunk_of_two_points(ONLY_ONE_PAGE_CHUNK_HEADER, statistic(&[1, 2]))
+                .with_time_encoding(&TSEncoding::Other(201))
+        };
+        let e = chunk().next().unwrap().unwrap().batch().unwrap_err();
+        assert!(matches!(
+            e,
+            Error::NonInt64Times {
+                encoding: TSEncoding::Other(201)
+            }
+        ));
+        let mut points = chunk().next().unwrap().unwrap().point_iter().unwrap();
+        assert!(matches!(
+            points.next(),
+            Some(Err(Error::NonInt64Times { .. }))
+        ));
+    }
+
+    #[test]
+    fn pages_are_reordered_by_time() {
+        let chunk = |order| {
+            let mut page = vec![24];
+            for field in [2_i64, 1, 2, 10, 20, 30] {
+                page.extend(field.to_be_bytes());
+            }
+            let mut data = vec![page.len() as u8, page.len() as u8];
+            data.extend(page);
+            let header = ChunkHeader::new(
+                ONLY_ONE_PAGE_CHUNK_HEADER,
+                "s1".to_string(),
+                data.len() as u32,
+                TSDataType::Int64,
+                CompressionType::Uncompressed,
+                TSEncoding::Plain,
+            );
+            DefaultChunkReader::new(
+                Cursor::new(data),
+                header,
+                Arc::new(statistic(&[1, 2])),
+                None,
+                DEFAULT_MAX_ALLOCATION,
+            )
+            .unwrap()
+            .with_time_encoding(&TSEncoding::Plain)
+            .with_time_order(order)
+            .next()
+            .unwrap()
+            .unwrap()
+        };
+
+        let (times, values) = chunk(TimeOrder::AsStored).batch().unwrap();
+        assert_eq!(times, [2, 1, 2]);
+        assert_eq!(values, ColumnBatch::Int64(vec![10, 20, 30]));
+
+        let page = chunk(TimeOrder::Increasing(DuplicatePolicy::LatestWins));
+        let (times, values) = page.batch().unwrap();
+        assert_eq!(times, [1, 2]);
+        assert_eq!(values, ColumnBatch::Int64(vec![20, 30]));
+        let points: Vec<_> = page.point_iter().unwrap().map(Result::unwrap).collect();
+        assert_eq!(points, [(1, Field::Int64(20)), (2, Field::Int64(30))]);
+
+        let page = chunk(TimeOrder::Increasing(DuplicatePolicy::EarliestWins));
+        let (times, values) = page.data().unwrap();
+        assert_eq!(times, [Field::Int64(1), Field::Int64(2)]);
+        assert_eq!(values, [Field::Int64(20), Field::Int64(10)]);
+    }
 }
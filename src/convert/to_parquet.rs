@@ -0,0 +1,293 @@
+#[cfg(feature = "fs")]
+use std::fs::File;
+use std::io::{self, Write};
+#[cfg(feature = "fs")]
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use parquet::basic::{LogicalType, Repetition, TimeUnit, Type as PhysicalType};
+use parquet::data_type::{
+    BoolType, ByteArray, ByteArrayType, DoubleType, FloatType, Int32Type, Int64Type,
+};
+use parquet::errors::ParquetError;
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::format::{MicroSeconds, MilliSeconds, NanoSeconds};
+use parquet::schema::types::Type;
+use snafu::{ResultExt, Snafu};
+
+use crate::chunk;
+use crate::encoding::decoder::{ColumnBatch, Field};
+use crate::error::TsFileError;
+use crate::file::metadata::TSDataType;
+use crate::file::options::TimestampPrecision;
+use crate::file::path::PathPattern;
+use crate::file::reader::FileReader;
+use crate::query::rows::DeviceRows;
+
+/// Rows buffered per Parquet row group.
+pub const ROW_GROUP_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Unable to read points: {}", source))]
+    ReadPoints { source: chunk::reader::Error },
+    #[snafu(display("Unable to write parquet: {}", source))]
+    WriteParquet { source: ParquetError },
+    #[cfg(feature = "fs")]
+    #[snafu(display("Unable to create {}: {}", path.display(), source))]
+    CreateFile { path: PathBuf, source: io::Error },
+    #[snafu(display("Unable to read metadata: {}", source))]
+    ReadMetadata { source: TsFileError },
+    #[snafu(display("Sensor {} has a point of another type: {:?}", sensor, field))]
+    MismatchedType { sensor: String, field: Field },
+}
+
+type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[cfg(feature = "fs")]
+/// Writes every device of the file to `<dir>/<device>.parquet` and returns the paths.
+pub fn to_parquet<F: FileReader + ?Sized>(
+    reader: &F,
+    dir: impl AsRef<Path>,
+) -> Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for device in reader.devices().context(ReadMetadata)? {
+        let path = dir
+            .as_ref()
+            .join(format!("{}.parquet", device.replace(['/', '\\'], "_")));
+        let file = File::create(&path).context(CreateFile { path: path.clone() })?;
+        write_device(reader, &device, file)?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+/// Writes the rows of `device` to `out` as one Parquet file: a required timestamp column
+/// `time` in the reader's precision and one optional column per sensor, with the writer's default
+/// row group statistics.
+pub fn write_device<F: FileReader + ?Sized, W: Write + Send>(
+    reader: &F,
+    device: &str,
+    out: W,
+) -> Result<W> {
+    let series = reader
+        .sensor_meta_iter(device)
+        .collect::<crate::error::Result<Vec<_>>>()
+        .context(ReadMetadata)?;
+    let (sensors, types): (Vec<_>, Vec<_>) = series
+        .iter()
+        .filter(|meta| !meta.is_time_column())
+        .map(|meta| (meta.measurement_id().to_string(), meta.data_type().clone()))
+        .unzip();
+    write_columns(reader, device, sensors, types, out)
+}
+
+/// Like `write_device`, with a column for each of `sensors` the file contains, in index
+/// order. The chunks of the other sensors of the device are never read.
+pub fn write_sensors<F: FileReader + ?Sized, W: Write + Send, S: AsRef<str>>(
+    reader: &F,
+    device: &str,
+    sensors: &[S],
+    out: W,
+) -> Result<W> {
+    let (sensors, types): (Vec<_>, Vec<_>) = reader
+        .match_paths(&PathPattern::sensors(device, sensors))
+        .context(ReadMetadata)?
+        .iter()
+        .map(|(_, meta)| (meta.measurement_id().to_string(), meta.data_type().clone()))
+        .unzip();
+    write_columns(reader, device, sensors, types, out)
+}
+
+fn write_columns<F: FileReader + ?Sized, W: Write + Send>(
+    reader: &F,
+    device: &str,
+    sensors: Vec<String>,
+    types: Vec<TSDataType>,
+    out: W,
+) -> Result<W> {
+    let schema = schema(&sensors, &types, reader.timestamp_precision()).context(WriteParquet)?;
+    let mut writer = SerializedFileWriter::new(
+        out,
+        Arc::new(schema),
+        Arc::new(WriterProperties::builder().build()),
+    )
+    .context(WriteParquet)?;
+
+    let mut group = RowGroup::new(&types);
+    for row in DeviceRows::new(reader, device, sensors.clone(), None).context(ReadMetadata)? {
+        let (time, values) = row.context(ReadPoints)?;
+        group.times.push(time);
+        for ((column, value), sensor) in group.columns.iter_mut().zip(values).zip(&sensors) {
+            column.push(sensor, value)?;
+        }
+        if group.times.len() >= ROW_GROUP_SIZE {
+            group.write(&mut writer).context(WriteParquet)?;
+            group = RowGroup::new(&types);
+        }
+    }
+    if !group.times.is_empty() {
+        group.write(&mut writer).context(WriteParquet)?;
+    }
+    writer.into_inner().context(WriteParquet)
+}
+
+fn schema(
+    sensors: &[String],
+    types: &[TSDataType],
+    precision: TimestampPrecision,
+) -> parquet::errors::Result<Type> {
+    let unit = match precision {
+        TimestampPrecision::Millisecond => TimeUnit::MILLIS(MilliSeconds::new()),
+        TimestampPrecision::Microsecond => TimeUnit::MICROS(MicroSeconds::new()),
+        TimestampPrecision::Nanosecond => TimeUnit::NANOS(NanoSeconds::new()),
+    };
+    let mut fields = vec![Arc::new(
+        Type::primitive_type_builder("time", PhysicalType::INT64)
+            .with_repetition(Repetition::REQUIRED)
+            .with_logical_type(Some(LogicalType::Timestamp {
+                is_adjusted_to_u_t_c: true,
+                unit,
+            }))
+            .build()?,
+    )];
+    for (sensor, data_type) in sensors.iter().zip(types) {
+        let (physical, logical) = match data_type {
+            TSDataType::Boolean => (PhysicalType::BOOLEAN, None),
+            TSDataType::Int32 => (PhysicalType::INT32, None),
+            TSDataType::Int64 | TSDataType::VECTOR => (PhysicalType::INT64, None),
+            TSDataType::FLOAT => (PhysicalType::FLOAT, None),
+            TSDataType::DOUBLE => (PhysicalType::DOUBLE, None),
+            TSDataType::TEXT => (PhysicalType::BYTE_ARRAY, Some(LogicalType::String)),
+        };
+        fields.push(Arc::new(
+            Type::primitive_type_builder(sensor, physical)
+                .with_repetition(Repetition::OPTIONAL)
+                .with_logical_type(logical)
+                .build()?,
+        ));
+    }
+    Type::group_type_builder("schema")
+        .with_fields(fields)
+        .build()
+}
+
+/// The buffered rows of one row group.
+struct RowGroup {
+    times: Vec<i64>,
+    columns: Vec<Column>,
+}
+
+/// The present values of a sensor and its definition levels, `0` for a missing value.
+struct Column {
+    values: ColumnBatch,
+    levels: Vec<i16>,
+}
+
+impl RowGroup {
+    fn new(types: &[TSDataType]) -> Self {
+        let columns = types
+            .iter()
+            .map(|data_type| Column {
+                values: ColumnBatch::new(data_type),
+                levels: Vec::new(),
+            })
+            .collect();
+        Self {
+            times: Vec::new(),
+            columns,
+        }
+    }
+
+    fn write<W: Write + Send>(
+        self,
+        writer: &mut SerializedFileWriter<W>,
+    ) -> parquet::errors::Result<()> {
+        let mut group = writer.next_row_group()?;
+        if let Some(mut column) = group.next_column()? {
+            column
+                .typed::<Int64Type>()
+                .write_batch(&self.times, None, None)?;
+            column.close()?;
+        }
+        for Column { values, levels } in self.columns {
+            let mut column = match group.next_column()? {
+                Some(column) => column,
+                None => break,
+            };
+            let levels = Some(levels.as_slice());
+            match values {
+                ColumnBatch::Boolean(v) => column.typed::<BoolType>().write_batch(&v, levels, None),
+                ColumnBatch::Int32(v) => column.typed::<Int32Type>().write_batch(&v, levels, None),
+                ColumnBatch::Int64(v) => column.typed::<Int64Type>().write_batch(&v, levels, None),
+                ColumnBatch::FLOAT(v) => column.typed::<FloatType>().write_batch(&v, levels, None),
+                ColumnBatch::DOUBLE(v) => {
+                    column.typed::<DoubleType>().write_batch(&v, levels, None)
+                }
+                ColumnBatch::TEXT(v) => {
+                    let v: Vec<ByteArray> = v.into_iter().map(ByteArray::from).collect();
+                    column
+                        .typed::<ByteArrayType>()
+                        .write_batch(&v, levels, None)
+                }
+            }?;
+            column.close()?;
+        }
+        group.close()?;
+        Ok(())
+    }
+}
+
+impl Column {
+    fn push(&mut self, sensor: &str, value: Option<Field>) -> Result<()> {
+        let value = match value {
+            Some(value) => value,
+            None => {
+                self.levels.push(0);
+                return Ok(());
+            }
+        };
+        match (&mut self.values, value) {
+            (ColumnBatch::Boolean(v), Field::Boolean(x)) => v.push(x),
+            (ColumnBatch::Int32(v), Field::Int32(x)) => v.push(x),
+            (ColumnBatch::Int64(v), Field::Int64(x)) => v.push(x),
+            (ColumnBatch::FLOAT(v), Field::FLOAT(x)) => v.push(x),
+            (ColumnBatch::DOUBLE(v), Field::DOUBLE(x)) => v.push(x),
+            (ColumnBatch::TEXT(v), Field::TEXT(x)) => v.push(x),
+            (_, field) => {
+                return MismatchedType {
+                    sensor: sensor.to_string(),
+                    field,
+                }
+                .fail()
+            }
+        }
+        self.levels.push(1);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parquet::schema::types::ColumnPath;
+
+    #[test]
+    fn schema_maps_sensor_types() {
+        let sensors = vec!["s1".to_string(), "s2".to_string()];
+        let schema = schema(
+            &sensors,
+            &[TSDataType::DOUBLE, TSDataType::TEXT],
+            TimestampPrecision::Millisecond,
+        )
+        .unwrap();
+        let descr = parquet::schema::types::SchemaDescriptor::new(Arc::new(schema));
+        assert_eq!(descr.num_columns(), 3);
+        assert_eq!(descr.column(0).path(), &ColumnPath::from("time"));
+        assert_eq!(descr.column(1).physical_type(), PhysicalType::DOUBLE);
+        assert_eq!(descr.column(2).physical_type(), PhysicalType::BYTE_ARRAY);
+        assert_eq!(descr.column(2).max_def_level(), 1);
+    }
+}
@@ -0,0 +1,51 @@
+use byteorder::ReadBytesExt;
+use std::io::{Read, Result};
+
+/// Reads bits most significant first from a byte source, the bit order IoTDB encoders
+/// write in.
+pub struct BitReader<'a, R: Read> {
+    cursor: &'a mut R,
+    buffer: u8,
+    bits_left: u32,
+}
+
+impl<'a, R: Read> BitReader<'a, R> {
+    pub fn new(cursor: &'a mut R) -> Self {
+        Self {
+            cursor,
+            buffer: 0,
+            bits_left: 0,
+        }
+    }
+
+    pub fn read_bit(&mut self) -> Result<bool> {
+        Ok(self.read_bits(1)? == 1)
+    }
+
+    /// Reads up to 64 bits as an unsigned number.
+    pub fn read_bits(&mut self, mut bits: u32) -> Result<u64> {
+        let mut value: u64 = 0;
+        while bits > 0 {
+            if self.bits_left == 0 {
+                self.buffer = self.cursor.read_u8()?;
+                self.bits_left = 8;
+            }
+            let take = bits.min(self.bits_left);
+            let mask = ((1_u16 << take) - 1) as u8;
+            let chunk = (self.buffer >> (self.bits_left - take)) & mask;
+            value = (value << take) | chunk as u64;
+            self.bits_left -= take;
+            bits -= take;
+        }
+        Ok(value)
+    }
+
+    /// Counts the `1` bits before the first `0`, reading at most `max` bits.
+    pub fn read_ones(&mut self, max: u32) -> Result<u32> {
+        let mut count = 0;
+        while count < max && self.read_bit()? {
+            count += 1;
+        }
+        Ok(count)
+    }
+}
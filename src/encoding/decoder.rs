@@ -1,20 +1,46 @@
+use crate::chunk::reader::TSEncoding;
+use crate::encoding::bits::BitReader;
+use crate::encoding::chimp::{
+    DoubleChimpDecoder, FloatChimpDecoder, IntChimpDecoder, LongChimpDecoder,
+};
+use crate::encoding::gorilla::{
+    DoubleGorillaDecoder, DoubleGorillaV1Decoder, FloatGorillaDecoder, FloatGorillaV1Decoder,
+    IntGorillaDecoder, LongGorillaDecoder,
+};
+use crate::encoding::rlbe::{IntRlbeDecoder, LongRlbeDecoder};
+use crate::encoding::rle::{BooleanRleDecoder, IntRleDecoder, LongRleDecoder};
+use crate::encoding::sprintz::{IntSprintzDecoder, LongSprintzDecoder};
+use crate::file::metadata::TSDataType;
+use crate::file::registry;
 use crate::utils::cursor;
-use crate::utils::cursor::PackWidthReader;
+use crate::utils::cursor::VarIntReader;
 use byteorder::{BigEndian, ReadBytesExt};
-use snafu::{ResultExt, Snafu};
-use std::io::Cursor;
+use bytes::Bytes;
+use snafu::{ensure, ResultExt, Snafu};
+use std::fmt;
+use std::io::{Cursor, Read};
+use std::iter;
 
 #[derive(Debug, Snafu)]
+#[snafu(visibility(pub(crate)))]
 pub enum Error {
     #[snafu(display("Unable to read cursor data: {}", source))]
     ReadCursorData { source: std::io::Error },
     #[snafu(display("Unable to read packed data: {}", source))]
     ReadPackedData { source: cursor::Error },
+    #[snafu(display("Corrupt encoded data: {}", detail))]
+    CorruptData { detail: String },
+    #[snafu(display("Pages encoded with {} cannot be decoded", encoding))]
+    UnsupportedEncoding { encoding: &'static str },
 }
 
-type Result<T, E = Error> = std::result::Result<T, E>;
+pub(crate) type Result<T, E = Error> = std::result::Result<T, E>;
 
-#[derive(Debug)]
+/// Values a single page may decode to. IoTDB writes at most about a million points per
+/// page by default; run lengths beyond this are treated as corrupt rather than allocated.
+pub(crate) const MAX_PAGE_VALUES: usize = 1 << 24;
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Field {
     Boolean(bool),
     Int32(i32),
@@ -24,43 +50,438 @@ pub enum Field {
     TEXT(Vec<u8>),
 }
 
+impl Field {
+    /// The value of a numeric field.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Field::Int32(v) => Some(*v as f64),
+            Field::Int64(v) => Some(*v as f64),
+            Field::FLOAT(v) => Some(*v as f64),
+            Field::DOUBLE(v) => Some(*v),
+            Field::Boolean(_) | Field::TEXT(_) => None,
+        }
+    }
+}
+
+/// Text is shown lossily decoded as UTF-8.
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Field::Boolean(v) => v.fmt(f),
+            Field::Int32(v) => v.fmt(f),
+            Field::Int64(v) => v.fmt(f),
+            Field::FLOAT(v) => v.fmt(f),
+            Field::DOUBLE(v) => v.fmt(f),
+            Field::TEXT(v) => String::from_utf8_lossy(v).fmt(f),
+        }
+    }
+}
+
+impl From<bool> for Field {
+    fn from(v: bool) -> Self {
+        Field::Boolean(v)
+    }
+}
+
+impl From<i32> for Field {
+    fn from(v: i32) -> Self {
+        Field::Int32(v)
+    }
+}
+
+impl From<i64> for Field {
+    fn from(v: i64) -> Self {
+        Field::Int64(v)
+    }
+}
+
+impl From<f32> for Field {
+    fn from(v: f32) -> Self {
+        Field::FLOAT(v)
+    }
+}
+
+impl From<f64> for Field {
+    fn from(v: f64) -> Self {
+        Field::DOUBLE(v)
+    }
+}
+
+impl From<&str> for Field {
+    fn from(v: &str) -> Self {
+        Field::TEXT(v.as_bytes().to_vec())
+    }
+}
+
+/// The values of a column in one contiguous vector of their primitive type.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColumnBatch {
+    Boolean(Vec<bool>),
+    Int32(Vec<i32>),
+    Int64(Vec<i64>),
+    FLOAT(Vec<f32>),
+    DOUBLE(Vec<f64>),
+    TEXT(Vec<Vec<u8>>),
+}
+
+impl ColumnBatch {
+    /// An empty batch for values of `data_type`.
+    pub fn new(data_type: &TSDataType) -> Self {
+        match data_type {
+            TSDataType::Boolean => ColumnBatch::Boolean(Vec::new()),
+            TSDataType::Int32 => ColumnBatch::Int32(Vec::new()),
+            TSDataType::Int64 => ColumnBatch::Int64(Vec::new()),
+            TSDataType::FLOAT => ColumnBatch::FLOAT(Vec::new()),
+            TSDataType::DOUBLE => ColumnBatch::DOUBLE(Vec::new()),
+            TSDataType::TEXT => ColumnBatch::TEXT(Vec::new()),
+            // the values of a time column are its timestamps
+            TSDataType::VECTOR => ColumnBatch::Int64(Vec::new()),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            ColumnBatch::Boolean(v) => v.len(),
+            ColumnBatch::Int32(v) => v.len(),
+            ColumnBatch::Int64(v) => v.len(),
+            ColumnBatch::FLOAT(v) => v.len(),
+            ColumnBatch::DOUBLE(v) => v.len(),
+            ColumnBatch::TEXT(v) => v.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The i-th value as a `Field`.
+    pub fn get(&self, i: usize) -> Option<Field> {
+        match self {
+            ColumnBatch::Boolean(v) => v.get(i).map(|v| Field::Boolean(*v)),
+            ColumnBatch::Int32(v) => v.get(i).map(|v| Field::Int32(*v)),
+            ColumnBatch::Int64(v) => v.get(i).map(|v| Field::Int64(*v)),
+            ColumnBatch::FLOAT(v) => v.get(i).map(|v| Field::FLOAT(*v)),
+            ColumnBatch::DOUBLE(v) => v.get(i).map(|v| Field::DOUBLE(*v)),
+            ColumnBatch::TEXT(v) => v.get(i).map(|v| Field::TEXT(v.clone())),
+        }
+    }
+
+    /// Appends the values of `other`, returning `false` and appending nothing if they are
+    /// of another type.
+    pub fn append(&mut self, other: ColumnBatch) -> bool {
+        match (self, other) {
+            (ColumnBatch::Boolean(v), ColumnBatch::Boolean(o)) => v.extend(o),
+            (ColumnBatch::Int32(v), ColumnBatch::Int32(o)) => v.extend(o),
+            (ColumnBatch::Int64(v), ColumnBatch::Int64(o)) => v.extend(o),
+            (ColumnBatch::FLOAT(v), ColumnBatch::FLOAT(o)) => v.extend(o),
+            (ColumnBatch::DOUBLE(v), ColumnBatch::DOUBLE(o)) => v.extend(o),
+            (ColumnBatch::TEXT(v), ColumnBatch::TEXT(o)) => v.extend(o),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Keeps the values whose flag in `keep` is set.
+    pub fn retain(&mut self, keep: &[bool]) {
+        fn retain<T>(values: &mut Vec<T>, keep: &[bool]) {
+            let mut flags = keep.iter();
+            values.retain(|_| *flags.next().unwrap_or(&false));
+        }
+        match self {
+            ColumnBatch::Boolean(v) => retain(v, keep),
+            ColumnBatch::Int32(v) => retain(v, keep),
+            ColumnBatch::Int64(v) => retain(v, keep),
+            ColumnBatch::FLOAT(v) => retain(v, keep),
+            ColumnBatch::DOUBLE(v) => retain(v, keep),
+            ColumnBatch::TEXT(v) => retain(v, keep),
+        }
+    }
+
+    /// Replaces the values with the ones at `indices`, in their order.
+    pub fn select(&mut self, indices: &[usize]) {
+        fn select<T: Clone>(values: &mut Vec<T>, indices: &[usize]) {
+            *values = indices.iter().map(|&i| values[i].clone()).collect();
+        }
+        match self {
+            ColumnBatch::Boolean(v) => select(v, indices),
+            ColumnBatch::Int32(v) => select(v, indices),
+            ColumnBatch::Int64(v) => select(v, indices),
+            ColumnBatch::FLOAT(v) => select(v, indices),
+            ColumnBatch::DOUBLE(v) => select(v, indices),
+            ColumnBatch::TEXT(v) => select(v, indices),
+        }
+    }
+
+    pub fn into_fields(self) -> Vec<Field> {
+        match self {
+            ColumnBatch::Boolean(v) => v.into_iter().map(Field::Boolean).collect(),
+            ColumnBatch::Int32(v) => v.into_iter().map(Field::Int32).collect(),
+            ColumnBatch::Int64(v) => v.into_iter().map(Field::Int64).collect(),
+            ColumnBatch::FLOAT(v) => v.into_iter().map(Field::FLOAT).collect(),
+            ColumnBatch::DOUBLE(v) => v.into_iter().map(Field::DOUBLE).collect(),
+            ColumnBatch::TEXT(v) => v.into_iter().map(Field::TEXT).collect(),
+        }
+    }
+}
+
 pub trait Decoder {
     fn new() -> Self
     where
         Self: Sized;
-    fn decode(&self, data: &mut Cursor<Vec<u8>>) -> Result<Vec<Field>>;
+
+    /// Decodes all values of `data` into a typed column, without a `Field` per value.
+    fn decode_batch(&self, data: &mut Cursor<&[u8]>) -> Result<ColumnBatch>;
+
+    fn decode(&self, data: &mut Cursor<&[u8]>) -> Result<Vec<Field>> {
+        Ok(self.decode_batch(data)?.into_fields())
+    }
+
+    /// Decodes `data` lazily, one value per item. Decoders without a streaming
+    /// implementation decode the whole page up front.
+    fn iter(&self, data: Bytes) -> Box<dyn Iterator<Item = Result<Field>>> {
+        match self.decode(&mut Cursor::new(&data[..])) {
+            Ok(values) => Box::new(values.into_iter().map(Ok)),
+            Err(e) => Box::new(iter::once(Err(e))),
+        }
+    }
 }
 
-pub trait BinaryDelta: Decoder {}
+/// Yields `read(data)` until the cursor is exhausted or a read fails.
+fn read_to_end(
+    data: Bytes,
+    read: fn(&mut Cursor<Bytes>) -> Result<Field>,
+) -> Box<dyn Iterator<Item = Result<Field>>> {
+    let mut data = Cursor::new(data);
+    let mut failed = false;
+    Box::new(iter::from_fn(move || {
+        if failed || data.position() >= data.get_ref().len() as u64 {
+            return None;
+        }
+        let value = read(&mut data);
+        failed = value.is_err();
+        Some(value)
+    }))
+}
 
+/// The decoder for values of `data_type` written with `encoding`.
+pub fn value_decoder(encoding: &TSEncoding, data_type: &TSDataType) -> Box<dyn Decoder> {
+    if let Some(decoder) = registry::decoder(encoding.id(), data_type) {
+        return decoder;
+    }
+    match (encoding, data_type) {
+        (TSEncoding::Gorilla, TSDataType::FLOAT) => Box::new(FloatGorillaDecoder::new()),
+        (TSEncoding::Gorilla, TSDataType::DOUBLE) => Box::new(DoubleGorillaDecoder::new()),
+        (TSEncoding::Gorilla, TSDataType::Int32) => Box::new(IntGorillaDecoder::new()),
+        (TSEncoding::Gorilla, TSDataType::Int64) => Box::new(LongGorillaDecoder::new()),
+        (TSEncoding::GorillaV1, TSDataType::FLOAT) => Box::new(FloatGorillaV1Decoder::new()),
+        (TSEncoding::GorillaV1, TSDataType::DOUBLE) => Box::new(DoubleGorillaV1Decoder::new()),
+        (TSEncoding::Chimp, TSDataType::FLOAT) => Box::new(FloatChimpDecoder::new()),
+        (TSEncoding::Chimp, TSDataType::DOUBLE) => Box::new(DoubleChimpDecoder::new()),
+        (TSEncoding::Chimp, TSDataType::Int32) => Box::new(IntChimpDecoder::new()),
+        (TSEncoding::Chimp, TSDataType::Int64) => Box::new(LongChimpDecoder::new()),
+        (TSEncoding::Rle, TSDataType::Boolean) => Box::new(BooleanRleDecoder::new()),
+        (TSEncoding::Rle, TSDataType::Int32) => Box::new(IntRleDecoder::new()),
+        (TSEncoding::Rle, TSDataType::Int64) => Box::new(LongRleDecoder::new()),
+        (TSEncoding::Rlbe, TSDataType::Int32) => Box::new(IntRlbeDecoder::new()),
+        (TSEncoding::Rlbe, TSDataType::Int64) => Box::new(LongRlbeDecoder::new()),
+        (TSEncoding::Sprintz, TSDataType::Int32) => Box::new(IntSprintzDecoder::new()),
+        (TSEncoding::Sprintz, TSDataType::Int64) => Box::new(LongSprintzDecoder::new()),
+        (TSEncoding::Ts2diff, TSDataType::Int64) => Box::new(LongBinaryDecoder::new()),
+        (_, TSDataType::VECTOR) => value_decoder(encoding, &TSDataType::Int64),
+        (TSEncoding::Plain, _) => plain_decoder(data_type),
+        (TSEncoding::Other(_), _) => Box::new(UnsupportedDecoder::new()),
+        // the others are bit patterns PLAIN would misread as values; FREQ is left to
+        // registered decoders, as a lossy decoder that merely looks right is worse
+        // than an error
+        _ => Box::new(UnsupportedDecoder {
+            encoding: encoding.name(),
+        }),
+    }
+}
+
+fn plain_decoder(data_type: &TSDataType) -> Box<dyn Decoder> {
+    match data_type {
+        TSDataType::Boolean => Box::new(BooleanPlainDecoder::new()),
+        TSDataType::Int32 => Box::new(IntPlainDecoder::new()),
+        TSDataType::Int64 | TSDataType::VECTOR => Box::new(LongPlainDecoder::new()),
+        TSDataType::FLOAT => Box::new(FloatPlainDecoder::new()),
+        TSDataType::DOUBLE => Box::new(DoublePlainDecoder::new()),
+        TSDataType::TEXT => Box::new(TextPlainDecoder::new()),
+    }
+}
+
+/// Fails every page, for encodings whose values cannot be read yet.
+pub struct UnsupportedDecoder {
+    encoding: &'static str,
+}
+
+impl Decoder for UnsupportedDecoder {
+    fn new() -> Self {
+        Self {
+            encoding: "an unknown encoding",
+        }
+    }
+
+    fn decode_batch(&self, _data: &mut Cursor<&[u8]>) -> Result<ColumnBatch> {
+        UnsupportedEncoding {
+            encoding: self.encoding,
+        }
+        .fail()
+    }
+}
+
+/// Decodes TS_2DIFF, the default time encoding, as IoTDB's `LongDeltaDecoder` does.
+///
+/// The data is a sequence of blocks, see [`decode_ts2diff_block`].
 pub struct LongBinaryDecoder {}
 
+/// Decodes one TS_2DIFF block into `out`: a big endian `i32` count of deltas and `i32`
+/// bit width, the `i64` minimum delta and first value, then the deltas less the minimum,
+/// bit-packed most significant bit first. Each value is the previous one plus the minimum
+/// and its packed delta, wrapping as Java `long` arithmetic does.
+fn decode_ts2diff_block<T: AsRef<[u8]>>(data: &mut Cursor<T>, out: &mut Vec<i64>) -> Result<()> {
+    let pack_num = data.read_i32::<BigEndian>().context(ReadCursorData)?;
+    let pack_width = data.read_i32::<BigEndian>().context(ReadCursorData)?;
+    let min_delta_base = data.read_i64::<BigEndian>().context(ReadCursorData)?;
+    let mut previous = data.read_i64::<BigEndian>().context(ReadCursorData)?;
+    ensure!(
+        (0..=64).contains(&pack_width),
+        CorruptData {
+            detail: format!("TS_2DIFF pack width {}", pack_width)
+        }
+    );
+    ensure!(
+        pack_num >= 0 && out.len() + (pack_num as usize) < MAX_PAGE_VALUES,
+        CorruptData {
+            detail: format!("TS_2DIFF block of {} values", pack_num)
+        }
+    );
+    let packed_len = (pack_num as u64 * pack_width as u64).div_ceil(8);
+    ensure!(
+        packed_len <= data.remaining(),
+        CorruptData {
+            detail: format!("TS_2DIFF block of {} bytes exceeds page", packed_len)
+        }
+    );
+
+    out.reserve(pack_num as usize + 1);
+    out.push(previous);
+    let mut bits = BitReader::new(data);
+    for _ in 0..pack_num {
+        let delta = bits.read_bits(pack_width as u32).context(ReadCursorData)?;
+        previous = previous
+            .wrapping_add(min_delta_base)
+            .wrapping_add(delta as i64);
+        out.push(previous);
+    }
+    Ok(())
+}
+
+impl LongBinaryDecoder {
+    /// Appends the decoded values to `out`.
+    pub fn decode_i64_into(&self, data: &mut Cursor<&[u8]>, out: &mut Vec<i64>) -> Result<()> {
+        while data.position() < data.get_ref().len() as u64 {
+            decode_ts2diff_block(data, out)?;
+        }
+        Ok(())
+    }
+}
+
 impl Decoder for LongBinaryDecoder {
     fn new() -> Self {
         Self {}
     }
 
-    fn decode(&self, data: &mut Cursor<Vec<u8>>) -> Result<Vec<Field>> {
-        let pack_num = data.read_i32::<BigEndian>().context(ReadCursorData)?;
-        let pack_width = data.read_i32::<BigEndian>().context(ReadCursorData)?;
-        let min_delta_base = data.read_i64::<BigEndian>().context(ReadCursorData)?;
-        let mut previous = data.read_i64::<BigEndian>().context(ReadCursorData)?;
-        let mut result = Vec::with_capacity(pack_num as usize);
+    fn decode_batch(&self, data: &mut Cursor<&[u8]>) -> Result<ColumnBatch> {
+        let mut result = Vec::new();
+        self.decode_i64_into(data, &mut result)?;
+        Ok(ColumnBatch::Int64(result))
+    }
 
-        for i in 0..pack_num {
-            let value = data
-                .read_pack_width_long(pack_width * i, pack_width)
-                .context(ReadPackedData)?;
-            previous = previous + min_delta_base + value;
-            result.push(Field::Int64(previous));
-        }
+    /// Decodes a block at a time.
+    fn iter(&self, data: Bytes) -> Box<dyn Iterator<Item = Result<Field>>> {
+        let mut data = Cursor::new(data);
+        let mut block = Vec::new().into_iter();
+        let mut failed = false;
+        Box::new(iter::from_fn(move || loop {
+            if let Some(value) = block.next() {
+                return Some(Ok(Field::Int64(value)));
+            }
+            if failed || data.position() >= data.get_ref().len() as u64 {
+                return None;
+            }
+            let mut values = Vec::new();
+            if let Err(e) = decode_ts2diff_block(&mut data, &mut values) {
+                failed = true;
+                return Some(Err(e));
+            }
+            block = values.into_iter();
+        }))
+    }
+}
 
-        Ok(result)
+/// Applies `read` until the cursor is exhausted.
+fn decode_to_end<T: AsRef<[u8]>, V>(
+    data: &mut Cursor<T>,
+    read: fn(&mut Cursor<T>) -> Result<V>,
+) -> Result<Vec<V>> {
+    let mut result = Vec::new();
+    while data.position() < data.get_ref().as_ref().len() as u64 {
+        result.push(read(data)?);
     }
+    Ok(result)
+}
+
+fn read_boolean<T: AsRef<[u8]>>(data: &mut Cursor<T>) -> Result<bool> {
+    Ok(data.read_u8().context(ReadCursorData)? != 0)
 }
 
-pub trait PlainDecoder: Decoder {}
+fn read_int<T: AsRef<[u8]>>(data: &mut Cursor<T>) -> Result<i32> {
+    data.read_varint().context(ReadPackedData)
+}
+
+fn read_long<T: AsRef<[u8]>>(data: &mut Cursor<T>) -> Result<i64> {
+    data.read_i64::<BigEndian>().context(ReadCursorData)
+}
 
+fn read_float<T: AsRef<[u8]>>(data: &mut Cursor<T>) -> Result<f32> {
+    data.read_f32::<BigEndian>().context(ReadCursorData)
+}
+
+fn read_double<T: AsRef<[u8]>>(data: &mut Cursor<T>) -> Result<f64> {
+    data.read_f64::<BigEndian>().context(ReadCursorData)
+}
+
+fn read_text<T: AsRef<[u8]>>(data: &mut Cursor<T>) -> Result<Vec<u8>> {
+    let len = data.read_varint().context(ReadPackedData)?;
+    ensure!(
+        len >= 0 && data.position() + len as u64 <= data.get_ref().as_ref().len() as u64,
+        CorruptData {
+            detail: format!("text value of {} bytes exceeds page", len)
+        }
+    );
+    let mut value = vec![0; len as usize];
+    data.read_exact(&mut value).context(ReadCursorData)?;
+    Ok(value)
+}
+
+pub struct BooleanPlainDecoder {}
+
+impl Decoder for BooleanPlainDecoder {
+    fn new() -> Self {
+        Self {}
+    }
+
+    fn decode_batch(&self, data: &mut Cursor<&[u8]>) -> Result<ColumnBatch> {
+        Ok(ColumnBatch::Boolean(decode_to_end(data, read_boolean)?))
+    }
+
+    fn iter(&self, data: Bytes) -> Box<dyn Iterator<Item = Result<Field>>> {
+        read_to_end(data, |data| read_boolean(data).map(Field::Boolean))
+    }
+}
+
+/// INT32 values are zigzag varints, unlike the other fixed width types.
 pub struct IntPlainDecoder {}
 
 impl Decoder for IntPlainDecoder {
@@ -68,14 +489,176 @@ impl Decoder for IntPlainDecoder {
         Self {}
     }
 
-    fn decode(&self, data: &mut Cursor<Vec<u8>>) -> Result<Vec<Field>> {
-        let mut result = Vec::new();
-        while data.position() < data.get_ref().len() as u64 {
-            result.push(Field::Int32(
-                data.read_i32::<BigEndian>().context(ReadCursorData)?,
-            ));
+    fn decode_batch(&self, data: &mut Cursor<&[u8]>) -> Result<ColumnBatch> {
+        Ok(ColumnBatch::Int32(decode_to_end(data, read_int)?))
+    }
+
+    fn iter(&self, data: Bytes) -> Box<dyn Iterator<Item = Result<Field>>> {
+        read_to_end(data, |data| read_int(data).map(Field::Int32))
+    }
+}
+
+pub struct LongPlainDecoder {}
+
+impl Decoder for LongPlainDecoder {
+    fn new() -> Self {
+        Self {}
+    }
+
+    fn decode_batch(&self, data: &mut Cursor<&[u8]>) -> Result<ColumnBatch> {
+        Ok(ColumnBatch::Int64(decode_to_end(data, read_long)?))
+    }
+
+    fn iter(&self, data: Bytes) -> Box<dyn Iterator<Item = Result<Field>>> {
+        read_to_end(data, |data| read_long(data).map(Field::Int64))
+    }
+}
+
+pub struct FloatPlainDecoder {}
+
+impl Decoder for FloatPlainDecoder {
+    fn new() -> Self {
+        Self {}
+    }
+
+    fn decode_batch(&self, data: &mut Cursor<&[u8]>) -> Result<ColumnBatch> {
+        Ok(ColumnBatch::FLOAT(decode_to_end(data, read_float)?))
+    }
+
+    fn iter(&self, data: Bytes) -> Box<dyn Iterator<Item = Result<Field>>> {
+        read_to_end(data, |data| read_float(data).map(Field::FLOAT))
+    }
+}
+
+pub struct DoublePlainDecoder {}
+
+impl Decoder for DoublePlainDecoder {
+    fn new() -> Self {
+        Self {}
+    }
+
+    fn decode_batch(&self, data: &mut Cursor<&[u8]>) -> Result<ColumnBatch> {
+        Ok(ColumnBatch::DOUBLE(decode_to_end(data, read_double)?))
+    }
+
+    fn iter(&self, data: Bytes) -> Box<dyn Iterator<Item = Result<Field>>> {
+        read_to_end(data, |data| read_double(data).map(Field::DOUBLE))
+    }
+}
+
+/// TEXT values are a zigzag varint length followed by the bytes.
+pub struct TextPlainDecoder {}
+
+impl Decoder for TextPlainDecoder {
+    fn new() -> Self {
+        Self {}
+    }
+
+    fn decode_batch(&self, data: &mut Cursor<&[u8]>) -> Result<ColumnBatch> {
+        Ok(ColumnBatch::TEXT(decode_to_end(data, read_text)?))
+    }
+
+    fn iter(&self, data: Bytes) -> Box<dyn Iterator<Item = Result<Field>>> {
+        read_to_end(data, |data| read_text(data).map(Field::TEXT))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_plain_batch() {
+        let data: Vec<u8> = [1.5_f64, -2.0, 4.25]
+            .iter()
+            .flat_map(|v| v.to_be_bytes())
+            .collect();
+        let mut batch = DoublePlainDecoder::new()
+            .decode_batch(&mut Cursor::new(&data[..]))
+            .unwrap();
+        assert_eq!(batch, ColumnBatch::DOUBLE(vec![1.5, -2.0, 4.25]));
+
+        batch.retain(&[true, false, true]);
+        assert_eq!(batch.get(1), Some(Field::DOUBLE(4.25)));
+        assert_eq!(batch.len(), 2);
+    }
+
+    /// Written by IoTDB's `LongDeltaEncoder` with a block size of 4, for the values of
+    /// `TS2DIFF_VALUES`: a block of four deltas, one falling, and one of three.
+    const TS2DIFF_PAGE: [u8; 53] = [
+        0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x04, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+        0xff, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x03, 0xe8, 0x48, 0x0c, 0x00, 0x00, 0x00, 0x03,
+        0x00, 0x00, 0x00, 0x07, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xf6, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x03, 0xfd, 0xb2, 0x03, 0xc0,
+    ];
+
+    const TS2DIFF_VALUES: [i64; 9] = [1000, 1003, 1010, 1009, 1020, 1021, 1100, 1090, 1200];
+
+    #[test]
+    fn unimplemented_pairs_fail_rather_than_decode_as_plain() {
+        let data = 7_i64.to_be_bytes();
+        for (encoding, data_type) in [
+            (TSEncoding::Ts2diff, TSDataType::Int32),
+            (TSEncoding::Rle, TSDataType::DOUBLE),
+            (TSEncoding::PlainDictionary, TSDataType::TEXT),
+            (TSEncoding::GorillaV1, TSDataType::Int64),
+            (TSEncoding::Freq, TSDataType::DOUBLE),
+            (TSEncoding::Regular, TSDataType::Int64),
+        ] {
+            let result = value_decoder(&encoding, &data_type).decode_batch(&mut Cursor::new(&data));
+            assert!(
+                matches!(result, Err(Error::UnsupportedEncoding { encoding: name }) if name == encoding.name()),
+                "{:?} {:?}",
+                encoding,
+                data_type
+            );
         }
+        let plain = value_decoder(&TSEncoding::Plain, &TSDataType::Int64);
+        assert_eq!(
+            plain.decode_batch(&mut Cursor::new(&data)).unwrap(),
+            ColumnBatch::Int64(vec![7])
+        );
+    }
+
+    #[test]
+    fn ts2diff_decodes_every_block() {
+        let mut times = Vec::new();
+        LongBinaryDecoder::new()
+            .decode_i64_into(&mut Cursor::new(&TS2DIFF_PAGE[..]), &mut times)
+            .unwrap();
+        assert_eq!(times, TS2DIFF_VALUES);
+    }
+
+    #[test]
+    fn ts2diff_batches_match_and_bound_blocks() {
+        let batch = LongBinaryDecoder::new()
+            .decode_batch(&mut Cursor::new(&TS2DIFF_PAGE[..]))
+            .unwrap();
+        assert_eq!(batch, ColumnBatch::Int64(TS2DIFF_VALUES.to_vec()));
+
+        // the second block loses its last packed byte
+        let truncated = &TS2DIFF_PAGE[..TS2DIFF_PAGE.len() - 1];
+        assert!(matches!(
+            LongBinaryDecoder::new().decode_batch(&mut Cursor::new(truncated)),
+            Err(Error::CorruptData { .. })
+        ));
+    }
+
+    #[test]
+    fn ts2diff_iter_streams_every_block() {
+        let values: Vec<_> = LongBinaryDecoder::new()
+            .iter(Bytes::from_static(&TS2DIFF_PAGE))
+            .collect::<Result<_>>()
+            .unwrap();
+        let expected: Vec<_> = TS2DIFF_VALUES.iter().map(|&v| Field::Int64(v)).collect();
+        assert_eq!(values, expected);
 
-        Ok(result)
+        // the first block is yielded before the truncated second one fails
+        let truncated: Vec<_> = LongBinaryDecoder::new()
+            .iter(Bytes::from_static(&TS2DIFF_PAGE[..40]))
+            .collect();
+        assert_eq!(truncated.len(), 6);
+        assert!(truncated[..5].iter().all(Result::is_ok));
+        assert!(truncated[5].is_err());
     }
 }
@@ -0,0 +1,311 @@
+use std::io::Cursor;
+
+use byteorder::ReadBytesExt;
+use snafu::{ensure, ResultExt};
+
+use crate::encoding::bits::BitReader;
+use crate::encoding::decoder::{ColumnBatch, CorruptData, Decoder, ReadCursorData, Result};
+
+/// Decodes one page of IoTDB's Gorilla (v2) XOR encoding for `width` bit values.
+///
+/// Every value after the first is a control code: `0` repeats the previous value, `10`
+/// reuses the previous leading/trailing zero counts and `11` stores new ones. The encoder
+/// terminates a page with `ending`.
+fn decode_xor(data: &mut Cursor<&[u8]>, width: u32, ending: u64) -> Result<Vec<u64>> {
+    let (leading_bits, meaningful_bits) = if width == 32 { (5, 5) } else { (6, 6) };
+    let mut result = Vec::new();
+    if data.position() >= data.get_ref().len() as u64 {
+        return Ok(result);
+    }
+    let mut reader = BitReader::new(data);
+
+    let mut stored = reader.read_bits(width).context(ReadCursorData)?;
+    let mut leading = 0;
+    let mut trailing = 0;
+    loop {
+        result.push(stored);
+        match reader.read_ones(2).context(ReadCursorData)? {
+            2 => {
+                leading = reader.read_bits(leading_bits).context(ReadCursorData)? as u32;
+                let significant =
+                    reader.read_bits(meaningful_bits).context(ReadCursorData)? as u32 + 1;
+                ensure!(
+                    leading + significant <= width,
+                    CorruptData {
+                        detail: format!("{} leading and {} significant bits", leading, significant)
+                    }
+                );
+                trailing = width - significant - leading;
+                let xor = reader
+                    .read_bits(width - leading - trailing)
+                    .context(ReadCursorData)?;
+                stored ^= xor << trailing;
+            }
+            1 => {
+                let xor = reader
+                    .read_bits(width - leading - trailing)
+                    .context(ReadCursorData)?;
+                stored ^= xor << trailing;
+            }
+            _ => {}
+        }
+        if stored == ending {
+            return Ok(result);
+        }
+    }
+}
+
+/// Decodes one page of the legacy Gorilla (v1) encoding used before IoTDB 0.12.
+///
+/// The first value is stored little endian. The `10` code reuses the zero counts of the
+/// previous value itself, `11` stores a new leading zero count and an explicit length. The
+/// page ends with a NaN.
+fn decode_xor_v1(data: &mut Cursor<&[u8]>, width: u32) -> Result<Vec<u64>> {
+    let (leading_bits, length_bits) = if width == 32 { (5, 6) } else { (6, 7) };
+    let mut result = Vec::new();
+    if data.position() >= data.get_ref().len() as u64 {
+        return Ok(result);
+    }
+
+    let mut stored = 0_u64;
+    for i in 0..width / 8 {
+        stored |= (data.read_u8().context(ReadCursorData)? as u64) << (8 * i);
+    }
+    let mut reader = BitReader::new(data);
+    loop {
+        result.push(stored);
+        if !reader.read_bit().context(ReadCursorData)? {
+            continue;
+        }
+        if !reader.read_bit().context(ReadCursorData)? {
+            let (leading, trailing) = if width == 32 {
+                (
+                    (stored as u32).leading_zeros(),
+                    (stored as u32).trailing_zeros(),
+                )
+            } else {
+                (stored.leading_zeros(), stored.trailing_zeros())
+            };
+            let xor = reader
+                .read_bits(width.saturating_sub(leading + trailing))
+                .context(ReadCursorData)?;
+            stored ^= xor.checked_shl(trailing).unwrap_or(0);
+        } else {
+            let leading = reader.read_bits(leading_bits).context(ReadCursorData)? as u32;
+            let len = reader.read_bits(length_bits).context(ReadCursorData)? as u32;
+            ensure!(
+                leading + len <= width,
+                CorruptData {
+                    detail: format!("{} leading zeros and {} meaningful bits", leading, len)
+                }
+            );
+            let xor = reader.read_bits(len).context(ReadCursorData)?;
+            stored ^= xor.checked_shl(width - leading - len).unwrap_or(0);
+        }
+
+        let is_nan = if width == 32 {
+            f32::from_bits(stored as u32).is_nan()
+        } else {
+            f64::from_bits(stored).is_nan()
+        };
+        if is_nan {
+            return Ok(result);
+        }
+    }
+}
+
+pub struct FloatGorillaDecoder {}
+
+impl Decoder for FloatGorillaDecoder {
+    fn new() -> Self {
+        Self {}
+    }
+
+    fn decode_batch(&self, data: &mut Cursor<&[u8]>) -> Result<ColumnBatch> {
+        Ok(ColumnBatch::FLOAT(
+            decode_xor(data, 32, f32::NAN.to_bits() as u64)?
+                .into_iter()
+                .map(|v| f32::from_bits(v as u32))
+                .collect(),
+        ))
+    }
+}
+
+pub struct DoubleGorillaDecoder {}
+
+impl Decoder for DoubleGorillaDecoder {
+    fn new() -> Self {
+        Self {}
+    }
+
+    fn decode_batch(&self, data: &mut Cursor<&[u8]>) -> Result<ColumnBatch> {
+        Ok(ColumnBatch::DOUBLE(
+            decode_xor(data, 64, f64::NAN.to_bits())?
+                .into_iter()
+                .map(f64::from_bits)
+                .collect(),
+        ))
+    }
+}
+
+pub struct FloatGorillaV1Decoder {}
+
+impl Decoder for FloatGorillaV1Decoder {
+    fn new() -> Self {
+        Self {}
+    }
+
+    fn decode_batch(&self, data: &mut Cursor<&[u8]>) -> Result<ColumnBatch> {
+        Ok(ColumnBatch::FLOAT(
+            decode_xor_v1(data, 32)?
+                .into_iter()
+                .map(|v| f32::from_bits(v as u32))
+                .collect(),
+        ))
+    }
+}
+
+pub struct DoubleGorillaV1Decoder {}
+
+impl Decoder for DoubleGorillaV1Decoder {
+    fn new() -> Self {
+        Self {}
+    }
+
+    fn decode_batch(&self, data: &mut Cursor<&[u8]>) -> Result<ColumnBatch> {
+        Ok(ColumnBatch::DOUBLE(
+            decode_xor_v1(data, 64)?
+                .into_iter()
+                .map(f64::from_bits)
+                .collect(),
+        ))
+    }
+}
+
+pub struct IntGorillaDecoder {}
+
+impl Decoder for IntGorillaDecoder {
+    fn new() -> Self {
+        Self {}
+    }
+
+    fn decode_batch(&self, data: &mut Cursor<&[u8]>) -> Result<ColumnBatch> {
+        Ok(ColumnBatch::Int32(
+            decode_xor(data, 32, i32::MIN as u32 as u64)?
+                .into_iter()
+                .map(|v| v as u32 as i32)
+                .collect(),
+        ))
+    }
+}
+
+pub struct LongGorillaDecoder {}
+
+impl Decoder for LongGorillaDecoder {
+    fn new() -> Self {
+        Self {}
+    }
+
+    fn decode_batch(&self, data: &mut Cursor<&[u8]>) -> Result<ColumnBatch> {
+        Ok(ColumnBatch::Int64(
+            decode_xor(data, 64, i64::MIN as u64)?
+                .into_iter()
+                .map(|v| v as i64)
+                .collect(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn v2_decodes_pages_of_each_type() {
+        // written by IoTDB's IntGorillaEncoder: a repeat, then new and reused zero counts
+        let data = [
+            0x00, 0x00, 0x00, 0x07, 0x60, 0xf7, 0xff, 0xff, 0xff, 0xdc, 0x1f, 0xff, 0xfe, 0x79,
+            0x5d, 0xbf, 0xff, 0xff, 0xf0, 0x2f, 0xff, 0xe7, 0x96, 0xc4, 0x00, 0x00, 0x00, 0x03,
+            0x40, 0x00, 0x00, 0x06, 0x80,
+        ];
+        let batch = IntGorillaDecoder::new()
+            .decode_batch(&mut Cursor::new(&data[..]))
+            .unwrap();
+        assert_eq!(
+            batch,
+            ColumnBatch::Int32(vec![7, 7, -3, 100_000, -100_000, 12, 12, 13])
+        );
+
+        // LongGorillaEncoder
+        let data = [
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x07, 0x60, 0x7d, 0xff, 0xff, 0xff, 0xff,
+            0xff, 0xff, 0xff, 0xf7, 0x03, 0xff, 0xff, 0xff, 0xff, 0xda, 0xbf, 0x41, 0xbf, 0xdb,
+            0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xe0, 0x02, 0xff, 0xff, 0xff, 0xfd, 0xab, 0xf4,
+            0x1c, 0x0c, 0x40, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x34, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x68,
+        ];
+        let batch = LongGorillaDecoder::new()
+            .decode_batch(&mut Cursor::new(&data[..]))
+            .unwrap();
+        assert_eq!(
+            batch,
+            ColumnBatch::Int64(vec![7, 7, -3, 10_000_000_000, -10_000_000_000, 12, 12, 13])
+        );
+
+        // SinglePrecisionEncoderV2 and DoublePrecisionEncoderV2, ended by a NaN
+        let data = [
+            0x3f, 0xc0, 0x00, 0x00, 0x60, 0x5f, 0xfe, 0xd0, 0x0d, 0x99, 0x75, 0xc3, 0x87, 0xdf,
+            0xc1, 0x23, 0x33, 0x54, 0x20, 0xcc, 0xcc, 0xd0,
+        ];
+        let batch = FloatGorillaDecoder::new()
+            .decode_batch(&mut Cursor::new(&data[..]))
+            .unwrap();
+        assert_eq!(
+            batch,
+            ColumnBatch::FLOAT(vec![1.5, 1.5, -2.25, 3.75, 100.125, 0.1, 0.1])
+        );
+        let data = [
+            0x3f, 0xf8, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x60, 0x1d, 0xff, 0xf6, 0x80, 0x0d,
+            0x92, 0x5d, 0x70, 0xe0, 0xfb, 0xff, 0x82, 0x46, 0x66, 0x66, 0x66, 0x66, 0x6a, 0x80,
+            0x83, 0x33, 0x33, 0x33, 0x33, 0x33, 0x34,
+        ];
+        let batch = DoubleGorillaDecoder::new()
+            .decode_batch(&mut Cursor::new(&data[..]))
+            .unwrap();
+        assert_eq!(
+            batch,
+            ColumnBatch::DOUBLE(vec![1.5, 1.5, -2.25, 3.75, 100.125, 0.1, 0.1])
+        );
+    }
+
+    #[test]
+    fn v1_decodes_pages_of_each_type() {
+        // written by IoTDB's SinglePrecisionEncoderV1: the first value little endian, then
+        // XORs within the zero counts of the previous value and with new ones
+        let data = [
+            0x00, 0x00, 0xc0, 0x3f, 0x60, 0x33, 0xff, 0x68, 0x06, 0xcc, 0x65, 0x70, 0xe1, 0x7f,
+            0xf8, 0x24, 0x66, 0x6b, 0x0b, 0xf0, 0x83, 0x33, 0x33, 0x40,
+        ];
+        let batch = FloatGorillaV1Decoder::new()
+            .decode_batch(&mut Cursor::new(&data[..]))
+            .unwrap();
+        assert_eq!(
+            batch,
+            ColumnBatch::FLOAT(vec![1.5, 1.5, -2.25, 3.75, 100.125, 0.1, 0.1])
+        );
+
+        // DoublePrecisionEncoderV1
+        let data = [
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xf8, 0x3f, 0x60, 0x0f, 0xff, 0xfb, 0x40, 0x06,
+            0xc9, 0x19, 0x5c, 0x38, 0x2f, 0xbf, 0xf0, 0x48, 0xcc, 0xcc, 0xcc, 0xcc, 0xcd, 0x60,
+            0xbe, 0x80, 0x83, 0x33, 0x33, 0x33, 0x33, 0x33, 0x34,
+        ];
+        let batch = DoubleGorillaV1Decoder::new()
+            .decode_batch(&mut Cursor::new(&data[..]))
+            .unwrap();
+        assert_eq!(
+            batch,
+            ColumnBatch::DOUBLE(vec![1.5, 1.5, -2.25, 3.75, 100.125, 0.1, 0.1])
+        );
+    }
+}
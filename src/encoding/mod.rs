@@ -1 +1,7 @@
+pub(crate) mod bits;
+pub mod chimp;
 pub mod decoder;
+pub mod gorilla;
+pub mod rlbe;
+pub mod rle;
+pub mod sprintz;
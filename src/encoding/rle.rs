@@ -0,0 +1,165 @@
+use std::io::Cursor;
+use std::iter;
+
+use byteorder::ReadBytesExt;
+use snafu::{ensure, ResultExt};
+
+use crate::encoding::bits::BitReader;
+use crate::encoding::decoder::{
+    ColumnBatch, CorruptData, Decoder, ReadCursorData, Result, MAX_PAGE_VALUES,
+};
+use crate::utils::cursor::VarIntReader;
+
+/// Values in a bit-packed group.
+const GROUP_SIZE: u32 = 8;
+
+/// Decodes IoTDB's RLE/bit-packing hybrid into raw `bit_width` wide values.
+///
+/// The data is a sequence of blocks, see [`decode_rle_block`].
+fn decode_rle(data: &mut Cursor<&[u8]>) -> Result<Vec<u64>> {
+    let mut result = Vec::new();
+    while data.position() < data.get_ref().len() as u64 {
+        decode_rle_block(data, &mut result)?;
+    }
+    Ok(result)
+}
+
+/// Decodes one RLE block: a varint byte length, a one byte bit width and runs.
+///
+/// A run header with a clear low bit is an RLE run of `header >> 1` copies of one little
+/// endian value; otherwise it is `header >> 1` groups of eight bit-packed values, preceded
+/// by the number of values used in the last group.
+pub(crate) fn decode_rle_block(data: &mut Cursor<&[u8]>, result: &mut Vec<u64>) -> Result<()> {
+    let data_len = data.get_ref().len() as u64;
+    let length = data.read_unsigned_varint_32().context(ReadCursorData)? as u64;
+    let end = data.position() + length;
+    ensure!(
+        end <= data_len,
+        CorruptData {
+            detail: format!("RLE block of {} bytes exceeds page", length)
+        }
+    );
+    let bit_width = data.read_u8().context(ReadCursorData)? as u32;
+    ensure!(
+        bit_width <= 64,
+        CorruptData {
+            detail: format!("RLE bit width {}", bit_width)
+        }
+    );
+
+    while data.position() < end {
+        let header = data.read_unsigned_varint_32().context(ReadCursorData)?;
+        if header & 1 == 0 {
+            let mut value = 0_u64;
+            for i in 0..bit_width.div_ceil(8) {
+                value |= (data.read_u8().context(ReadCursorData)? as u64) << (8 * i);
+            }
+            let run = (header >> 1) as usize;
+            ensure!(
+                result.len() + run <= MAX_PAGE_VALUES,
+                CorruptData {
+                    detail: format!("RLE run of {} values", run)
+                }
+            );
+            result.extend(iter::repeat_n(value, run));
+        } else {
+            let groups = header >> 1;
+            let last = data.read_u8().context(ReadCursorData)? as u32;
+            ensure!(
+                groups > 0,
+                CorruptData {
+                    detail: "empty bit-packed run".to_string()
+                }
+            );
+            let count = (groups as u64 - 1) * GROUP_SIZE as u64 + last as u64;
+            ensure!(
+                result.len() as u64 + count <= MAX_PAGE_VALUES as u64,
+                CorruptData {
+                    detail: format!("bit-packed run of {} values", count)
+                }
+            );
+            let start = data.position();
+            let group_end = (groups as u64)
+                .checked_mul(bit_width as u64)
+                .and_then(|len| start.checked_add(len))
+                .map_or(end, |group_end| group_end.min(end));
+            let mut packed = &data.get_ref()[start as usize..group_end as usize];
+            let mut reader = BitReader::new(&mut packed);
+            for _ in 0..count {
+                result.push(reader.read_bits(bit_width).context(ReadCursorData)?);
+            }
+            data.set_position(group_end);
+        }
+    }
+    Ok(())
+}
+
+pub struct IntRleDecoder {}
+
+impl Decoder for IntRleDecoder {
+    fn new() -> Self {
+        Self {}
+    }
+
+    fn decode_batch(&self, data: &mut Cursor<&[u8]>) -> Result<ColumnBatch> {
+        Ok(ColumnBatch::Int32(
+            decode_rle(data)?
+                .into_iter()
+                .map(|v| v as u32 as i32)
+                .collect(),
+        ))
+    }
+}
+
+pub struct LongRleDecoder {}
+
+impl Decoder for LongRleDecoder {
+    fn new() -> Self {
+        Self {}
+    }
+
+    fn decode_batch(&self, data: &mut Cursor<&[u8]>) -> Result<ColumnBatch> {
+        Ok(ColumnBatch::Int64(
+            decode_rle(data)?.into_iter().map(|v| v as i64).collect(),
+        ))
+    }
+}
+
+pub struct BooleanRleDecoder {}
+
+impl Decoder for BooleanRleDecoder {
+    fn new() -> Self {
+        Self {}
+    }
+
+    fn decode_batch(&self, data: &mut Cursor<&[u8]>) -> Result<ColumnBatch> {
+        Ok(ColumnBatch::Boolean(
+            decode_rle(data)?.into_iter().map(|v| v != 0).collect(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_hybrid_runs() {
+        // 8 byte block of width 3: a run of 4 x 5, then one group with 3 used values
+        let data = vec![8, 3, 0b1000, 5, 0b11, 3, 0b0010_1001, 0b1000_0000, 0];
+        let values = decode_rle(&mut Cursor::new(&data[..])).unwrap();
+        assert_eq!(values, vec![5, 5, 5, 5, 1, 2, 3]);
+    }
+
+    #[test]
+    fn oversized_bit_packed_runs_are_corrupt() {
+        // 2^31 - 1 groups of zero-width values, which take no input, then the same at width 64
+        for width in [0, 64] {
+            let data = [7, width, 0xff, 0xff, 0xff, 0xff, 0x0f, 8];
+            assert!(matches!(
+                decode_rle(&mut Cursor::new(&data[..])),
+                Err(crate::encoding::decoder::Error::CorruptData { .. })
+            ));
+        }
+    }
+}
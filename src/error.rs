@@ -0,0 +1,356 @@
+use std::fmt;
+use std::io;
+use std::sync::{Arc, Mutex};
+
+use snafu::Snafu;
+
+use crate::chunk::reader as chunk_reader;
+#[cfg(feature = "parquet")]
+use crate::convert::to_parquet;
+use crate::encoding::decoder;
+#[cfg(feature = "arrow")]
+use crate::export::arrow_ipc;
+use crate::export::{csv, line_protocol};
+#[cfg(feature = "async")]
+use crate::file::async_reader;
+use crate::file::{
+    compress, footer, metadata, metadata_writer, mods, reader, repair, scanner, statistics,
+    tsfile_search_reader, tsfile_set_reader, upgrade,
+};
+use crate::query::parser;
+use crate::utils::cursor;
+
+/// The error of any module of the crate, with where in the file it happened.
+///
+/// Sources are boxed so results stay small.
+/// Every module error converts into it with `?`; the location starts out empty and is
+/// filled in with `at_offset` and `in_series` as the error travels up.
+#[derive(Debug, Snafu)]
+pub enum TsFileError {
+    #[snafu(display("{}{}", source, location))]
+    Io {
+        location: Location,
+        source: Box<io::Error>,
+    },
+    #[snafu(display("{}{}", source, location))]
+    Read {
+        location: Location,
+        source: Box<reader::Error>,
+    },
+    #[snafu(display("{}{}", source, location))]
+    Cursor {
+        location: Location,
+        source: Box<cursor::Error>,
+    },
+    #[snafu(display("{}{}", source, location))]
+    Footer {
+        location: Location,
+        source: Box<footer::Error>,
+    },
+    #[snafu(display("{}{}", source, location))]
+    Metadata {
+        location: Location,
+        source: Box<metadata::Error>,
+    },
+    #[snafu(display("{}{}", source, location))]
+    Statistics {
+        location: Location,
+        source: Box<statistics::Error>,
+    },
+    #[snafu(display("{}{}", source, location))]
+    Compress {
+        location: Location,
+        source: Box<compress::Error>,
+    },
+    #[snafu(display("{}{}", source, location))]
+    Decode {
+        location: Location,
+        source: Box<decoder::Error>,
+    },
+    #[snafu(display("{}{}", source, location))]
+    Chunk {
+        location: Location,
+        source: Box<chunk_reader::Error>,
+    },
+    #[snafu(display("{}{}", source, location))]
+    Open {
+        location: Location,
+        source: Box<tsfile_search_reader::Error>,
+    },
+    #[snafu(display("{}{}", source, location))]
+    OpenSet {
+        location: Location,
+        source: Box<tsfile_set_reader::Error>,
+    },
+    #[cfg(feature = "async")]
+    #[snafu(display("{}{}", source, location))]
+    AsyncOpen {
+        location: Location,
+        source: Box<async_reader::Error>,
+    },
+    #[snafu(display("{}{}", source, location))]
+    Scan {
+        location: Location,
+        source: Box<scanner::Error>,
+    },
+    #[snafu(display("{}{}", source, location))]
+    WriteMetadata {
+        location: Location,
+        source: Box<metadata_writer::Error>,
+    },
+    #[snafu(display("{}{}", source, location))]
+    Mods {
+        location: Location,
+        source: Box<mods::Error>,
+    },
+    #[snafu(display("{}{}", source, location))]
+    Repair {
+        location: Location,
+        source: Box<repair::Error>,
+    },
+    #[snafu(display("{}{}", source, location))]
+    Upgrade {
+        location: Location,
+        source: Box<upgrade::Error>,
+    },
+    #[snafu(display("{}{}", source, location))]
+    ParseQuery {
+        location: Location,
+        source: Box<parser::Error>,
+    },
+    #[snafu(display("{}{}", source, location))]
+    Csv {
+        location: Location,
+        source: Box<csv::Error>,
+    },
+    #[snafu(display("{}{}", source, location))]
+    LineProtocol {
+        location: Location,
+        source: Box<line_protocol::Error>,
+    },
+    #[cfg(feature = "arrow")]
+    #[snafu(display("{}{}", source, location))]
+    ArrowIpc {
+        location: Location,
+        source: Box<arrow_ipc::Error>,
+    },
+    #[cfg(feature = "parquet")]
+    #[snafu(display("{}{}", source, location))]
+    Parquet {
+        location: Location,
+        source: Box<to_parquet::Error>,
+    },
+}
+
+pub type Result<T, E = TsFileError> = std::result::Result<T, E>;
+
+/// Where an error happened, as far as it is known: a file offset and the device and
+/// sensor being read.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Location {
+    offset: Option<u64>,
+    device: Option<String>,
+    sensor: Option<String>,
+}
+
+impl Location {
+    pub fn offset(&self) -> Option<u64> {
+        self.offset
+    }
+
+    pub fn device(&self) -> Option<&str> {
+        self.device.as_deref()
+    }
+
+    pub fn sensor(&self) -> Option<&str> {
+        self.sensor.as_deref()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.offset.is_none() && self.device.is_none()
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(offset) = self.offset {
+            write!(f, " at offset {}", offset)?;
+        }
+        match (&self.device, &self.sensor) {
+            (Some(device), Some(sensor)) => write!(f, " in {}.{}", device, sensor),
+            (Some(device), None) => write!(f, " in {}", device),
+            _ => Ok(()),
+        }
+    }
+}
+
+macro_rules! from_module_errors {
+    ($($(#[$attr:meta])* $source:ty => $variant:ident,)*) => {
+        $(
+            $(#[$attr])*
+            impl From<$source> for TsFileError {
+                fn from(source: $source) -> Self {
+                    TsFileError::$variant {
+                        location: Location::default(),
+                        source: Box::new(source),
+                    }
+                }
+            }
+        )*
+
+        impl TsFileError {
+            pub fn location(&self) -> &Location {
+                match self {
+                    $($(#[$attr])* TsFileError::$variant { location, .. } => location,)*
+                }
+            }
+
+            fn location_mut(&mut self) -> &mut Location {
+                match self {
+                    $($(#[$attr])* TsFileError::$variant { location, .. } => location,)*
+                }
+            }
+        }
+    };
+}
+
+from_module_errors! {
+    io::Error => Io,
+    reader::Error => Read,
+    cursor::Error => Cursor,
+    footer::Error => Footer,
+    metadata::Error => Metadata,
+    statistics::Error => Statistics,
+    compress::Error => Compress,
+    decoder::Error => Decode,
+    chunk_reader::Error => Chunk,
+    tsfile_search_reader::Error => Open,
+    tsfile_set_reader::Error => OpenSet,
+    #[cfg(feature = "async")]
+    async_reader::Error => AsyncOpen,
+    scanner::Error => Scan,
+    metadata_writer::Error => WriteMetadata,
+    mods::Error => Mods,
+    repair::Error => Repair,
+    upgrade::Error => Upgrade,
+    parser::Error => ParseQuery,
+    csv::Error => Csv,
+    line_protocol::Error => LineProtocol,
+    #[cfg(feature = "arrow")]
+    arrow_ipc::Error => ArrowIpc,
+    #[cfg(feature = "parquet")]
+    to_parquet::Error => Parquet,
+}
+
+impl TsFileError {
+    pub fn offset(&self) -> Option<u64> {
+        self.location().offset
+    }
+
+    /// Records the file offset being read, keeping a more precise one recorded before.
+    pub fn at_offset(mut self, offset: u64) -> Self {
+        self.location_mut().offset.get_or_insert(offset);
+        self
+    }
+
+    /// Records the device and, if known, the sensor being read.
+    pub fn in_series(mut self, device: &str, sensor: Option<&str>) -> Self {
+        let location = self.location_mut();
+        if location.device.is_none() {
+            location.device = Some(device.to_string());
+            location.sensor = sensor.map(str::to_string);
+        }
+        self
+    }
+}
+
+/// `at_offset` and `in_series` for results of any module error.
+pub trait ErrorContext<T> {
+    fn at_offset(self, offset: u64) -> Result<T>;
+
+    fn in_series(self, device: &str, sensor: Option<&str>) -> Result<T>;
+}
+
+impl<T, E: Into<TsFileError>> ErrorContext<T> for std::result::Result<T, E> {
+    fn at_offset(self, offset: u64) -> Result<T> {
+        self.map_err(|e| e.into().at_offset(offset))
+    }
+
+    fn in_series(self, device: &str, sensor: Option<&str>) -> Result<T> {
+        self.map_err(|e| e.into().in_series(device, sensor))
+    }
+}
+
+/// Where a reader in lenient mode records the errors it skips. Readers in strict mode have
+/// none and return the errors instead.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Warnings(Option<Arc<Mutex<Vec<TsFileError>>>>);
+
+impl Warnings {
+    pub(crate) fn lenient() -> Self {
+        Warnings(Some(Arc::default()))
+    }
+
+    /// Warnings of the same mode, without the errors recorded so far.
+    pub(crate) fn fresh(&self) -> Self {
+        Warnings(self.0.as_ref().map(|_| Arc::default()))
+    }
+
+    pub(crate) fn is_lenient(&self) -> bool {
+        self.0.is_some()
+    }
+
+    /// Records an error skipped in lenient mode.
+    pub(crate) fn push(&self, error: TsFileError) {
+        if let Some(warnings) = &self.0 {
+            warnings.lock().unwrap().push(error);
+        }
+    }
+
+    /// The value of `result`, or `None` once its error is recorded. In strict mode the
+    /// error is returned.
+    pub(crate) fn skip<T, E: Into<TsFileError>>(
+        &self,
+        result: std::result::Result<T, E>,
+    ) -> Result<Option<T>> {
+        match result {
+            Ok(value) => Ok(Some(value)),
+            Err(e) if self.is_lenient() => {
+                self.push(e.into());
+                Ok(None)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// The errors recorded since the last call.
+    pub(crate) fn take(&self) -> Vec<TsFileError> {
+        match &self.0 {
+            Some(warnings) => std::mem::take(&mut *warnings.lock().unwrap()),
+            None => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn location_is_kept_from_the_innermost_context() {
+        let result: std::result::Result<(), _> = Err(io::Error::from(io::ErrorKind::UnexpectedEof));
+        let error = result
+            .at_offset(42)
+            .in_series("root.sg.d1", Some("s1"))
+            .at_offset(7)
+            .in_series("root.sg.d2", None)
+            .unwrap_err();
+        assert_eq!(error.offset(), Some(42));
+        assert_eq!(error.location().device(), Some("root.sg.d1"));
+        assert_eq!(
+            error.to_string(),
+            "unexpected end of file at offset 42 in root.sg.d1.s1"
+        );
+        assert!(matches!(error, TsFileError::Io { .. }));
+    }
+}
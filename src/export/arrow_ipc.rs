@@ -0,0 +1,245 @@
+use std::io::Write;
+use std::sync::Arc;
+
+use arrow_array::{
+    ArrayRef, BooleanArray, Float32Array, Float64Array, Int32Array, Int64Array, RecordBatch,
+    StringArray, TimestampMicrosecondArray, TimestampMillisecondArray, TimestampNanosecondArray,
+};
+use arrow_ipc::writer::{FileWriter, StreamWriter};
+use arrow_schema::{ArrowError, DataType, Field as ArrowField, Schema, SchemaRef, TimeUnit};
+use snafu::{ResultExt, Snafu};
+
+use crate::chunk;
+use crate::encoding::decoder::Field;
+use crate::error::TsFileError;
+use crate::file::metadata::TSDataType;
+use crate::file::options::TimestampPrecision;
+use crate::file::path::PathPattern;
+use crate::file::reader::FileReader;
+use crate::query::filter::Filter;
+use crate::query::rows::{DeviceRows, SeriesPoints};
+
+/// Rows per record batch.
+pub const BATCH_SIZE: usize = 8192;
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Unable to read points: {}", source))]
+    ReadPoints { source: chunk::reader::Error },
+    #[snafu(display("Unable to read metadata: {}", source))]
+    ReadMetadata { source: TsFileError },
+    #[snafu(display("Unable to write arrow ipc: {}", source))]
+    WriteArrow { source: ArrowError },
+    #[snafu(display("Series {} has a point of another type: {:?}", path, field))]
+    MismatchedType { path: String, field: Field },
+}
+
+type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IpcFormat {
+    /// The streaming format, readable as it arrives.
+    Stream,
+    /// The random access file format, also known as Feather v2.
+    File,
+}
+
+/// Writes the points of the paths matching `pattern`, restricted to `filter`, as Arrow
+/// IPC: a non-null `time` timestamp column in the reader's precision and a nullable
+/// `device.sensor` column per path, joined on time. Rows are sent in record batches of
+/// `BATCH_SIZE`, so memory use does not grow with the file.
+pub fn write_arrow_ipc<F: FileReader + ?Sized, W: Write>(
+    reader: &F,
+    pattern: &PathPattern,
+    filter: Option<&Filter>,
+    format: IpcFormat,
+    out: W,
+) -> Result<W> {
+    let precision = reader.timestamp_precision();
+    let paths = reader.match_paths(pattern).context(ReadMetadata)?;
+    let columns: Vec<String> = paths
+        .iter()
+        .map(|(device, meta)| format!("{}.{}", device, meta.measurement_id()))
+        .collect();
+    let types: Vec<TSDataType> = paths
+        .iter()
+        .map(|(_, meta)| meta.data_type().clone())
+        .collect();
+    let series = paths
+        .iter()
+        .map(|(device, meta)| {
+            let sensor_reader = reader
+                .get_sensor_reader(device, meta.measurement_id())
+                .context(ReadMetadata)?
+                .map(|mut r| {
+                    if let Some(filter) = filter {
+                        r.set_filter(filter.clone());
+                    }
+                    r
+                });
+            Ok(SeriesPoints::new(sensor_reader))
+        })
+        .collect::<Result<_>>()?;
+    let schema = schema(&columns, &types, precision);
+
+    let mut writer = match format {
+        IpcFormat::Stream => {
+            IpcWriter::Stream(StreamWriter::try_new(out, &schema).context(WriteArrow)?)
+        }
+        IpcFormat::File => IpcWriter::File(FileWriter::try_new(out, &schema).context(WriteArrow)?),
+    };
+    let mut times = Vec::with_capacity(BATCH_SIZE);
+    let mut values: Vec<Vec<Option<Field>>> = vec![Vec::with_capacity(BATCH_SIZE); columns.len()];
+    for row in DeviceRows::from_series(columns.clone(), series, precision) {
+        let (time, row) = row.context(ReadPoints)?;
+        times.push(time);
+        for (column, value) in values.iter_mut().zip(row) {
+            column.push(value);
+        }
+        if times.len() >= BATCH_SIZE {
+            let batch = record_batch(&schema, &columns, &types, &mut times, &mut values)?;
+            writer.write(&batch).context(WriteArrow)?;
+        }
+    }
+    if !times.is_empty() {
+        let batch = record_batch(&schema, &columns, &types, &mut times, &mut values)?;
+        writer.write(&batch).context(WriteArrow)?;
+    }
+    writer.into_inner().context(WriteArrow)
+}
+
+enum IpcWriter<W: Write> {
+    Stream(StreamWriter<W>),
+    File(FileWriter<W>),
+}
+
+impl<W: Write> IpcWriter<W> {
+    fn write(&mut self, batch: &RecordBatch) -> std::result::Result<(), ArrowError> {
+        match self {
+            IpcWriter::Stream(w) => w.write(batch),
+            IpcWriter::File(w) => w.write(batch),
+        }
+    }
+
+    /// Finishes the stream or file and returns the underlying writer.
+    fn into_inner(self) -> std::result::Result<W, ArrowError> {
+        match self {
+            IpcWriter::Stream(w) => w.into_inner(),
+            IpcWriter::File(w) => w.into_inner(),
+        }
+    }
+}
+
+fn schema(columns: &[String], types: &[TSDataType], precision: TimestampPrecision) -> SchemaRef {
+    let unit = match precision {
+        TimestampPrecision::Millisecond => TimeUnit::Millisecond,
+        TimestampPrecision::Microsecond => TimeUnit::Microsecond,
+        TimestampPrecision::Nanosecond => TimeUnit::Nanosecond,
+    };
+    let mut fields = vec![ArrowField::new(
+        "time",
+        DataType::Timestamp(unit, Some("UTC".into())),
+        false,
+    )];
+    for (column, data_type) in columns.iter().zip(types) {
+        let data_type = match data_type {
+            TSDataType::Boolean => DataType::Boolean,
+            TSDataType::Int32 => DataType::Int32,
+            TSDataType::Int64 | TSDataType::VECTOR => DataType::Int64,
+            TSDataType::FLOAT => DataType::Float32,
+            TSDataType::DOUBLE => DataType::Float64,
+            TSDataType::TEXT => DataType::Utf8,
+        };
+        fields.push(ArrowField::new(column, data_type, true));
+    }
+    Arc::new(Schema::new(fields))
+}
+
+/// Moves the buffered rows into a record batch.
+fn record_batch(
+    schema: &SchemaRef,
+    columns: &[String],
+    types: &[TSDataType],
+    times: &mut Vec<i64>,
+    values: &mut [Vec<Option<Field>>],
+) -> Result<RecordBatch> {
+    let times = std::mem::take(times);
+    let time: ArrayRef = match schema.field(0).data_type() {
+        DataType::Timestamp(TimeUnit::Microsecond, tz) => {
+            Arc::new(TimestampMicrosecondArray::from(times).with_timezone_opt(tz.clone()))
+        }
+        DataType::Timestamp(TimeUnit::Nanosecond, tz) => {
+            Arc::new(TimestampNanosecondArray::from(times).with_timezone_opt(tz.clone()))
+        }
+        DataType::Timestamp(_, tz) => {
+            Arc::new(TimestampMillisecondArray::from(times).with_timezone_opt(tz.clone()))
+        }
+        _ => unreachable!("time is a timestamp column"),
+    };
+    let mut arrays = vec![time];
+    for ((column, data_type), values) in columns.iter().zip(types).zip(values) {
+        arrays.push(array(column, data_type, std::mem::take(values))?);
+    }
+    RecordBatch::try_new(schema.clone(), arrays).context(WriteArrow)
+}
+
+fn array(path: &str, data_type: &TSDataType, values: Vec<Option<Field>>) -> Result<ArrayRef> {
+    macro_rules! collect {
+        ($array:ty, $variant:ident, $convert:expr) => {{
+            let mut out = Vec::with_capacity(values.len());
+            for value in values {
+                out.push(match value {
+                    None => None,
+                    Some(Field::$variant(v)) => Some($convert(v)),
+                    Some(field) => {
+                        return MismatchedType {
+                            path: path.to_string(),
+                            field,
+                        }
+                        .fail()
+                    }
+                });
+            }
+            Arc::new(<$array>::from(out)) as ArrayRef
+        }};
+    }
+    Ok(match data_type {
+        TSDataType::Boolean => collect!(BooleanArray, Boolean, |v| v),
+        TSDataType::Int32 => collect!(Int32Array, Int32, |v| v),
+        TSDataType::Int64 | TSDataType::VECTOR => collect!(Int64Array, Int64, |v| v),
+        TSDataType::FLOAT => collect!(Float32Array, FLOAT, |v| v),
+        TSDataType::DOUBLE => collect!(Float64Array, DOUBLE, |v| v),
+        TSDataType::TEXT => collect!(StringArray, TEXT, |v: Vec<u8>| {
+            String::from_utf8_lossy(&v).into_owned()
+        }),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow_ipc::reader::StreamReader;
+
+    #[test]
+    fn stream_roundtrip() {
+        let columns = vec!["root.d.s1".to_string(), "root.d.s2".to_string()];
+        let types = [TSDataType::Int32, TSDataType::TEXT];
+        let schema = schema(&columns, &types, TimestampPrecision::Millisecond);
+        let mut times = vec![1, 2];
+        let mut values = vec![
+            vec![Some(Field::Int32(7)), None],
+            vec![None, Some(Field::from("x"))],
+        ];
+        let batch = record_batch(&schema, &columns, &types, &mut times, &mut values).unwrap();
+
+        let mut writer = StreamWriter::try_new(Vec::new(), &schema).unwrap();
+        writer.write(&batch).unwrap();
+        let data = writer.into_inner().unwrap();
+        let read: Vec<RecordBatch> = StreamReader::try_new(data.as_slice(), None)
+            .unwrap()
+            .map(|b| b.unwrap())
+            .collect();
+        assert_eq!(read, vec![batch]);
+        assert_eq!(read[0].column(1).null_count(), 1);
+    }
+}
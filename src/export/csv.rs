@@ -0,0 +1,211 @@
+use std::io::{self, Write};
+
+use snafu::{ResultExt, Snafu};
+
+use crate::chunk;
+use crate::encoding::decoder::Field;
+use crate::error::TsFileError;
+use crate::file::options::TimestampPrecision;
+use crate::file::path::PathPattern;
+use crate::file::reader::FileReader;
+use crate::query::filter::Filter;
+use crate::query::rows::{DeviceRows, SeriesPoints};
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Unable to read points: {}", source))]
+    ReadPoints { source: chunk::reader::Error },
+    #[snafu(display("Unable to read metadata: {}", source))]
+    ReadMetadata { source: TsFileError },
+    #[snafu(display("Unable to write csv: {}", source))]
+    WriteCsv { source: io::Error },
+}
+
+type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Layout {
+    /// One `time,device,sensor,value` line per point, series after series.
+    Long,
+    /// One line per timestamp with a `device.sensor` column per path, empty where a path
+    /// has no point at that time.
+    Wide,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimeFormat {
+    /// The timestamp as stored, in units of the reader's precision.
+    Epoch,
+    /// An RFC 3339 UTC date and time, e.g. `2021-11-26T02:18:44.311Z`.
+    #[cfg(feature = "chrono")]
+    Rfc3339,
+}
+
+/// How `write_csv` lays out its output; long layout with raw timestamps by default.
+#[derive(Debug, Clone)]
+pub struct CsvOptions {
+    layout: Layout,
+    time_format: TimeFormat,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            layout: Layout::Long,
+            time_format: TimeFormat::Epoch,
+        }
+    }
+}
+
+impl CsvOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    pub fn with_time_format(mut self, time_format: TimeFormat) -> Self {
+        self.time_format = time_format;
+        self
+    }
+
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    pub fn time_format(&self) -> TimeFormat {
+        self.time_format
+    }
+}
+
+/// Writes the points of the paths matching `pattern`, restricted to `filter`, as CSV with
+/// a header line and the layout and time format of `options`. Points are decoded a page at a time, so memory use does not grow with
+/// the file; `out` should be buffered.
+pub fn write_csv<F: FileReader + ?Sized, W: Write>(
+    reader: &F,
+    pattern: &PathPattern,
+    filter: Option<&Filter>,
+    options: &CsvOptions,
+    mut out: W,
+) -> Result<W> {
+    let precision = reader.timestamp_precision();
+    let time = |time: i64| format_time(time, precision, options.time_format);
+    let paths = reader.match_paths(pattern).context(ReadMetadata)?;
+    let series = paths
+        .iter()
+        .map(|(device, meta)| {
+            let sensor_reader = reader
+                .get_sensor_reader(device, meta.measurement_id())
+                .context(ReadMetadata)?
+                .map(|mut r| {
+                    if let Some(filter) = filter {
+                        r.set_filter(filter.clone());
+                    }
+                    r
+                });
+            Ok(SeriesPoints::new(sensor_reader))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    match options.layout {
+        Layout::Long => {
+            writeln!(out, "time,device,sensor,value").context(WriteCsv)?;
+            for ((device, meta), points) in paths.iter().zip(series) {
+                let prefix = format!("{},{}", escape(device), escape(meta.measurement_id()));
+                for point in points {
+                    let (point_time, value) = point.context(ReadPoints)?;
+                    let (time, value) = (time(point_time), format_field(&value));
+                    writeln!(out, "{},{},{}", time, prefix, value).context(WriteCsv)?;
+                }
+            }
+        }
+        Layout::Wide => {
+            let columns: Vec<String> = paths
+                .iter()
+                .map(|(device, meta)| format!("{}.{}", device, meta.measurement_id()))
+                .collect();
+            write!(out, "time").context(WriteCsv)?;
+            for column in &columns {
+                write!(out, ",{}", escape(column)).context(WriteCsv)?;
+            }
+            writeln!(out).context(WriteCsv)?;
+            for row in DeviceRows::from_series(columns, series, precision) {
+                let (row_time, values) = row.context(ReadPoints)?;
+                write!(out, "{}", time(row_time)).context(WriteCsv)?;
+                for value in values {
+                    match value {
+                        Some(value) => write!(out, ",{}", format_field(&value)),
+                        None => write!(out, ","),
+                    }
+                    .context(WriteCsv)?;
+                }
+                writeln!(out).context(WriteCsv)?;
+            }
+        }
+    }
+    Ok(out)
+}
+
+#[cfg_attr(not(feature = "chrono"), allow(unused_variables))]
+fn format_time(time: i64, precision: TimestampPrecision, format: TimeFormat) -> String {
+    match format {
+        TimeFormat::Epoch => time.to_string(),
+        #[cfg(feature = "chrono")]
+        TimeFormat::Rfc3339 => match precision.to_datetime(time) {
+            Some(datetime) => datetime.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true),
+            None => time.to_string(),
+        },
+    }
+}
+
+fn format_field(field: &Field) -> String {
+    match field {
+        Field::Boolean(v) => v.to_string(),
+        Field::Int32(v) => v.to_string(),
+        Field::Int64(v) => v.to_string(),
+        Field::FLOAT(v) => v.to_string(),
+        Field::DOUBLE(v) => v.to_string(),
+        Field::TEXT(v) => escape(&String::from_utf8_lossy(v)),
+    }
+}
+
+/// Quotes `value` if it contains a separator, quote or line break.
+fn escape(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_values_are_quoted() {
+        assert_eq!(format_field(&Field::from("plain")), "plain");
+        assert_eq!(format_field(&Field::from("a,b")), "\"a,b\"");
+        assert_eq!(
+            format_field(&Field::from("say \"hi\"")),
+            "\"say \"\"hi\"\"\""
+        );
+        assert_eq!(format_field(&Field::DOUBLE(1.5)), "1.5");
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn times_in_rfc3339() {
+        let format = |precision| format_time(1637893124311, precision, TimeFormat::Rfc3339);
+        assert_eq!(
+            format(TimestampPrecision::Millisecond),
+            "2021-11-26T02:18:44.311Z"
+        );
+        assert_eq!(
+            format(TimestampPrecision::Microsecond),
+            "1970-01-19T22:58:13.124311Z"
+        );
+    }
+}
@@ -0,0 +1,146 @@
+use std::io::{self, Write};
+
+use snafu::{ResultExt, Snafu};
+
+use crate::chunk;
+use crate::encoding::decoder::Field;
+use crate::error::TsFileError;
+use crate::file::path::PathPattern;
+use crate::file::reader::FileReader;
+use crate::query::filter::Filter;
+use crate::query::rows::{DeviceRows, SeriesPoints};
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Unable to read points: {}", source))]
+    ReadPoints { source: chunk::reader::Error },
+    #[snafu(display("Unable to read metadata: {}", source))]
+    ReadMetadata { source: TsFileError },
+    #[snafu(display("Unable to write line protocol: {}", source))]
+    WriteLines { source: io::Error },
+}
+
+type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Writes the points of the paths matching `pattern`, restricted to `filter`, as InfluxDB
+/// line protocol: one line per device and timestamp, with the device as measurement and
+/// a field per sensor. Timestamps are written in nanoseconds, the default precision of
+/// Influx writes. Non-finite floats, which Influx rejects, are left out.
+pub fn write_line_protocol<F: FileReader + ?Sized, W: Write>(
+    reader: &F,
+    pattern: &PathPattern,
+    filter: Option<&Filter>,
+    mut out: W,
+) -> Result<W> {
+    let precision = reader.timestamp_precision();
+    let nanos_per_unit = 1_000_000_000 / precision.units_per_second();
+    let paths = reader.match_paths(pattern).context(ReadMetadata)?;
+    // the index lists the sensors of a device together
+    let mut start = 0;
+    while start < paths.len() {
+        let device = &paths[start].0;
+        let end = start + paths[start..].iter().take_while(|p| &p.0 == device).count();
+        let (sensors, series): (Vec<_>, Vec<_>) = paths[start..end]
+            .iter()
+            .map(|(_, meta)| {
+                let sensor_reader = reader
+                    .get_sensor_reader(device, meta.measurement_id())
+                    .context(ReadMetadata)?
+                    .map(|mut r| {
+                        if let Some(filter) = filter {
+                            r.set_filter(filter.clone());
+                        }
+                        r
+                    });
+                Ok((
+                    meta.measurement_id().to_string(),
+                    SeriesPoints::new(sensor_reader),
+                ))
+            })
+            .collect::<Result<Vec<_>>>()?
+            .into_iter()
+            .unzip();
+        let measurement = escape(device, &[',', ' ']);
+        let keys: Vec<String> = sensors
+            .iter()
+            .map(|s| escape(s, &[',', '=', ' ']))
+            .collect();
+        for row in DeviceRows::from_series(sensors, series, precision) {
+            let (time, values) = row.context(ReadPoints)?;
+            let fields: Vec<(&str, Field)> = keys
+                .iter()
+                .zip(values)
+                .filter_map(|(key, value)| Some((key.as_str(), value?)))
+                .collect();
+            write_line(&mut out, &measurement, &fields, time * nanos_per_unit)
+                .context(WriteLines)?;
+        }
+        start = end;
+    }
+    Ok(out)
+}
+
+/// Writes one line of already escaped names; nothing if no field has a writable value.
+fn write_line<W: Write>(
+    out: &mut W,
+    measurement: &str,
+    fields: &[(&str, Field)],
+    time: i64,
+) -> io::Result<()> {
+    let mut separator = ' ';
+    for (key, value) in fields {
+        let value = match value {
+            Field::Boolean(v) => v.to_string(),
+            Field::Int32(v) => format!("{}i", v),
+            Field::Int64(v) => format!("{}i", v),
+            Field::FLOAT(v) if v.is_finite() => v.to_string(),
+            Field::DOUBLE(v) if v.is_finite() => v.to_string(),
+            Field::FLOAT(_) | Field::DOUBLE(_) => continue,
+            Field::TEXT(v) => format!("\"{}\"", escape(&String::from_utf8_lossy(v), &['"'])),
+        };
+        if separator == ' ' {
+            write!(out, "{}", measurement)?;
+        }
+        write!(out, "{}{}={}", separator, key, value)?;
+        separator = ',';
+    }
+    if separator == ',' {
+        writeln!(out, " {}", time)?;
+    }
+    Ok(())
+}
+
+/// Backslash-escapes `special` characters and backslashes.
+fn escape(name: &str, special: &[char]) -> String {
+    let mut escaped = String::with_capacity(name.len());
+    for c in name.chars() {
+        if c == '\\' || special.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_per_timestamp() {
+        let mut out = Vec::new();
+        let measurement = escape("root.sg.d 1", &[',', ' ']);
+        let fields = [
+            ("s1", Field::DOUBLE(1.5)),
+            ("s2", Field::Int32(3)),
+            ("s3", Field::from("say \"hi\"")),
+            ("s4", Field::DOUBLE(f64::NAN)),
+        ];
+        write_line(&mut out, &measurement, &fields, 1_000_000).unwrap();
+        write_line(&mut out, &measurement, &fields[3..], 2_000_000).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "root.sg.d\\ 1 s1=1.5,s2=3i,s3=\"say \\\"hi\\\"\" 1000000\n"
+        );
+    }
+}
@@ -0,0 +1,4 @@
+#[cfg(feature = "arrow")]
+pub mod arrow_ipc;
+pub mod csv;
+pub mod line_protocol;
@@ -0,0 +1,288 @@
+use std::future::Future;
+#[cfg(feature = "fs")]
+use std::io::SeekFrom;
+use std::io::{self, Cursor};
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+use snafu::{ensure, ResultExt, Snafu};
+#[cfg(feature = "fs")]
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+#[cfg(feature = "fs")]
+use tokio::sync::Mutex;
+
+use crate::chunk::reader::{check_len, first_read_len, split_chunk, DefaultChunkReader};
+use crate::file::metadata::MetadataIndexNodeType::*;
+use crate::file::metadata::{
+    ChunkMetadata, MetaDataIndexNode, MetadataIndexNodeType, TimeseriesMetadata, TsFileMetadata,
+};
+use crate::file::options::DEFAULT_MAX_ALLOCATION;
+use crate::file::reader::{ChunkReader, PageResult};
+use crate::file::{footer, metadata};
+use crate::{chunk, FOOTER_SIZE};
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Unable to read {} bytes at {}: {}", len, start, source))]
+    ReadSection {
+        start: u64,
+        len: usize,
+        source: io::Error,
+    },
+    #[cfg(feature = "fs")]
+    #[snafu(display("Unable to open file: {}", source))]
+    OpenFile { source: io::Error },
+    #[snafu(display("Invalid TsFile. {}", detail))]
+    InvalidTsFile { detail: String },
+    #[snafu(display("Unable to parser footer: {}", source))]
+    ParserFooter { source: footer::Error },
+    #[snafu(display("Unable to parser metadata: {}", source))]
+    ParserMetadata { source: metadata::Error },
+    #[snafu(display("Unable to parser chunk: {}", source))]
+    ParserChunk { source: chunk::reader::Error },
+}
+
+type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// The async counterpart of `SectionReader`: random access reads of a byte range.
+pub trait AsyncSectionReader: Send + Sync {
+    fn len(&self) -> u64;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn get_bytes(&self, start: u64, len: usize)
+        -> impl Future<Output = io::Result<Vec<u8>>> + Send;
+}
+
+#[cfg(feature = "fs")]
+/// A file read through tokio, so seeks and reads do not block the runtime.
+pub struct AsyncFile {
+    file: Mutex<tokio::fs::File>,
+    len: u64,
+}
+
+#[cfg(feature = "fs")]
+impl AsyncFile {
+    pub async fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = tokio::fs::File::open(path).await?;
+        let len = file.metadata().await?.len();
+        Ok(Self {
+            file: Mutex::new(file),
+            len,
+        })
+    }
+}
+
+#[cfg(feature = "fs")]
+impl AsyncSectionReader for AsyncFile {
+    fn len(&self) -> u64 {
+        self.len
+    }
+
+    async fn get_bytes(&self, start: u64, len: usize) -> io::Result<Vec<u8>> {
+        let mut file = self.file.lock().await;
+        file.seek(SeekFrom::Start(start)).await?;
+        let mut data = vec![0; len];
+        file.read_exact(&mut data).await?;
+        Ok(data)
+    }
+}
+
+/// Reads metadata and chunks of a TsFile through an `AsyncSectionReader`.
+pub struct AsyncTsFileReader<R: AsyncSectionReader> {
+    reader: R,
+    metadata: TsFileMetadata,
+}
+
+#[cfg(feature = "fs")]
+impl AsyncTsFileReader<AsyncFile> {
+    pub async fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::new(AsyncFile::open(path).await.context(OpenFile)?).await
+    }
+}
+
+impl<R: AsyncSectionReader> AsyncTsFileReader<R> {
+    pub async fn new(reader: R) -> Result<Self> {
+        let file_size = reader.len();
+        ensure!(
+            file_size >= (footer::HEAD_SIZE + FOOTER_SIZE) as u64,
+            InvalidTsFile {
+                detail: "Size is smaller than head and footer".to_string()
+            }
+        );
+        let head = read(&reader, 0, footer::HEAD_SIZE).await?;
+        let version = footer::check_head(&head).context(ParserFooter)?;
+        let end_buf = read(&reader, file_size - FOOTER_SIZE as u64, FOOTER_SIZE).await?;
+        let (start, len) = footer::metadata_range(file_size, &end_buf).context(ParserFooter)?;
+        let data = read(&reader, start, len as usize).await?;
+        let metadata = TsFileMetadata::parse(Cursor::new(data), version, start, true)
+            .context(ParserMetadata)?;
+        Ok(Self { reader, metadata })
+    }
+
+    pub fn metadata(&self) -> &TsFileMetadata {
+        &self.metadata
+    }
+
+    /// The timeseries metadata of every sensor of `device`.
+    pub async fn device_metadata(&self, device: &str) -> Result<Vec<TimeseriesMetadata>> {
+        let mut result = Vec::new();
+        let mut stack = match self.measurement_root(device).await? {
+            Some(node) => vec![node],
+            None => return Ok(result),
+        };
+        while let Some(node) = stack.pop() {
+            match node {
+                LeafMeasurement(c) => {
+                    for i in 0..c.children().len() {
+                        result.extend(self.read_timeseries(&c, i).await?);
+                    }
+                }
+                InternalMeasurement(c) => {
+                    for i in (0..c.children().len()).rev() {
+                        stack.push(self.read_child(&c, i).await?);
+                    }
+                }
+                InternalDevice(_) | LeafDevice(_) => {}
+            }
+        }
+        Ok(result)
+    }
+
+    /// The timeseries metadata of `device.sensor`, if the file contains it.
+    pub async fn sensor_metadata(
+        &self,
+        device: &str,
+        sensor: &str,
+    ) -> Result<Option<TimeseriesMetadata>> {
+        let mut node = match self.measurement_root(device).await? {
+            Some(node) => node,
+            None => return Ok(None),
+        };
+        loop {
+            node = match node {
+                InternalMeasurement(c) => match c.floor_child(sensor) {
+                    Some(i) => self.read_child(&c, i).await?,
+                    None => return Ok(None),
+                },
+                LeafMeasurement(c) => {
+                    let found = match c.floor_child(sensor) {
+                        Some(i) => self.read_timeseries(&c, i).await?,
+                        None => Vec::new(),
+                    };
+                    return Ok(found.into_iter().find(|t| t.measurement_id() == sensor));
+                }
+                InternalDevice(_) | LeafDevice(_) => return Ok(None),
+            };
+        }
+    }
+
+    /// A reader over the pages of `chunk`.
+    pub async fn chunk_reader(
+        &self,
+        chunk: &ChunkMetadata,
+    ) -> Result<Box<dyn ChunkReader<Item = PageResult>>> {
+        let offset = chunk.offset_chunk_header() as u64;
+        let len = first_read_len(
+            chunk.offset_chunk_header(),
+            chunk.max_size(),
+            self.reader.len(),
+            DEFAULT_MAX_ALLOCATION,
+        );
+        let mut data = read(&self.reader, offset, len).await?;
+        let (header, header_len, complete) = split_chunk(&mut data).context(ParserChunk)?;
+        if !complete {
+            check_len(
+                "chunk data",
+                header.data_size() as u64,
+                DEFAULT_MAX_ALLOCATION,
+            )
+            .context(ParserChunk)?;
+            data = read(
+                &self.reader,
+                offset + header_len,
+                header.data_size() as usize,
+            )
+            .await?;
+        }
+        Ok(Box::new(
+            DefaultChunkReader::new(
+                Cursor::new(data),
+                header,
+                chunk.statistic(),
+                None,
+                DEFAULT_MAX_ALLOCATION,
+            )
+            .context(ParserChunk)?,
+        ))
+    }
+
+    /// The root of the measurement subtree of `device`.
+    async fn measurement_root(&self, device: &str) -> Result<Option<MetadataIndexNodeType>> {
+        let mut node = self.metadata.file_meta().metadata_index().clone();
+        loop {
+            node = match node {
+                InternalDevice(c) => match c.floor_child(device) {
+                    Some(i) => self.read_child(&c, i).await?,
+                    None => return Ok(None),
+                },
+                LeafDevice(c) => match c.floor_child(device) {
+                    Some(i) if c.children()[i].name() == device => self.read_child(&c, i).await?,
+                    _ => return Ok(None),
+                },
+                measurement => return Ok(Some(measurement)),
+            };
+        }
+    }
+
+    async fn read_child(
+        &self,
+        node: &MetaDataIndexNode,
+        i: usize,
+    ) -> Result<MetadataIndexNodeType> {
+        let data = self.read_range(node, i).await?;
+        MetadataIndexNodeType::with_format(&mut Cursor::new(data), node.child_format())
+            .context(ParserMetadata)
+    }
+
+    async fn read_timeseries(
+        &self,
+        node: &MetaDataIndexNode,
+        i: usize,
+    ) -> Result<Vec<TimeseriesMetadata>> {
+        let data = self.read_range(node, i).await?;
+        let len = data.len() as u64;
+        let mut cursor = Cursor::new(data);
+        let mut result = Vec::new();
+        while cursor.position() < len {
+            result.push(TimeseriesMetadata::new(&mut cursor).context(ParserMetadata)?);
+        }
+        Ok(result)
+    }
+
+    async fn read_range(&self, node: &MetaDataIndexNode, i: usize) -> Result<Vec<u8>> {
+        let (start, end) = node.child_range(i).unwrap_or((0, 0));
+        ensure!(
+            0 <= start && start <= end,
+            InvalidTsFile {
+                detail: format!("index entry range {}..{}", start, end)
+            }
+        );
+        read(&self.reader, start as u64, (end - start) as usize).await
+    }
+}
+
+async fn read<R: AsyncSectionReader>(reader: &R, start: u64, len: usize) -> Result<Vec<u8>> {
+    ensure!(
+        start.saturating_add(len as u64) <= reader.len(),
+        InvalidTsFile {
+            detail: format!("read of {} bytes at {} exceeds file", len, start)
+        }
+    );
+    reader
+        .get_bytes(start, len)
+        .await
+        .context(ReadSection { start, len })
+}
@@ -1,24 +1,579 @@
-use snafu::{ResultExt, Snafu};
+use snafu::{ensure, OptionExt, ResultExt, Snafu};
 use snap::raw::Decoder;
-use std::io::Cursor;
+use std::borrow::Cow;
+use std::io::Read;
+use std::sync::Arc;
+
+use crate::chunk::reader::CompressionType;
+use crate::file::registry;
 
 #[derive(Debug, Snafu)]
 pub enum Error {
     #[snafu(display("Unable to decompress vec: {}", source))]
     DecompressVec { source: snap::Error },
+    #[cfg(feature = "zstd")]
+    #[snafu(display("Unable to decompress vec with zstd: {}", source))]
+    ZstdDecompressVec { source: std::io::Error },
+    #[snafu(display("Corrupt LZO data at byte {}: {}", offset, detail))]
+    LzoCorrupt { offset: usize, detail: String },
+    #[snafu(display("Unable to decompress vec with gzip: {}", source))]
+    GzipDecompressVec { source: std::io::Error },
+    #[snafu(display("Unable to decompress vec with lz4: {}", source))]
+    Lz4DecompressVec {
+        source: lz4_flex::block::DecompressError,
+    },
+    #[snafu(display(
+        "Page decompresses to {} bytes, not the {} its header gives",
+        actual,
+        expected
+    ))]
+    UncompressedSize { expected: usize, actual: usize },
+    #[snafu(display(
+        "Page decompresses to more than the {} bytes its header gives",
+        expected
+    ))]
+    ExceedsUncompressedSize { expected: usize },
+    #[snafu(display("Unable to compress vec: {}", source))]
+    CompressVec { source: snap::Error },
+    #[snafu(display("Unable to compress vec with gzip: {}", source))]
+    GzipCompressVec { source: std::io::Error },
+    #[cfg(feature = "zstd")]
+    #[snafu(display("Unable to compress vec with zstd: {}", source))]
+    ZstdCompressVec { source: std::io::Error },
+    #[snafu(display("Unsupported compression type {:?}", compression_type))]
+    UnsupportedCompression { compression_type: CompressionType },
+    /// The failure of a registered decompressor.
+    #[snafu(display("Unable to decompress page: {}", detail))]
+    Codec { detail: String },
 }
 
 type Result<T, E = Error> = std::result::Result<T, E>;
 
-pub trait Snappy {
-    // fn compress(&self) -> Vec<u8>;
-    fn un_compress(&self) -> Result<Vec<u8>>;
+struct LzoDecoder<'a> {
+    input: &'a [u8],
+    pos: usize,
+    output: Vec<u8>,
+    /// The uncompressed size of the page, which the output may not grow past.
+    limit: usize,
+}
+
+impl<'a> LzoDecoder<'a> {
+    fn corrupt(&self, detail: &str) -> LzoCorrupt<usize, String> {
+        LzoCorrupt {
+            offset: self.pos,
+            detail: detail.to_string(),
+        }
+    }
+
+    fn byte(&mut self) -> Result<usize> {
+        let b = *self
+            .input
+            .get(self.pos)
+            .with_context(|| self.corrupt("unexpected end of input"))?;
+        self.pos += 1;
+        Ok(b as usize)
+    }
+
+    fn le16(&mut self) -> Result<usize> {
+        Ok(self.byte()? | self.byte()? << 8)
+    }
+
+    /// Length extension: every zero byte adds 255, the first non-zero byte ends it.
+    fn extended(&mut self, base: usize) -> Result<usize> {
+        let mut len = base;
+        loop {
+            match self.byte()? {
+                0 => len += 255,
+                b => return Ok(len + b),
+            }
+        }
+    }
+
+    fn reserve(&self, len: usize) -> Result<()> {
+        ensure!(
+            self.output.len() + len <= self.limit,
+            ExceedsUncompressedSize {
+                expected: self.limit
+            }
+        );
+        Ok(())
+    }
+
+    fn literals(&mut self, len: usize) -> Result<()> {
+        let end = self.pos + len;
+        ensure!(
+            end <= self.input.len(),
+            self.corrupt("literal run past input")
+        );
+        self.reserve(len)?;
+        self.output.extend_from_slice(&self.input[self.pos..end]);
+        self.pos = end;
+        Ok(())
+    }
+
+    fn back_copy(&mut self, distance: usize, len: usize) -> Result<()> {
+        ensure!(
+            distance > 0 && distance <= self.output.len(),
+            self.corrupt("match distance before start of output")
+        );
+        self.reserve(len)?;
+        let start = self.output.len() - distance;
+        // Matches may overlap their own output, so copy byte by byte.
+        for i in start..start + len {
+            let b = self.output[i];
+            self.output.push(b);
+        }
+        Ok(())
+    }
+
+    fn decode(mut self) -> Result<Vec<u8>> {
+        // Number of literals copied by the previous instruction, 4 meaning "4 or more".
+        let mut state = 0;
+        if self.input.first().is_some_and(|&b| b > 17) {
+            let len = self.byte()? - 17;
+            self.literals(len)?;
+            state = len.min(4);
+        }
+
+        loop {
+            let inst = self.byte()?;
+            let (distance, len, next) = if inst >= 64 {
+                // M2: 3..8 bytes within 2 KB.
+                let distance = (self.byte()? << 3) + ((inst >> 2) & 7) + 1;
+                (distance, (inst >> 5) + 1, inst & 3)
+            } else if inst >= 32 {
+                // M3: within 16 KB.
+                let len = match inst & 31 {
+                    0 => self.extended(31)?,
+                    l => l,
+                } + 2;
+                let d = self.le16()?;
+                ((d >> 2) + 1, len, d & 3)
+            } else if inst >= 16 {
+                // M4: 16..48 KB away; a zero distance marks the end of the stream.
+                let len = match inst & 7 {
+                    0 => self.extended(7)?,
+                    l => l,
+                } + 2;
+                let d = self.le16()?;
+                let distance = ((inst & 8) << 11) + (d >> 2);
+                if distance == 0 {
+                    break;
+                }
+                (distance + 16384, len, d & 3)
+            } else if state == 0 {
+                // Long literal run.
+                let len = match inst {
+                    0 => self.extended(15)?,
+                    l => l,
+                } + 3;
+                self.literals(len)?;
+                state = 4;
+                continue;
+            } else if state < 4 {
+                // M1: 2 bytes within 1 KB.
+                ((inst >> 2) + (self.byte()? << 2) + 1, 2, inst & 3)
+            } else {
+                // M1 after a literal run: 3 bytes 2..3 KB away.
+                ((inst >> 2) + (self.byte()? << 2) + 2049, 3, inst & 3)
+            };
+            self.back_copy(distance, len)?;
+            self.literals(next)?;
+            state = next;
+        }
+
+        Ok(self.output)
+    }
+}
+
+/// Decompresses one page body, selected from a chunk header's compression type.
+pub trait Decompressor: Send + Sync {
+    fn decompress<'a>(&self, data: &'a [u8], uncompressed_size: usize) -> Result<Cow<'a, [u8]>>;
+
+    /// Decompresses into `out`, replacing its contents and reusing its allocation.
+    fn decompress_into(
+        &self,
+        data: &[u8],
+        uncompressed_size: usize,
+        out: &mut Vec<u8>,
+    ) -> Result<()> {
+        let page = self.decompress(data, uncompressed_size)?;
+        out.clear();
+        out.extend_from_slice(&page);
+        Ok(())
+    }
+}
+
+impl<D: Decompressor + ?Sized> Decompressor for Arc<D> {
+    fn decompress<'a>(&self, data: &'a [u8], uncompressed_size: usize) -> Result<Cow<'a, [u8]>> {
+        (**self).decompress(data, uncompressed_size)
+    }
+
+    fn decompress_into(
+        &self,
+        data: &[u8],
+        uncompressed_size: usize,
+        out: &mut Vec<u8>,
+    ) -> Result<()> {
+        (**self).decompress_into(data, uncompressed_size, out)
+    }
+}
+
+/// The decompressor registered for `compression_type`, or the built-in one.
+pub fn decompressor(compression_type: &CompressionType) -> Result<Box<dyn Decompressor>> {
+    if let Some(codec) = registry::decompressor(compression_type.id()) {
+        return Ok(Box::new(codec));
+    }
+    match compression_type {
+        CompressionType::Uncompressed => Ok(Box::new(NoDecompressor)),
+        CompressionType::Snappy => Ok(Box::new(SnappyDecompressor)),
+        CompressionType::Gzip => Ok(Box::new(GzipDecompressor)),
+        CompressionType::Lzo => Ok(Box::new(LzoDecompressor)),
+        CompressionType::LZ4 => Ok(Box::new(Lz4Decompressor)),
+        #[cfg(feature = "zstd")]
+        CompressionType::ZSTD => Ok(Box::new(ZstdDecompressor)),
+        other => UnsupportedCompression {
+            compression_type: other.clone(),
+        }
+        .fail(),
+    }
+}
+
+/// Passes the page body through without copying.
+pub struct NoDecompressor;
+
+impl Decompressor for NoDecompressor {
+    fn decompress<'a>(&self, data: &'a [u8], _: usize) -> Result<Cow<'a, [u8]>> {
+        Ok(Cow::Borrowed(data))
+    }
+}
+
+pub struct SnappyDecompressor;
+
+impl Decompressor for SnappyDecompressor {
+    fn decompress<'a>(&self, data: &'a [u8], uncompressed_size: usize) -> Result<Cow<'a, [u8]>> {
+        let mut out = Vec::new();
+        self.decompress_into(data, uncompressed_size, &mut out)?;
+        Ok(Cow::Owned(out))
+    }
+
+    /// Fails before allocating if the length the data starts with is not the page's.
+    fn decompress_into(
+        &self,
+        data: &[u8],
+        uncompressed_size: usize,
+        out: &mut Vec<u8>,
+    ) -> Result<()> {
+        let len = snap::raw::decompress_len(data).context(DecompressVec)?;
+        ensure!(
+            len == uncompressed_size,
+            UncompressedSize {
+                expected: uncompressed_size,
+                actual: len
+            }
+        );
+        out.clear();
+        out.resize(len, 0);
+        let len = Decoder::new()
+            .decompress(data, out)
+            .context(DecompressVec)?;
+        out.truncate(len);
+        Ok(())
+    }
+}
+
+pub struct GzipDecompressor;
+
+impl Decompressor for GzipDecompressor {
+    fn decompress<'a>(&self, data: &'a [u8], uncompressed_size: usize) -> Result<Cow<'a, [u8]>> {
+        let mut out = Vec::with_capacity(uncompressed_size);
+        self.decompress_into(data, uncompressed_size, &mut out)?;
+        Ok(Cow::Owned(out))
+    }
+
+    fn decompress_into(
+        &self,
+        data: &[u8],
+        uncompressed_size: usize,
+        out: &mut Vec<u8>,
+    ) -> Result<()> {
+        out.clear();
+        // one byte more than the page shows it does not end there
+        flate2::read::GzDecoder::new(data)
+            .take(uncompressed_size as u64 + 1)
+            .read_to_end(out)
+            .context(GzipDecompressVec)?;
+        ensure!(
+            out.len() <= uncompressed_size,
+            ExceedsUncompressedSize {
+                expected: uncompressed_size
+            }
+        );
+        Ok(())
+    }
+}
+
+/// Raw LZO1X blocks, as written by the Java `lzo1x` compressor for `CompressionType::Lzo`.
+/// The stream carries no header, so its end is bounded by the page's uncompressed size.
+pub struct LzoDecompressor;
+
+impl Decompressor for LzoDecompressor {
+    fn decompress<'a>(&self, data: &'a [u8], uncompressed_size: usize) -> Result<Cow<'a, [u8]>> {
+        let mut out = Vec::with_capacity(uncompressed_size);
+        self.decompress_into(data, uncompressed_size, &mut out)?;
+        Ok(Cow::Owned(out))
+    }
+
+    fn decompress_into(
+        &self,
+        data: &[u8],
+        uncompressed_size: usize,
+        out: &mut Vec<u8>,
+    ) -> Result<()> {
+        let mut output = std::mem::take(out);
+        output.clear();
+        *out = LzoDecoder {
+            input: data,
+            pos: 0,
+            output,
+            limit: uncompressed_size,
+        }
+        .decode()?;
+        Ok(())
+    }
 }
 
-impl Snappy for Cursor<Vec<u8>> {
-    fn un_compress(&self) -> Result<Vec<u8>> {
-        Decoder::new()
-            .decompress_vec(self.get_ref())
-            .context(DecompressVec)
+/// Raw LZ4 blocks, as written by lz4-java's fast compressor.
+pub struct Lz4Decompressor;
+
+impl Decompressor for Lz4Decompressor {
+    fn decompress<'a>(&self, data: &'a [u8], uncompressed_size: usize) -> Result<Cow<'a, [u8]>> {
+        Ok(Cow::Owned(
+            lz4_flex::block::decompress(data, uncompressed_size).context(Lz4DecompressVec)?,
+        ))
+    }
+
+    fn decompress_into(
+        &self,
+        data: &[u8],
+        uncompressed_size: usize,
+        out: &mut Vec<u8>,
+    ) -> Result<()> {
+        out.clear();
+        out.resize(uncompressed_size, 0);
+        let len = lz4_flex::block::decompress_into(data, out).context(Lz4DecompressVec)?;
+        out.truncate(len);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "zstd")]
+pub struct ZstdDecompressor;
+
+#[cfg(feature = "zstd")]
+impl Decompressor for ZstdDecompressor {
+    fn decompress<'a>(&self, data: &'a [u8], uncompressed_size: usize) -> Result<Cow<'a, [u8]>> {
+        let mut out = Vec::with_capacity(uncompressed_size);
+        self.decompress_into(data, uncompressed_size, &mut out)?;
+        Ok(Cow::Owned(out))
+    }
+
+    fn decompress_into(
+        &self,
+        data: &[u8],
+        uncompressed_size: usize,
+        out: &mut Vec<u8>,
+    ) -> Result<()> {
+        out.clear();
+        // one byte more than the page shows it does not end there
+        zstd::stream::read::Decoder::new(data)
+            .context(ZstdDecompressVec)?
+            .take(uncompressed_size as u64 + 1)
+            .read_to_end(out)
+            .context(ZstdDecompressVec)?;
+        ensure!(
+            out.len() <= uncompressed_size,
+            ExceedsUncompressedSize {
+                expected: uncompressed_size
+            }
+        );
+        Ok(())
+    }
+}
+
+/// Compresses one page body, the counterpart of `Decompressor` for the writers.
+pub trait Compressor: Send + Sync {
+    fn compress<'a>(&self, data: &'a [u8]) -> Result<Cow<'a, [u8]>>;
+}
+
+/// The built-in compressor for `compression_type`; LZO and the compressions only
+/// registered decompressors read have none.
+pub fn compressor(compression_type: &CompressionType) -> Result<Box<dyn Compressor>> {
+    match compression_type {
+        CompressionType::Uncompressed => Ok(Box::new(NoCompressor)),
+        CompressionType::Snappy => Ok(Box::new(SnappyCompressor)),
+        CompressionType::Gzip => Ok(Box::new(GzipCompressor)),
+        CompressionType::LZ4 => Ok(Box::new(Lz4Compressor)),
+        #[cfg(feature = "zstd")]
+        CompressionType::ZSTD => Ok(Box::new(ZstdCompressor::default())),
+        other => UnsupportedCompression {
+            compression_type: other.clone(),
+        }
+        .fail(),
+    }
+}
+
+/// Passes the page body through without copying.
+pub struct NoCompressor;
+
+impl Compressor for NoCompressor {
+    fn compress<'a>(&self, data: &'a [u8]) -> Result<Cow<'a, [u8]>> {
+        Ok(Cow::Borrowed(data))
+    }
+}
+
+pub struct SnappyCompressor;
+
+impl Compressor for SnappyCompressor {
+    fn compress<'a>(&self, data: &'a [u8]) -> Result<Cow<'a, [u8]>> {
+        let out = snap::raw::Encoder::new()
+            .compress_vec(data)
+            .context(CompressVec)?;
+        Ok(Cow::Owned(out))
+    }
+}
+
+pub struct GzipCompressor;
+
+impl Compressor for GzipCompressor {
+    fn compress<'a>(&self, data: &'a [u8]) -> Result<Cow<'a, [u8]>> {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        std::io::Write::write_all(&mut encoder, data).context(GzipCompressVec)?;
+        Ok(Cow::Owned(encoder.finish().context(GzipCompressVec)?))
+    }
+}
+
+/// Writes a raw LZ4 block, without the length prefix, as the decompressor reads it.
+pub struct Lz4Compressor;
+
+impl Compressor for Lz4Compressor {
+    fn compress<'a>(&self, data: &'a [u8]) -> Result<Cow<'a, [u8]>> {
+        Ok(Cow::Owned(lz4_flex::block::compress(data)))
+    }
+}
+
+#[cfg(feature = "zstd")]
+pub struct ZstdCompressor {
+    level: i32,
+}
+
+#[cfg(feature = "zstd")]
+impl ZstdCompressor {
+    /// A compressor at zstd `level`, 0 being zstd's default.
+    pub fn new(level: i32) -> Self {
+        Self { level }
+    }
+}
+
+#[cfg(feature = "zstd")]
+impl Default for ZstdCompressor {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+#[cfg(feature = "zstd")]
+impl Compressor for ZstdCompressor {
+    fn compress<'a>(&self, data: &'a [u8]) -> Result<Cow<'a, [u8]>> {
+        let out = zstd::stream::encode_all(data, self.level).context(ZstdCompressVec)?;
+        Ok(Cow::Owned(out))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lzo_literals_and_match() {
+        // "abcd" as an initial literal run, an M2 copy of 8 bytes at distance 4, end marker
+        let data = vec![21, b'a', b'b', b'c', b'd', 0b1110_1100, 0, 0x11, 0, 0];
+        let out = LzoDecompressor.decompress(&data, 12).unwrap();
+        assert_eq!(out, &b"abcdabcdabcd"[..]);
+    }
+
+    #[test]
+    fn output_is_bounded_by_the_uncompressed_size() {
+        let page = b"abcdabcdabcd";
+        let lzo = [21, b'a', b'b', b'c', b'd', 0b1110_1100, 0, 0x11, 0, 0];
+        assert!(matches!(
+            LzoDecompressor.decompress(&lzo, 10),
+            Err(Error::ExceedsUncompressedSize { expected: 10 })
+        ));
+
+        let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        std::io::Write::write_all(&mut gzip, page).unwrap();
+        let gzip = gzip.finish().unwrap();
+        assert_eq!(GzipDecompressor.decompress(&gzip, 12).unwrap(), &page[..]);
+        assert!(matches!(
+            GzipDecompressor.decompress(&gzip, 11),
+            Err(Error::ExceedsUncompressedSize { expected: 11 })
+        ));
+
+        #[cfg(feature = "zstd")]
+        {
+            let zstd = zstd::stream::encode_all(&page[..], 0).unwrap();
+            assert_eq!(ZstdDecompressor.decompress(&zstd, 12).unwrap(), &page[..]);
+            assert!(matches!(
+                ZstdDecompressor.decompress(&zstd, 11),
+                Err(Error::ExceedsUncompressedSize { expected: 11 })
+            ));
+        }
+
+        let snappy = snap::raw::Encoder::new().compress_vec(page).unwrap();
+        let mut out = Vec::new();
+        assert!(matches!(
+            SnappyDecompressor.decompress_into(&snappy, 13, &mut out),
+            Err(Error::UncompressedSize {
+                expected: 13,
+                actual: 12
+            })
+        ));
+    }
+
+    #[test]
+    fn compressed_pages_decompress() {
+        let page = b"abcdabcdabcdabcdabcdabcd".repeat(8);
+        let mut types = vec![
+            CompressionType::Uncompressed,
+            CompressionType::Snappy,
+            CompressionType::Gzip,
+            CompressionType::LZ4,
+        ];
+        if cfg!(feature = "zstd") {
+            types.push(CompressionType::ZSTD);
+        }
+        for compression_type in types {
+            let data = compressor(&compression_type)
+                .unwrap()
+                .compress(&page)
+                .unwrap();
+            let out = decompressor(&compression_type)
+                .unwrap()
+                .decompress(&data, page.len())
+                .unwrap();
+            assert_eq!(out, &page[..], "{:?}", compression_type);
+        }
+        assert!(compressor(&CompressionType::Lzo).is_err());
+    }
+
+    #[test]
+    fn uncompressed_is_borrowed() {
+        let data = vec![1, 2, 3];
+        let out = decompressor(&CompressionType::Uncompressed)
+            .unwrap()
+            .decompress(&data, 3)
+            .unwrap();
+        assert!(matches!(out, Cow::Borrowed(_)));
     }
 }
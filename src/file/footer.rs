@@ -1,11 +1,10 @@
-use std::io::{Cursor, Read};
-
 use byteorder::{BigEndian, ByteOrder};
 use snafu::{ensure, ResultExt};
 
 use crate::file::metadata::TsFileMetadata;
+use crate::file::reader;
 use crate::file::reader::SectionReader;
-use crate::FOOTER_SIZE;
+use crate::{FOOTER_SIZE, MAGIC_STRING, VERSION_NUMBER, VERSION_NUMBER_V4};
 
 use crate::file::metadata;
 use snafu::Snafu;
@@ -16,26 +15,113 @@ pub enum Error {
     ParserMetadata { source: metadata::Error },
     #[snafu(display("Invalid TsFile. {}", detail))]
     InvalidTsFile { detail: String },
+    #[snafu(display("Unsupported TsFile version {:#04x}", found))]
+    UnsupportedVersion { found: u8 },
     #[snafu(display("Unable to read cursor: {}", source))]
     ReadCursorData { source: std::io::Error },
+    #[snafu(display("Unable to read section: {}", source))]
+    ReadSection { source: reader::Error },
 }
 
 type Result<T, E = Error> = std::result::Result<T, E>;
 
-pub fn parser_metadata<R: SectionReader>(reader: &R) -> Result<TsFileMetadata> {
+/// The magic string followed by the version byte at the start of a file.
+pub(crate) const HEAD_SIZE: usize = MAGIC_STRING.len() + 1;
+
+/// Parses the metadata of a file, leaving out the bloom filter unless `bloom_filter` is set.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip_all, fields(file_size = reader.len()))
+)]
+pub fn parser_metadata_with_bloom_filter<R: SectionReader>(
+    reader: &R,
+    bloom_filter: bool,
+) -> Result<TsFileMetadata> {
+    let (version, footer_metadata_pos, metadata_len) = metadata_section(reader)?;
+    trace_event!(bytes = metadata_len, "read metadata");
+
+    let data = reader
+        .get_cursor(footer_metadata_pos, metadata_len as usize)
+        .context(ReadSection)?;
+    TsFileMetadata::parse(data, version, footer_metadata_pos, bloom_filter).context(ParserMetadata)
+}
+
+/// Bytes of the metadata first read by `parser_metadata_index`.
+const INDEX_WINDOW: usize = 64 * 1024;
+
+/// Like `parser_metadata_with_bloom_filter`, reading only the start of the metadata: the root of the index,
+/// the table schemas of version 4 files and the meta offset. Reads start at 64 KiB and
+/// double until they hold all of it, so opening a file costs memory in proportion to the
+/// root rather than to the whole metadata, which the bloom filter of a file with millions
+/// of series makes large. The bloom filter and properties are left out.
+pub fn parser_metadata_index<R: SectionReader>(reader: &R) -> Result<TsFileMetadata> {
+    parser_metadata_index_from(reader, INDEX_WINDOW)
+}
+
+fn parser_metadata_index_from<R: SectionReader>(
+    reader: &R,
+    window: usize,
+) -> Result<TsFileMetadata> {
+    let (version, position, metadata_len) = metadata_section(reader)?;
+    let metadata_len = metadata_len as usize;
+    let mut window = window.max(1).min(metadata_len);
+    loop {
+        trace_event!(bytes = window, "read metadata index");
+        let mut data = reader.get_cursor(position, window).context(ReadSection)?;
+        match TsFileMetadata::parse_index(&mut data, version, position) {
+            Ok(metadata) => return Ok(metadata),
+            // the window may have cut the index short
+            Err(_) if window < metadata_len => window = (window * 2).min(metadata_len),
+            Err(e) => return Err(e).context(ParserMetadata),
+        }
+    }
+}
+
+/// Checks the head and footer of a file and returns its version with the position and
+/// length of the serialized `TsFileMetadata`.
+fn metadata_section<R: SectionReader>(reader: &R) -> Result<(u8, u64, i32)> {
     let file_size = reader.len();
     ensure!(
-        file_size >= (FOOTER_SIZE as u64),
+        file_size >= (HEAD_SIZE + FOOTER_SIZE) as u64,
         InvalidTsFile {
-            detail: "Size is smaller than footer".to_string()
+            detail: "Size is smaller than head and footer".to_string()
         }
     );
 
-    let mut result = reader.get_read(file_size - FOOTER_SIZE as u64, FOOTER_SIZE);
+    let head = reader
+        .get_cursor(0, HEAD_SIZE)
+        .context(ReadSection)?
+        .into_inner();
+    let version = check_head(&head)?;
+
+    let end_buf = reader
+        .get_cursor(file_size - FOOTER_SIZE as u64, FOOTER_SIZE)
+        .context(ReadSection)?
+        .into_inner();
+    let (position, metadata_len) = metadata_range(file_size, &end_buf)?;
+    Ok((version, position, metadata_len))
+}
 
-    let mut end_buf = vec![0; FOOTER_SIZE];
-    result.read_exact(&mut end_buf).context(ReadCursorData)?;
+/// Validates the magic string and version byte of the first `HEAD_SIZE` bytes of a file
+/// and returns the version.
+pub(crate) fn check_head(head: &[u8]) -> Result<u8> {
+    ensure!(
+        &head[..MAGIC_STRING.len()] == MAGIC_STRING.as_bytes(),
+        InvalidTsFile {
+            detail: "Corrupt head".to_string()
+        }
+    );
+    let found = head[MAGIC_STRING.len()];
+    ensure!(
+        found == VERSION_NUMBER || found == VERSION_NUMBER_V4,
+        UnsupportedVersion { found }
+    );
+    Ok(found)
+}
 
+/// Validates the footer, the last `FOOTER_SIZE` bytes of a file of `file_size` bytes, and
+/// returns the position and length of the serialized `TsFileMetadata`.
+pub(crate) fn metadata_range(file_size: u64, end_buf: &[u8]) -> Result<(u64, i32)> {
     ensure!(
         end_buf[4..] == [b'T', b's', b'F', b'i', b'l', b'e'],
         InvalidTsFile {
@@ -45,24 +131,155 @@ pub fn parser_metadata<R: SectionReader>(reader: &R) -> Result<TsFileMetadata> {
 
     let metadata_len = BigEndian::read_i32(&end_buf[0..4]);
     ensure!(
-        metadata_len >= 0,
+        metadata_len >= 0 && metadata_len as u64 <= file_size - FOOTER_SIZE as u64,
         InvalidTsFile {
-            detail: " Metadata length is less than zero".to_string()
+            detail: "Invalid metadata length".to_string()
         }
     );
 
-    let footer_metadata_pos = file_size - FOOTER_SIZE as u64 - metadata_len as u64;
+    Ok((
+        file_size - FOOTER_SIZE as u64 - metadata_len as u64,
+        metadata_len,
+    ))
+}
 
-    let metadata_reader = reader.get_read(footer_metadata_pos, metadata_len as usize);
-    let mut metadata_reader = Box::new(metadata_reader);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::VERSION_NUMBER_V2;
 
-    let mut data = vec![0; metadata_len as usize];
-    let result = metadata_reader.read(&mut data).context(ReadCursorData)?;
-    ensure!(
-        result == metadata_len as usize,
-        InvalidTsFile {
-            detail: "TsFileMetadata error".to_string()
+    #[test]
+    fn version_2_head_is_unsupported() {
+        let mut file = format!("{}{}", MAGIC_STRING, VERSION_NUMBER_V2).into_bytes();
+        file.extend([0, 0, 0, 0]);
+        file.extend(MAGIC_STRING.as_bytes());
+        let error = parser_metadata_with_bloom_filter(&bytes::Bytes::from(file), true).unwrap_err();
+        assert!(matches!(error, Error::UnsupportedVersion { found: b'0' }));
+    }
+
+    #[test]
+    fn version_4_metadata_is_parsed() {
+        use crate::file::metadata::{ColumnCategory, EntryFormat, MetadataIndexNodeType};
+        use std::io::Cursor;
+
+        let varint_string = |s: &str, out: &mut Vec<u8>| {
+            out.push(s.len() as u8 * 2);
+            out.extend(s.as_bytes());
+        };
+        let mut file = MAGIC_STRING.as_bytes().to_vec();
+        file.push(VERSION_NUMBER_V4);
+        let position = file.len() as u64;
+        let mut metadata = vec![1];
+        varint_string("root.sg", &mut metadata);
+        let root = position + metadata.len() as u64;
+        // a device leaf with the device id ["root.sg", "d1"]
+        metadata.extend([1, 2]);
+        varint_string("root.sg", &mut metadata);
+        varint_string("d1", &mut metadata);
+        metadata.extend(7_i64.to_be_bytes());
+        metadata.extend(9_i64.to_be_bytes());
+        metadata.push(1);
+        // a table with a single tag column
+        metadata.push(1);
+        varint_string("t1", &mut metadata);
+        metadata.push(1);
+        metadata.extend(3_i32.to_be_bytes());
+        metadata.extend(b"tag");
+        metadata.extend([5, 0, 0]);
+        metadata.extend(0_i32.to_be_bytes());
+        metadata.extend(0_i32.to_be_bytes());
+        metadata.extend(5_i64.to_be_bytes());
+        // an empty bloom filter, then a property
+        metadata.extend([0, 0, 0, 2]);
+        varint_string("k", &mut metadata);
+        varint_string("v", &mut metadata);
+        file.extend(&metadata);
+        file.extend((metadata.len() as i32).to_be_bytes());
+        file.extend(MAGIC_STRING.as_bytes());
+
+        let metadata =
+            parser_metadata_with_bloom_filter(&bytes::Bytes::from(file.clone()), true).unwrap();
+        let file_meta = metadata.file_meta();
+        assert_eq!(file_meta.version(), VERSION_NUMBER_V4);
+        let table = &file_meta.tables()[0];
+        assert_eq!((table.name(), table.columns()[0].name()), ("t1", "tag"));
+        assert_eq!(table.columns()[0].category(), ColumnCategory::Tag);
+        assert_eq!(file_meta.properties(), [("k".to_string(), "v".to_string())]);
+        match file_meta.metadata_index() {
+            MetadataIndexNodeType::InternalDevice(node) => {
+                assert_eq!(node.children()[0].name(), "root.sg");
+                assert_eq!(node.children()[0].offset(), root as i64);
+            }
+            node => panic!("unexpected node {:?}", node),
         }
-    );
-    TsFileMetadata::parser(Cursor::new(data)).context(ParserMetadata)
+
+        let mut cursor = Cursor::new(file[root as usize..].to_vec());
+        match MetadataIndexNodeType::with_format(&mut cursor, EntryFormat::DeviceId).unwrap() {
+            MetadataIndexNodeType::LeafDevice(node) => {
+                assert_eq!(node.children()[0].name(), "root.sg.d1")
+            }
+            node => panic!("unexpected node {:?}", node),
+        }
+    }
+
+    #[test]
+    fn index_is_read_without_the_bloom_filter() {
+        use crate::file::metadata::MetadataIndexNodeType;
+        use crate::file::reader::Length;
+        use crate::file::tsfile_search_reader::test_file;
+        use std::io::Cursor;
+        use std::sync::Mutex;
+
+        /// Records the length of every read.
+        struct Recorded(bytes::Bytes, Mutex<Vec<usize>>);
+
+        impl Length for Recorded {
+            fn len(&self) -> u64 {
+                Length::len(&self.0)
+            }
+        }
+
+        impl SectionReader for Recorded {
+            type T = Cursor<bytes::Bytes>;
+
+            fn get_read(&self, start: u64, len: usize) -> Self::T {
+                self.1.lock().unwrap().push(len);
+                self.0.get_read(start, len)
+            }
+
+            fn get_cursor(&self, start: u64, len: usize) -> reader::Result<Cursor<Vec<u8>>> {
+                self.1.lock().unwrap().push(len);
+                self.0.get_cursor(start, len)
+            }
+        }
+
+        let mut file = test_file(&[("root.sg.d1", "s1", &[1]), ("root.sg.d2", "s1", &[1])]);
+        // a bloom filter of 100000 bytes after the meta offset
+        let footer = file.split_off(file.len() - FOOTER_SIZE);
+        let metadata_len = BigEndian::read_i32(&footer[..4]) as usize;
+        file.extend([0xa0, 0x8d, 0x06]);
+        file.extend(vec![0; 100_000]);
+        file.extend([0x80, 0x08, 5]);
+        file.extend(((metadata_len + 100_006) as i32).to_be_bytes());
+        file.extend(MAGIC_STRING.as_bytes());
+        let file = bytes::Bytes::from(file);
+
+        let whole = parser_metadata_with_bloom_filter(&file, true).unwrap();
+        assert!(whole.file_meta().bloom_filter().is_some());
+        let reader = Recorded(file, Mutex::default());
+        let index = parser_metadata_index_from(&reader, 16).unwrap();
+        assert!(index.file_meta().bloom_filter().is_none());
+        assert_eq!(
+            index.file_meta().meta_offset(),
+            whole.file_meta().meta_offset()
+        );
+        let devices = |node: &MetadataIndexNodeType| match node {
+            MetadataIndexNodeType::LeafDevice(node) => node.children().len(),
+            node => panic!("unexpected node {:?}", node),
+        };
+        assert_eq!(devices(index.file_meta().metadata_index()), 2);
+        // windows of 16, 32 and 64 bytes after the head and footer
+        let reads = reader.1.into_inner().unwrap();
+        assert_eq!(reads[2..], [16, 32, 64]);
+    }
 }
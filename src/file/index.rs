@@ -0,0 +1,185 @@
+//! Walking the metadata index tree of a file, see `TsFileSearchReader::visit_index`.
+
+use std::fmt::Write;
+
+use crate::file::metadata::MetadataIndexNodeType::{self, *};
+use crate::file::metadata::{MetadataIndexEntry, TimeseriesMetadata};
+
+/// Receives the nodes, entries and timeseries of a metadata index tree, depth first and in
+/// file order. Nodes are only read when the visitor descends into the entry pointing to
+/// them, so a visitor skipping entries never pays for their subtrees.
+pub trait IndexVisitor {
+    /// Called on reaching a node at `depth`, the root being at 0. `range` is the byte range
+    /// the node was read from, `None` for the root, which is read with the footer.
+    /// Returning `false` skips its entries.
+    fn enter_node(
+        &mut self,
+        node: &MetadataIndexNodeType,
+        range: Option<(i64, i64)>,
+        depth: usize,
+    ) -> bool {
+        let _ = (node, range, depth);
+        true
+    }
+
+    /// Called for each entry of `node` before its subtree: a child node, or for leaf
+    /// measurement nodes a run of timeseries. Returning `false` skips the subtree.
+    fn visit_entry(
+        &mut self,
+        node: &MetadataIndexNodeType,
+        entry: &MetadataIndexEntry,
+        depth: usize,
+    ) -> bool {
+        let _ = (node, entry, depth);
+        true
+    }
+
+    /// Called for each timeseries of `device` under an entry of a leaf measurement node,
+    /// with the offset it was read from.
+    fn visit_timeseries(
+        &mut self,
+        device: &str,
+        series: &TimeseriesMetadata,
+        offset: u64,
+        depth: usize,
+    ) {
+        let _ = (device, series, offset, depth);
+    }
+
+    /// Called once all entries of `node` are visited.
+    fn leave_node(&mut self, node: &MetadataIndexNodeType, depth: usize) {
+        let _ = (node, depth);
+    }
+}
+
+/// Prints the index tree as indented text, one line per node and entry, with the offsets
+/// they point to:
+///
+/// ```text
+/// LEAF_DEVICE, entries end at 323
+///   root.sg.d1 -> 281
+///     LEAF_MEASUREMENT at 281..302, entries end at 226
+///       s1 -> 116
+///         s1 Int32, 1 chunks, 1 points at 116
+///         s2 Int32, 1 chunks, 1 points at 171
+/// ```
+#[derive(Debug, Default)]
+pub struct TreePrinter {
+    out: String,
+    timeseries: bool,
+}
+
+impl TreePrinter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the timeseries under leaf measurement entries are read and printed too.
+    pub fn with_timeseries(mut self, timeseries: bool) -> Self {
+        self.timeseries = timeseries;
+        self
+    }
+
+    /// The text printed so far.
+    pub fn output(&self) -> &str {
+        &self.out
+    }
+
+    pub fn into_output(self) -> String {
+        self.out
+    }
+
+    fn indent(&mut self, depth: usize) {
+        self.out.push_str(&"  ".repeat(depth));
+    }
+}
+
+impl IndexVisitor for TreePrinter {
+    fn enter_node(
+        &mut self,
+        node: &MetadataIndexNodeType,
+        range: Option<(i64, i64)>,
+        depth: usize,
+    ) -> bool {
+        let (kind, inner) = match node {
+            InternalDevice(n) => ("INTERNAL_DEVICE", n),
+            LeafDevice(n) => ("LEAF_DEVICE", n),
+            InternalMeasurement(n) => ("INTERNAL_MEASUREMENT", n),
+            LeafMeasurement(n) => ("LEAF_MEASUREMENT", n),
+        };
+        // nodes sit below the entry pointing to them
+        self.indent(depth * 2);
+        self.out.push_str(kind);
+        if let Some((start, end)) = range {
+            let _ = write!(self.out, " at {}..{}", start, end);
+        }
+        let _ = writeln!(self.out, ", entries end at {}", inner.end_offset());
+        true
+    }
+
+    fn visit_entry(
+        &mut self,
+        node: &MetadataIndexNodeType,
+        entry: &MetadataIndexEntry,
+        depth: usize,
+    ) -> bool {
+        self.indent(depth * 2 + 1);
+        let _ = writeln!(self.out, "{} -> {}", entry.name(), entry.offset());
+        self.timeseries || !matches!(node, LeafMeasurement(_))
+    }
+
+    fn visit_timeseries(
+        &mut self,
+        _device: &str,
+        series: &TimeseriesMetadata,
+        offset: u64,
+        depth: usize,
+    ) {
+        self.indent(depth * 2);
+        let _ = writeln!(
+            self.out,
+            "{} {:?}, {} chunks, {} points at {}",
+            series.measurement_id(),
+            series.data_type(),
+            series.chunk_metadata().len(),
+            series.statistic().count(),
+            offset
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file::tsfile_search_reader::{test_file, TsFileSearchReader};
+
+    #[test]
+    fn printer_descends_only_where_asked() {
+        let file = test_file(&[
+            ("root.sg.d1", "s1", &[1]),
+            ("root.sg.d1", "s2", &[1]),
+            ("root.sg.d2", "s1", &[1]),
+        ]);
+        let reader = TsFileSearchReader::new(bytes::Bytes::from(file)).unwrap();
+
+        let mut printer = TreePrinter::new();
+        reader.visit_index(&mut printer).unwrap();
+        assert_eq!(
+            printer.output(),
+            "LEAF_DEVICE, entries end at 323\n\
+             \x20 root.sg.d1 -> 281\n\
+             \x20   LEAF_MEASUREMENT at 281..302, entries end at 226\n\
+             \x20     s1 -> 116\n\
+             \x20 root.sg.d2 -> 302\n\
+             \x20   LEAF_MEASUREMENT at 302..323, entries end at 281\n\
+             \x20     s1 -> 226\n"
+        );
+
+        let mut printer = TreePrinter::new().with_timeseries(true);
+        reader.visit_index(&mut printer).unwrap();
+        let lines: Vec<_> = printer.output().lines().collect();
+        assert_eq!(lines.len(), 10);
+        assert_eq!(lines[4], "        s1 Int32, 1 chunks, 1 points at 116");
+        assert_eq!(lines[5], "        s2 Int32, 1 chunks, 1 points at 171");
+    }
+}
@@ -5,8 +5,7 @@ use std::sync::Arc;
 
 use bit_set::BitSet;
 use byteorder::{BigEndian, ReadBytesExt};
-use snafu::ResultExt;
-use varint::VarintRead;
+use snafu::{ensure, ResultExt};
 
 use crate::file::metadata::MetadataIndexNodeType::{
     InternalDevice, InternalMeasurement, LeafDevice, LeafMeasurement,
@@ -30,12 +29,22 @@ pub enum Error {
         s_type: String,
         source: statistics::Error,
     },
+    #[snafu(display("{} of {} exceeds the {} remaining bytes", field, len, remaining))]
+    LengthExceedsData {
+        field: String,
+        len: u64,
+        remaining: u64,
+    },
+    #[snafu(display("Unknown column category {} of table {}", category, table))]
+    UnknownColumnCategory { table: String, category: i32 },
 }
 
 type Result<T, E = Error> = std::result::Result<T, E>;
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct TsFileMetadata {
+    #[cfg_attr(not(feature = "serde"), allow(dead_code))]
     size: u64,
     file_meta: FileMeta,
 }
@@ -47,21 +56,48 @@ impl TsFileMetadata {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct FileMeta {
+    version: u8,
     metadata_index: Arc<MetadataIndexNodeType>,
     meta_offset: i64,
     bloom_filter: Option<BloomFilter>,
+    tables: Vec<TableSchema>,
+    properties: Vec<(String, String)>,
 }
 
 impl FileMeta {
     pub fn new(index: MetadataIndexNodeType, offset: i64, filter: Option<BloomFilter>) -> Self {
         FileMeta {
+            version: crate::VERSION_NUMBER,
             metadata_index: Arc::new(index),
             meta_offset: offset,
             bloom_filter: filter,
+            tables: Vec::new(),
+            properties: Vec::new(),
         }
     }
 
+    /// The version byte of the file, 3 or 4.
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    /// The table schemas of a version 4 file, none for older files.
+    pub fn tables(&self) -> &[TableSchema] {
+        &self.tables
+    }
+
+    /// The key/value properties a version 4 file may store after its bloom filter.
+    pub fn properties(&self) -> &[(String, String)] {
+        &self.properties
+    }
+
+    /// The offset of the first chunk metadata list, where the metadata section starts.
+    pub fn meta_offset(&self) -> i64 {
+        self.meta_offset
+    }
+
     pub fn bloom_filter(&self) -> &Option<BloomFilter> {
         &self.bloom_filter
     }
@@ -71,13 +107,75 @@ impl FileMeta {
     }
 }
 
+/// The kind of a column of a version 4 table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum ColumnCategory {
+    Tag,
+    Field,
+    Attribute,
+    Time,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ColumnSchema {
+    name: String,
+    data_type: TSDataType,
+    encoding: u8,
+    compression: u8,
+    properties: Vec<(String, String)>,
+    category: ColumnCategory,
+}
+
+impl ColumnSchema {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn data_type(&self) -> &TSDataType {
+        &self.data_type
+    }
+    pub fn encoding(&self) -> u8 {
+        self.encoding
+    }
+    pub fn compression(&self) -> u8 {
+        self.compression
+    }
+    pub fn properties(&self) -> &[(String, String)] {
+        &self.properties
+    }
+    pub fn category(&self) -> ColumnCategory {
+        self.category
+    }
+}
+
+/// The schema of a table of a version 4 file.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TableSchema {
+    name: String,
+    columns: Vec<ColumnSchema>,
+}
+
+impl TableSchema {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn columns(&self) -> &[ColumnSchema] {
+        &self.columns
+    }
+}
+
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(not(feature = "serde"), allow(dead_code))]
 pub struct BloomFilter {
     minimal_size: i32,
     maximal_hash_function_size: i32,
     seeds: Vec<u32>,
     size: u32,
     hash_function_size: u32,
+    #[cfg_attr(feature = "serde", serde(skip))]
     bits: BitSet,
     func: Vec<HashFunction>,
 }
@@ -98,6 +196,7 @@ impl BloomFilter {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct HashFunction {
     cap: u32,
     seed: u32,
@@ -111,7 +210,10 @@ impl HashFunction {
     }
 }
 
-#[derive(Debug)]
+/// A node of the metadata index tree. Cloning is cheap: the children are shared, so
+/// iterators and threads can hold nodes without copying the parsed tree.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum MetadataIndexNodeType {
     InternalDevice(MetaDataIndexNode),
     LeafDevice(MetaDataIndexNode),
@@ -119,49 +221,60 @@ pub enum MetadataIndexNodeType {
     LeafMeasurement(MetaDataIndexNode),
 }
 
-impl Clone for MetadataIndexNodeType {
-    fn clone(&self) -> Self {
-        match self {
-            InternalDevice(m) => InternalDevice(m.clone()),
-            LeafDevice(m) => LeafDevice(m.clone()),
-            InternalMeasurement(m) => InternalMeasurement(m.clone()),
-            LeafMeasurement(m) => LeafMeasurement(m.clone()),
-        }
-    }
-}
-
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct MetaDataIndexNode {
-    children: Vec<MetadataIndexEntry>,
+    children: Arc<[MetadataIndexEntry]>,
     end_offset: i64,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    child_format: EntryFormat,
+}
+
+/// How the names of index entries are stored: varint strings, or the segmented device ids
+/// version 4 files use in the device levels of the index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum EntryFormat {
+    Name,
+    DeviceId,
 }
 
 impl MetaDataIndexNode {
-    pub fn children(&self) -> &Vec<MetadataIndexEntry> {
+    /// The format of the entries of the child nodes.
+    pub(crate) fn child_format(&self) -> EntryFormat {
+        self.child_format
+    }
+
+    pub fn children(&self) -> &[MetadataIndexEntry] {
         &self.children
     }
 
     pub fn end_offset(&self) -> i64 {
         self.end_offset
     }
-}
 
-impl Clone for MetaDataIndexNode {
-    fn clone(&self) -> Self {
-        let mut vec: Vec<MetadataIndexEntry> = Vec::with_capacity(self.children.len());
-        Vec::clone_from(&mut vec, self.children());
-        MetaDataIndexNode {
-            children: vec,
-            end_offset: self.end_offset,
-        }
+    /// The `[start, end)` byte range of the i-th child.
+    pub fn child_range(&self, i: usize) -> Option<(i64, i64)> {
+        let start = self.children.get(i)?.offset();
+        let end = match self.children.get(i + 1) {
+            Some(next) => next.offset(),
+            None => self.end_offset,
+        };
+        Some((start, end))
     }
 
-    fn clone_from(&mut self, _source: &Self) {
-        todo!()
+    /// The index of the last child whose name is not greater than `name`, the only child
+    /// that can contain it.
+    pub fn floor_child(&self, name: &str) -> Option<usize> {
+        match self.children.binary_search_by(|c| c.name().cmp(name)) {
+            Ok(i) => Some(i),
+            Err(0) => None,
+            Err(i) => Some(i - 1),
+        }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct MetadataIndexEntry {
     name: String,
     offset: i64,
@@ -176,28 +289,24 @@ impl MetadataIndexEntry {
     }
 }
 
-impl Clone for MetadataIndexEntry {
-    fn clone(&self) -> Self {
-        Self {
-            name: self.name.clone(),
-            offset: self.offset,
-        }
-    }
-}
-
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum TimeseriesMetadataType {
     OneChunk,
     MoreChunks,
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct TimeseriesMetadata {
     chunk_metadata_list: Vec<ChunkMetadata>,
     chunk_metadata_list_size: u32,
     measurement_id: String,
     data_type: TSDataType,
+    #[cfg_attr(not(feature = "serde"), allow(dead_code))]
     metadata_type: TimeseriesMetadataType,
+    value_column: bool,
+    statistic: Arc<Statistic>,
 }
 
 impl TimeseriesMetadata {
@@ -207,11 +316,68 @@ impl TimeseriesMetadata {
     pub fn measurement_id(&self) -> &str {
         self.measurement_id.as_str()
     }
+    pub fn statistic(&self) -> Arc<Statistic> {
+        self.statistic.clone()
+    }
+    pub fn data_type(&self) -> &TSDataType {
+        &self.data_type
+    }
+    pub fn chunk_metadata(&self) -> &[ChunkMetadata] {
+        &self.chunk_metadata_list
+    }
+    /// Whether this is the time column of an aligned device rather than a sensor.
+    pub fn is_time_column(&self) -> bool {
+        self.data_type == TSDataType::VECTOR || self.measurement_id.is_empty()
+    }
+    /// Whether this is a value column of an aligned device, whose chunks take their
+    /// timestamps from the time column.
+    pub fn is_value_column(&self) -> bool {
+        self.value_column
+    }
+    /// Serialized size of the chunk metadata list in bytes.
+    pub fn chunk_metadata_list_size(&self) -> u32 {
+        self.chunk_metadata_list_size
+    }
+
+    /// Sets the size bound of each chunk from the chunks of the whole file.
+    pub(crate) fn set_chunk_bounds(&mut self, bounds: &ChunkBounds) {
+        for chunk in &mut self.chunk_metadata_list {
+            chunk.max_size = bounds.max_size(chunk.offset_chunk_header);
+        }
+    }
+}
+
+/// The offsets of every chunk of a file. Chunks never overlap and the metadata follows the
+/// last of them, so the next offset bounds the size of each chunk.
+#[derive(Debug, Default)]
+pub(crate) struct ChunkBounds {
+    offsets: Vec<i64>,
+    meta_offset: i64,
+}
+
+impl ChunkBounds {
+    pub(crate) fn new(mut offsets: Vec<i64>, meta_offset: i64) -> Self {
+        offsets.sort_unstable();
+        offsets.dedup();
+        Self {
+            offsets,
+            meta_offset,
+        }
+    }
+
+    /// The bytes from `offset` to the next chunk, or to the metadata for the last chunk.
+    pub(crate) fn max_size(&self, offset: i64) -> Option<u64> {
+        let next = self.offsets.partition_point(|&o| o <= offset);
+        let end = self.offsets.get(next).copied().unwrap_or(self.meta_offset);
+        (end > offset).then(|| (end - offset) as u64)
+    }
 }
 
 impl TimeseriesMetadata {
     pub fn new(cursor: &mut Cursor<Vec<u8>>) -> Result<TimeseriesMetadata> {
-        let meta_type = match cursor.read_u8().context(ReadCursorData)? {
+        // the high bits mark the time and value columns of aligned devices
+        let marker = cursor.read_u8().context(ReadCursorData)?;
+        let meta_type = match marker & 0x3f {
             0 => TimeseriesMetadataType::OneChunk,
             _ => TimeseriesMetadataType::MoreChunks,
         };
@@ -250,6 +416,11 @@ impl TimeseriesMetadata {
                     s_type: "TEXT".to_string(),
                 })?,
             ),
+            TSDataType::VECTOR => Statistic::Time(
+                TimeStatistics::try_from(cursor.borrow_mut()).context(ParserStatistics {
+                    s_type: "time".to_string(),
+                })?,
+            ),
         });
         let end_pos = cursor.position() + chunk_metadata_list_size as u64;
         let mut chunk_metadata_list = Vec::new();
@@ -301,6 +472,13 @@ impl TimeseriesMetadata {
                             },
                         )?)
                     }
+                    TSDataType::VECTOR => {
+                        Statistic::Time(TimeStatistics::try_from(cursor.borrow_mut()).context(
+                            ParserStatistics {
+                                s_type: "time".to_string(),
+                            },
+                        )?)
+                    }
                 }),
             };
             chunk_metadata_list.push(ChunkMetadata::new(
@@ -314,13 +492,16 @@ impl TimeseriesMetadata {
             measurement_id,
             data_type,
             metadata_type: meta_type,
+            value_column: marker & 0x40 != 0,
             chunk_metadata_list_size,
             chunk_metadata_list,
+            statistic: statistics,
         })
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum TSDataType {
     Boolean,
     Int32,
@@ -328,6 +509,10 @@ pub enum TSDataType {
     FLOAT,
     DOUBLE,
     TEXT,
+    /// The time column of an aligned device. IoTDB 0.13 writes the devices created from
+    /// aligned schema templates this way; the column has an empty measurement id and is
+    /// followed by the value columns of the device.
+    VECTOR,
 }
 
 impl Clone for TSDataType {
@@ -339,6 +524,7 @@ impl Clone for TSDataType {
             TSDataType::FLOAT => Self::FLOAT,
             TSDataType::DOUBLE => Self::DOUBLE,
             TSDataType::TEXT => Self::TEXT,
+            TSDataType::VECTOR => Self::VECTOR,
         }
     }
 }
@@ -351,22 +537,12 @@ impl TSDataType {
             2 => Self::Int64,
             3 => Self::FLOAT,
             4 => Self::DOUBLE,
+            6 => Self::VECTOR,
             _ => Self::TEXT,
         }
     }
-    // fn new(flag: u8, cursor: &mut Cursor<Vec<u8>>) -> Result<TSDataType> {
-    //     match flag {
-    //         0 => Ok(Self::Boolean(BooleanStatistics::try_from(cursor).unwrap())),
-    //         1 => Ok(Self::Int32(IntegerStatistics::try_from(cursor).unwrap())),
-    //         2 => Ok(Self::Int64(LongStatistics::try_from(cursor).unwrap())),
-    //         3 => Ok(Self::FLOAT(FloatStatistics::try_from(cursor).unwrap())),
-    //         4 => Ok(Self::DOUBLE(DoubleStatistics::try_from(cursor).unwrap())),
-    //         5 => Ok(Self::TEXT(BinaryStatistics::try_from(cursor).unwrap())),
-    //         _ => Err(TsFileError::General("123".to_string())),
-    //     }
-    // }
-
-    fn int_id(&self) -> u8 {
+
+    pub(crate) fn int_id(&self) -> u8 {
         match self {
             Boolean => 0,
             TSDataType::Int32 => 1,
@@ -374,15 +550,51 @@ impl TSDataType {
             TSDataType::FLOAT => 3,
             TSDataType::DOUBLE => 4,
             TSDataType::TEXT => 5,
+            TSDataType::VECTOR => 6,
         }
     }
 }
 
+/// The sensors of a device as its timeseries record them. Version 3 files keep no schema
+/// templates: devices created from an aligned template are stored as aligned devices, the
+/// others like devices without a template.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DeviceSchema {
+    aligned: bool,
+    sensors: Vec<(String, TSDataType)>,
+}
+
+impl DeviceSchema {
+    /// Whether the sensors share the time column of the device.
+    pub fn is_aligned(&self) -> bool {
+        self.aligned
+    }
+
+    /// Sensor ids and data types, in index order.
+    pub fn sensors(&self) -> &[(String, TSDataType)] {
+        &self.sensors
+    }
+
+    pub(crate) fn add(&mut self, series: &TimeseriesMetadata) {
+        if series.is_time_column() {
+            self.aligned = true;
+        } else {
+            self.sensors
+                .push((series.measurement_id.clone(), series.data_type.clone()));
+        }
+    }
+}
+
+/// Metadata of one chunk. Version 3 files do not record a chunk's encoding, compression
+/// or size here; those come from the chunk header.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ChunkMetadata {
     measurement_uid: String,
     ts_data_type: TSDataType,
     offset_chunk_header: i64,
+    max_size: Option<u64>,
     statistic: Arc<Statistic>,
 }
 
@@ -397,10 +609,22 @@ impl ChunkMetadata {
             measurement_uid,
             ts_data_type,
             offset_chunk_header,
+            max_size: None,
             statistic,
         }
     }
 
+    pub fn measurement_uid(&self) -> &str {
+        &self.measurement_uid
+    }
+
+    /// An upper bound of the serialized chunk size, header included: the distance to the
+    /// next chunk of the file, or to the metadata for the last one. Readers set it for the
+    /// series they read; metadata parsed on its own has none.
+    pub fn max_size(&self) -> Option<u64> {
+        self.max_size
+    }
+
     pub fn ts_data_type(&self) -> &TSDataType {
         &self.ts_data_type
     }
@@ -409,35 +633,187 @@ impl ChunkMetadata {
         self.offset_chunk_header
     }
 
+    /// `(start_time, end_time)` of the chunk, both inclusive.
+    pub fn time_range(&self) -> (i64, i64) {
+        self.statistic.time_range()
+    }
+
     pub fn statistic(&self) -> Arc<Statistic> {
         self.statistic.clone()
     }
 }
 
 impl TsFileMetadata {
-    pub fn parser(mut data: Cursor<Vec<u8>>) -> Result<Self> {
-        // metadataIndex
-        let metadata_index = MetadataIndexNodeType::new(&mut data).unwrap();
-        // metaOffset
+    pub fn parser(data: Cursor<Vec<u8>>) -> Result<Self> {
+        Self::parser_with_bloom_filter(data, true)
+    }
+
+    /// Like `parser`, leaving out the bloom filter unless `bloom_filter` is set.
+    pub fn parser_with_bloom_filter(data: Cursor<Vec<u8>>, bloom_filter: bool) -> Result<Self> {
+        Self::parse(data, crate::VERSION_NUMBER, 0, bloom_filter)
+    }
+
+    /// Parses the metadata of a file of the given version, read from `position` of the file.
+    pub fn parse(
+        mut data: Cursor<Vec<u8>>,
+        version: u8,
+        position: u64,
+        bloom_filter: bool,
+    ) -> Result<Self> {
+        let mut metadata = Self::parse_index(&mut data, version, position)?;
+        if version < crate::VERSION_NUMBER_V4 {
+            if bloom_filter {
+                metadata.file_meta.bloom_filter = read_bloom_filter(&mut data)?;
+            }
+            return Ok(metadata);
+        }
+        // the properties follow the bloom filter, so it is read either way
+        let filter = read_bloom_filter(&mut data)?.filter(|_| bloom_filter);
+        let mut properties = Vec::new();
+        if data.remaining() > 0 {
+            let count = data.read_varint().context(ReadVarInt)?;
+            for _ in 0..count {
+                let key = data.read_varint_string().context(ReadVarInt)?;
+                let value = data.read_varint_string().context(ReadVarInt)?;
+                properties.push((key, value));
+            }
+        }
+        metadata.file_meta.bloom_filter = filter;
+        metadata.file_meta.properties = properties;
+        Ok(metadata)
+    }
+
+    /// Parses the start of the metadata of a file of the given version, read from
+    /// `position` of the file: the index root, the table schemas of version 4 files and
+    /// the meta offset. The bloom filter and properties after them are left out, so `data`
+    /// may end before them.
+    pub(crate) fn parse_index(
+        data: &mut Cursor<Vec<u8>>,
+        version: u8,
+        position: u64,
+    ) -> Result<Self> {
+        let (metadata_index, tables) = if version < crate::VERSION_NUMBER_V4 {
+            (MetadataIndexNodeType::new(data)?, Vec::new())
+        } else {
+            let metadata_index = read_table_roots(data, position)?;
+            let count = data.read_unsigned_varint_32().context(ReadCursorData)?;
+            let mut tables = Vec::new();
+            for _ in 0..count {
+                tables.push(TableSchema::new(data)?);
+            }
+            (metadata_index, tables)
+        };
         let meta_offset = data.read_i64::<BigEndian>().context(ReadCursorData)?;
+        let mut file_meta = FileMeta::new(metadata_index, meta_offset, None);
+        file_meta.version = version;
+        file_meta.tables = tables;
+        Ok(Self { size: 0, file_meta })
+    }
+}
 
-        // read bloom filter
-        let mut bloom_filter = None;
-        let length = data.get_ref().capacity();
-        if data.position() < length as u64 {
-            let bloom_filter_size = data.read_unsigned_varint_32().context(ReadCursorData)?;
-            let mut bytes = vec![0; bloom_filter_size as usize];
-            data.read_exact(&mut bytes).context(ReadCursorData)?;
-
-            let filter_size = data.read_unsigned_varint_32().context(ReadCursorData)?;
-            let hash_function_size = data.read_unsigned_varint_32().context(ReadCursorData)?;
-            bloom_filter = Some(BloomFilter::new(bytes, filter_size, hash_function_size));
+/// Reads the roots of the tables of a version 4 file into an index node whose entries are
+/// the table names and the offsets of their roots, so they are read like any other node.
+fn read_table_roots(data: &mut Cursor<Vec<u8>>, position: u64) -> Result<MetadataIndexNodeType> {
+    let count = data.read_unsigned_varint_32().context(ReadCursorData)?;
+    ensure!(
+        count as u64 <= data.remaining(),
+        LengthExceedsData {
+            field: "tables",
+            len: count as u64,
+            remaining: data.remaining()
         }
-        Ok(Self {
-            size: 0,
-            file_meta: FileMeta::new(metadata_index, meta_offset, bloom_filter),
-        })
+    );
+    let mut children = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        let name = data.read_varint_string().context(ReadVarInt)?;
+        let offset = (position + data.position()) as i64;
+        MetadataIndexNodeType::with_format(data, EntryFormat::DeviceId)?;
+        children.push(MetadataIndexEntry { name, offset });
     }
+    Ok(InternalDevice(MetaDataIndexNode {
+        children: children.into(),
+        end_offset: (position + data.position()) as i64,
+        child_format: EntryFormat::DeviceId,
+    }))
+}
+
+fn read_bloom_filter(data: &mut Cursor<Vec<u8>>) -> Result<Option<BloomFilter>> {
+    if data.remaining() == 0 {
+        return Ok(None);
+    }
+    let bloom_filter_size = data.read_unsigned_varint_32().context(ReadCursorData)?;
+    ensure!(
+        bloom_filter_size as u64 <= data.remaining(),
+        LengthExceedsData {
+            field: "bloom filter size",
+            len: bloom_filter_size as u64,
+            remaining: data.remaining()
+        }
+    );
+    let mut bytes = vec![0; bloom_filter_size as usize];
+    data.read_exact(&mut bytes).context(ReadCursorData)?;
+
+    let filter_size = data.read_unsigned_varint_32().context(ReadCursorData)?;
+    let hash_function_size = data.read_unsigned_varint_32().context(ReadCursorData)?;
+    Ok(Some(BloomFilter::new(
+        bytes,
+        filter_size,
+        hash_function_size,
+    )))
+}
+
+impl TableSchema {
+    fn new(data: &mut Cursor<Vec<u8>>) -> Result<Self> {
+        let name = data.read_varint_string().context(ReadVarInt)?;
+        let count = data.read_unsigned_varint_32().context(ReadCursorData)?;
+        ensure!(
+            count as u64 <= data.remaining(),
+            LengthExceedsData {
+                field: "table columns",
+                len: count as u64,
+                remaining: data.remaining()
+            }
+        );
+        let mut columns = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let column = read_string(data)?;
+            let data_type = TSDataType::new(data.read_u8().context(ReadCursorData)?);
+            let encoding = data.read_u8().context(ReadCursorData)?;
+            let compression = data.read_u8().context(ReadCursorData)?;
+            let mut properties = Vec::new();
+            for _ in 0..data.read_i32::<BigEndian>().context(ReadCursorData)? {
+                properties.push((read_string(data)?, read_string(data)?));
+            }
+            let category = match data.read_i32::<BigEndian>().context(ReadCursorData)? {
+                0 => ColumnCategory::Tag,
+                1 => ColumnCategory::Field,
+                2 => ColumnCategory::Attribute,
+                3 => ColumnCategory::Time,
+                category => {
+                    return UnknownColumnCategory {
+                        table: name,
+                        category,
+                    }
+                    .fail()
+                }
+            };
+            columns.push(ColumnSchema {
+                name: column,
+                data_type,
+                encoding,
+                compression,
+                properties,
+                category,
+            });
+        }
+        Ok(Self { name, columns })
+    }
+}
+
+/// Reads a string stored with an `i32` length, as the table schemas are.
+fn read_string(data: &mut Cursor<Vec<u8>>) -> Result<String> {
+    let len = data.read_i32::<BigEndian>().context(ReadCursorData)?;
+    data.read_string(len).context(ReadVarInt)
 }
 
 impl BloomFilter {
@@ -457,7 +833,7 @@ impl BloomFilter {
             seeds,
             hash_function_size,
             func,
-            bits: BitSet::from_bytes(&data[8..]),
+            bits: BitSet::from_bytes(data.get(8..).unwrap_or_default()),
         }
     }
 }
@@ -473,23 +849,41 @@ impl HashFunction {
 
 impl MetadataIndexNodeType {
     pub fn new(data: &mut Cursor<Vec<u8>>) -> Result<Self> {
+        Self::with_format(data, EntryFormat::Name)
+    }
+
+    /// Parses a node whose entries are stored in `format`.
+    pub(crate) fn with_format(data: &mut Cursor<Vec<u8>>, format: EntryFormat) -> Result<Self> {
         let len = data.read_unsigned_varint_32().context(ReadCursorData)?;
+        // every entry takes at least one byte
+        ensure!(
+            len as u64 <= data.remaining(),
+            LengthExceedsData {
+                field: "index node children",
+                len: len as u64,
+                remaining: data.remaining()
+            }
+        );
         let mut children: Vec<MetadataIndexEntry> = Vec::with_capacity(len as usize);
         for _i in 0..len {
-            children.push(MetadataIndexEntry::new(data.borrow_mut()).unwrap());
+            children.push(MetadataIndexEntry::new(data.borrow_mut(), format)?);
         }
 
         let end_offset = data.read_i64::<BigEndian>().context(ReadCursorData)?;
 
         let mut vec = vec![255; 1];
-        data.read_exact(&mut vec);
+        data.read_exact(&mut vec).context(ReadCursorData)?;
 
-        let node = MetaDataIndexNode {
-            children,
+        let mut node = MetaDataIndexNode {
+            children: children.into(),
             end_offset,
+            child_format: EntryFormat::Name,
         };
         match vec[0] {
-            0 => Ok(InternalDevice(node)),
+            0 => {
+                node.child_format = format;
+                Ok(InternalDevice(node))
+            }
             1 => Ok(LeafDevice(node)),
             2 => Ok(InternalMeasurement(node)),
             _ => Ok(LeafMeasurement(node)),
@@ -498,8 +892,12 @@ impl MetadataIndexNodeType {
 }
 
 impl MetadataIndexEntry {
-    fn new(data: &mut Cursor<Vec<u8>>) -> Result<Self> {
-        let name = data.read_varint_string().context(ReadVarInt)?;
+    fn new(data: &mut Cursor<Vec<u8>>, format: EntryFormat) -> Result<Self> {
+        let name = match format {
+            EntryFormat::Name => data.read_varint_string(),
+            EntryFormat::DeviceId => data.read_device_id(),
+        }
+        .context(ReadVarInt)?;
         let offset = data.read_i64::<BigEndian>().context(ReadCursorData)?;
         Ok(Self { name, offset })
     }
@@ -0,0 +1,339 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::io::{self, Write};
+
+use byteorder::{BigEndian, WriteBytesExt};
+use snafu::{ensure, ResultExt, Snafu};
+
+use crate::file::metadata::TSDataType;
+use crate::file::statistics;
+use crate::file::statistics::Statistic;
+use crate::utils::cursor::VarIntWriter;
+use crate::MAGIC_STRING;
+
+/// Most children of an index node, the default of IoTDB writers.
+pub const MAX_DEGREE_OF_INDEX_NODE: usize = 256;
+
+const SEPARATOR: u8 = 2;
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Unable to write metadata: {}", source))]
+    WriteData { source: io::Error },
+    #[snafu(display("Unable to write statistics: {}", source))]
+    WriteStatistics { source: statistics::Error },
+    #[snafu(display(
+        "Series {}.{} has chunks of {:?} and {:?}",
+        device,
+        measurement,
+        expected,
+        found
+    ))]
+    MismatchedType {
+        device: String,
+        measurement: String,
+        expected: TSDataType,
+        found: TSDataType,
+    },
+}
+
+type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Collects the chunks of a data section and writes the metadata that makes it a
+/// readable file: the separator, the timeseries metadata, the metadata index tree and
+/// the footer, laid out as IoTDB writers do. No bloom filter is written.
+#[derive(Debug, Default)]
+pub struct MetadataWriter {
+    devices: BTreeMap<String, BTreeMap<String, Series>>,
+}
+
+#[derive(Debug)]
+struct Series {
+    data_type: TSDataType,
+    chunks: Vec<(i64, Statistic)>,
+}
+
+/// An index node being built, `(name, offset)` per child.
+struct Node {
+    node_type: u8,
+    children: Vec<(String, i64)>,
+    end_offset: i64,
+}
+
+const INTERNAL_DEVICE: u8 = 0;
+const LEAF_DEVICE: u8 = 1;
+const INTERNAL_MEASUREMENT: u8 = 2;
+const LEAF_MEASUREMENT: u8 = 3;
+
+/// Bits of the timeseries marker set for the time and value columns of aligned devices.
+const TIME_COLUMN: u8 = 0x80;
+const VALUE_COLUMN: u8 = 0x40;
+
+impl MetadataWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a chunk whose header is at `offset`; chunks of a series are added in file order.
+    pub fn add_chunk(
+        &mut self,
+        device: &str,
+        measurement: &str,
+        data_type: &TSDataType,
+        offset: i64,
+        statistic: Statistic,
+    ) -> Result<()> {
+        let series = self
+            .devices
+            .entry(device.to_string())
+            .or_default()
+            .entry(measurement.to_string())
+            .or_insert_with(|| Series {
+                data_type: data_type.clone(),
+                chunks: Vec::new(),
+            });
+        ensure!(
+            &series.data_type == data_type,
+            MismatchedType {
+                device,
+                measurement,
+                expected: series.data_type.clone(),
+                found: data_type.clone(),
+            }
+        );
+        series.chunks.push((offset, statistic));
+        Ok(())
+    }
+
+    /// Writes the metadata to `out`, which is positioned at `position`, the end of the
+    /// data section.
+    pub fn finish<W: Write>(self, position: u64, out: W) -> Result<W> {
+        let meta_offset = position as i64;
+        let mut out = Positioned { out, position };
+        out.write_u8(SEPARATOR).context(WriteData)?;
+
+        let mut device_nodes = Vec::with_capacity(self.devices.len());
+        for (device, series) in self.devices {
+            let mut queue = VecDeque::new();
+            let mut current = Node::new(LEAF_MEASUREMENT);
+            // the series of a device with a time column are the value columns of its
+            // aligned chunks
+            let aligned = series.contains_key("");
+            for (i, (measurement, series)) in series.iter().enumerate() {
+                // a leaf entry points to a run of up to MAX_DEGREE_OF_INDEX_NODE series
+                if i % MAX_DEGREE_OF_INDEX_NODE == 0 {
+                    if current.is_full() {
+                        current.end_offset = out.position as i64;
+                        queue.push_back(current);
+                        current = Node::new(LEAF_MEASUREMENT);
+                    }
+                    current
+                        .children
+                        .push((measurement.clone(), out.position as i64));
+                }
+                let column = match (aligned, measurement.is_empty()) {
+                    (true, true) => TIME_COLUMN,
+                    (true, false) => VALUE_COLUMN,
+                    (false, _) => 0,
+                };
+                write_timeseries(&mut out, measurement, series, column)?;
+            }
+            current.end_offset = out.position as i64;
+            queue.push_back(current);
+            device_nodes.push((device, root_node(queue, &mut out, INTERNAL_MEASUREMENT)?));
+        }
+
+        let mut root = Node::new(LEAF_DEVICE);
+        if device_nodes.len() <= MAX_DEGREE_OF_INDEX_NODE {
+            for (device, node) in device_nodes {
+                root.children.push((device, out.position as i64));
+                node.write(&mut out)?;
+            }
+            root.end_offset = out.position as i64;
+        } else {
+            let mut queue = VecDeque::new();
+            for (device, node) in device_nodes {
+                if root.is_full() {
+                    root.end_offset = out.position as i64;
+                    queue.push_back(root);
+                    root = Node::new(LEAF_DEVICE);
+                }
+                root.children.push((device, out.position as i64));
+                node.write(&mut out)?;
+            }
+            root.end_offset = out.position as i64;
+            queue.push_back(root);
+            root = root_node(queue, &mut out, INTERNAL_DEVICE)?;
+            root.end_offset = out.position as i64;
+        }
+
+        let metadata_start = out.position;
+        root.write(&mut out)?;
+        out.write_i64::<BigEndian>(meta_offset).context(WriteData)?;
+        let metadata_len = (out.position - metadata_start) as i32;
+        out.write_i32::<BigEndian>(metadata_len)
+            .context(WriteData)?;
+        out.write_all(MAGIC_STRING.as_bytes()).context(WriteData)?;
+        Ok(out.out)
+    }
+}
+
+/// Writes the nodes of `queue` level by level under new nodes of `node_type` until one
+/// node is left, which is returned unwritten.
+fn root_node<W: Write>(
+    mut queue: VecDeque<Node>,
+    out: &mut Positioned<W>,
+    node_type: u8,
+) -> Result<Node> {
+    while queue.len() > 1 {
+        let mut current = Node::new(node_type);
+        for _ in 0..queue.len() {
+            let node = queue.pop_front().expect("queue holds more than one node");
+            if current.is_full() {
+                current.end_offset = out.position as i64;
+                queue.push_back(current);
+                current = Node::new(node_type);
+            }
+            let name = node
+                .children
+                .first()
+                .map(|c| c.0.clone())
+                .unwrap_or_default();
+            current.children.push((name, out.position as i64));
+            node.write(out)?;
+        }
+        current.end_offset = out.position as i64;
+        queue.push_back(current);
+    }
+    Ok(queue.pop_front().unwrap_or_else(|| Node::new(node_type)))
+}
+
+/// Writes a timeseries metadata with its chunk metadata list; the chunks of a series with
+/// a single chunk share the series statistics. `column` marks the columns of aligned devices.
+fn write_timeseries<W: Write>(
+    out: &mut W,
+    measurement: &str,
+    series: &Series,
+    column: u8,
+) -> Result<()> {
+    let one_chunk = series.chunks.len() == 1;
+    let mut statistic = Statistic::empty(&series.data_type);
+    let mut chunks = Vec::new();
+    for (offset, chunk) in &series.chunks {
+        statistic.merge(chunk).context(WriteStatistics)?;
+        chunks.write_i64::<BigEndian>(*offset).context(WriteData)?;
+        if !one_chunk {
+            chunk.serialize(&mut chunks).context(WriteStatistics)?;
+        }
+    }
+
+    out.write_u8(column | if one_chunk { 0 } else { 1 })
+        .context(WriteData)?;
+    write_string(out, measurement)?;
+    out.write_u8(series.data_type.int_id()).context(WriteData)?;
+    out.write_unsigned_varint_32(chunks.len() as u32)
+        .context(WriteData)?;
+    statistic.serialize(out).context(WriteStatistics)?;
+    out.write_all(&chunks).context(WriteData)
+}
+
+/// A string as its zigzag varint length and UTF-8 bytes.
+fn write_string<W: Write>(out: &mut W, value: &str) -> Result<()> {
+    out.write_signed_varint_32(value.len() as i32)
+        .context(WriteData)?;
+    out.write_all(value.as_bytes()).context(WriteData)
+}
+
+impl Node {
+    fn new(node_type: u8) -> Self {
+        Self {
+            node_type,
+            children: Vec::new(),
+            end_offset: 0,
+        }
+    }
+
+    fn is_full(&self) -> bool {
+        self.children.len() >= MAX_DEGREE_OF_INDEX_NODE
+    }
+
+    fn write<W: Write>(&self, out: &mut W) -> Result<()> {
+        out.write_unsigned_varint_32(self.children.len() as u32)
+            .context(WriteData)?;
+        for (name, offset) in &self.children {
+            write_string(out, name)?;
+            out.write_i64::<BigEndian>(*offset).context(WriteData)?;
+        }
+        out.write_i64::<BigEndian>(self.end_offset)
+            .context(WriteData)?;
+        out.write_u8(self.node_type).context(WriteData)
+    }
+}
+
+/// Tracks the file offset of the bytes written.
+struct Positioned<W> {
+    out: W,
+    position: u64,
+}
+
+impl<W: Write> Write for Positioned<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.out.write(buf)?;
+        self.position += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoding::decoder::ColumnBatch;
+    use crate::file::reader::FileReader;
+    use crate::file::tsfile_search_reader::TsFileSearchReader;
+    use crate::VERSION_NUMBER;
+
+    #[test]
+    fn index_reads_back() {
+        let mut writer = MetadataWriter::new();
+        let mut offset = 7;
+        for device in ["root.sg.d2", "root.sg.d1"] {
+            for (sensor, chunks) in [("s2", 1), ("s1", 2)] {
+                for i in 0..chunks {
+                    let mut statistic = Statistic::empty(&TSDataType::Int32);
+                    statistic.update_batch(&[i * 10, i * 10 + 1], &ColumnBatch::Int32(vec![3, 4]));
+                    writer
+                        .add_chunk(device, sensor, &TSDataType::Int32, offset, statistic)
+                        .unwrap();
+                    offset += 10;
+                }
+            }
+        }
+        let other_type = Statistic::empty(&TSDataType::DOUBLE);
+        assert!(writer
+            .add_chunk("root.sg.d1", "s1", &TSDataType::DOUBLE, offset, other_type)
+            .is_err());
+
+        let mut file = MAGIC_STRING.as_bytes().to_vec();
+        file.push(VERSION_NUMBER);
+        let file = writer.finish(file.len() as u64, file).unwrap();
+        let reader = TsFileSearchReader::new(bytes::Bytes::from(file)).unwrap();
+        assert_eq!(reader.devices().unwrap(), ["root.sg.d1", "root.sg.d2"]);
+        let series: Vec<_> = reader
+            .sensor_meta_iter("root.sg.d1")
+            .collect::<crate::error::Result<_>>()
+            .unwrap();
+        assert_eq!(series.len(), 2);
+        let s1 = series.iter().find(|s| s.measurement_id() == "s1").unwrap();
+        assert_eq!(s1.statistic().count(), 4);
+        assert_eq!(s1.statistic().time_range(), (0, 11));
+        let offsets: Vec<i64> = s1
+            .chunk_metadata()
+            .iter()
+            .map(|c| c.offset_chunk_header())
+            .collect();
+        assert_eq!(offsets, [47, 57]);
+    }
+}
@@ -1,6 +1,21 @@
+#[cfg(feature = "async")]
+pub mod async_reader;
 pub mod compress;
 pub mod footer;
+pub mod index;
 pub mod metadata;
+pub mod metadata_writer;
+pub mod mods;
+#[cfg(feature = "object-store")]
+pub mod object_store_reader;
+pub mod options;
+pub mod path;
 pub mod reader;
+pub mod registry;
+pub mod repair;
+pub mod scanner;
 pub mod statistics;
+pub mod summary;
 pub mod tsfile_search_reader;
+pub mod tsfile_set_reader;
+pub mod upgrade;
@@ -0,0 +1,150 @@
+#[cfg(feature = "fs")]
+use std::fs;
+#[cfg(feature = "fs")]
+use std::io;
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+#[cfg(feature = "fs")]
+use snafu::ResultExt;
+use snafu::{OptionExt, Snafu};
+
+use crate::file::path::PathPattern;
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[cfg(feature = "fs")]
+    #[snafu(display("Unable to read mods file: {}", source))]
+    ReadMods { source: io::Error },
+    #[snafu(display("Invalid modification on line {}: {}", line, detail))]
+    InvalidModification { line: usize, detail: String },
+}
+
+type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Whether the deletions of the `.mods` file next to a TsFile are applied when reading it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ModsHandling {
+    /// Points are read as stored, deleted or not.
+    #[default]
+    Ignore,
+    /// Deleted points are left out of the chunks and pages read, as IoTDB does. Statistics
+    /// still count them.
+    Apply,
+}
+
+/// A deletion recorded in a `.mods` file: the points with `start <= time <= end` of the
+/// series matching `path`, in chunks written before `file_offset`.
+#[derive(Debug, Clone)]
+pub struct Deletion {
+    path: String,
+    pattern: PathPattern,
+    file_offset: i64,
+    start: i64,
+    end: i64,
+}
+
+impl Deletion {
+    pub fn new(path: &str, file_offset: i64, start: i64, end: i64) -> Self {
+        Self {
+            path: path.to_string(),
+            pattern: PathPattern::new(path),
+            file_offset,
+            start,
+            end,
+        }
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn file_offset(&self) -> i64 {
+        self.file_offset
+    }
+
+    pub fn start(&self) -> i64 {
+        self.start
+    }
+
+    pub fn end(&self) -> i64 {
+        self.end
+    }
+
+    /// Whether the deletion applies to the series `device.sensor`.
+    pub fn matches(&self, device: &str, sensor: &str) -> bool {
+        self.pattern.matches(&format!("{}.{}", device, sensor))
+    }
+
+    /// Whether the deletion applies to the chunk whose header is at `offset`.
+    pub fn covers_chunk(&self, offset: i64) -> bool {
+        offset < self.file_offset
+    }
+}
+
+/// Parses the lines of a `.mods` file, `DELETION,path,fileOffset,startTime,endTime`. Lines
+/// of IoTDB 0.11, `DELETION,path,versionNum,time`, delete every point up to `time`.
+pub fn parse_mods(text: &str) -> Result<Vec<Deletion>> {
+    let mut deletions = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let invalid = |detail: &str| InvalidModification {
+            line: i + 1,
+            detail: detail.to_string(),
+        };
+        let fields: Vec<&str> = line.split(',').collect();
+        if fields[0] != "DELETION" {
+            return invalid(&format!("unknown type {}", fields[0])).fail();
+        }
+        let number = |j: usize| -> Result<i64> {
+            let field = fields.get(j).context(invalid("missing field"))?;
+            field
+                .parse()
+                .ok()
+                .context(invalid(&format!("invalid number {}", field)))
+        };
+        let deletion = match fields.len() {
+            5 => Deletion::new(fields[1], number(2)?, number(3)?, number(4)?),
+            4 => Deletion::new(fields[1], i64::MAX, i64::MIN, number(3)?),
+            _ => return invalid(&format!("{} fields", fields.len())).fail(),
+        };
+        deletions.push(deletion);
+    }
+    Ok(deletions)
+}
+
+/// The deletions of the `.mods` file of the TsFile at `path`, none if there is no such file.
+#[cfg(feature = "fs")]
+pub fn read_mods(path: &Path) -> Result<Vec<Deletion>> {
+    let mut mods = path.as_os_str().to_owned();
+    mods.push(".mods");
+    match fs::read_to_string(mods) {
+        Ok(text) => parse_mods(&text),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e).context(ReadMods),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deletions_are_parsed() {
+        let text = "DELETION,root.sg.d1.s1,120,10,20\nDELETION,root.sg.**,3,100\n";
+        let deletions = parse_mods(text).unwrap();
+        assert_eq!(deletions.len(), 2);
+        assert!(deletions[0].matches("root.sg.d1", "s1"));
+        assert!(!deletions[0].matches("root.sg.d1", "s2"));
+        assert_eq!((deletions[0].start(), deletions[0].end()), (10, 20));
+        assert!(deletions[0].covers_chunk(119) && !deletions[0].covers_chunk(120));
+        assert!(deletions[1].matches("root.sg.d2", "s1"));
+        assert_eq!((deletions[1].start(), deletions[1].end()), (i64::MIN, 100));
+
+        assert!(parse_mods("DELETION,root.sg.d1.s1,x,1,2").is_err());
+        assert!(parse_mods("UPDATE,root.sg.d1.s1,1,1,2").is_err());
+    }
+}
@@ -0,0 +1,105 @@
+use std::io::{self, Cursor, Read};
+use std::sync::Arc;
+
+use object_store::path::Path;
+use object_store::ObjectStore;
+use snafu::ResultExt;
+use tokio::runtime::Handle;
+
+use crate::file::async_reader::AsyncSectionReader;
+use crate::file::reader::{Length, ReadFixedLength, SectionReader};
+
+/// A TsFile in an object store (S3, GCS, Azure, ...). Every footer, index node and chunk
+/// read is a single range GET, so the file is never downloaded as a whole.
+///
+/// The blocking `SectionReader` methods run the requests on the runtime the reader was
+/// opened on, so they must not be called from that runtime's worker threads; use
+/// `spawn_blocking` or the `AsyncSectionReader` methods there.
+#[derive(Debug)]
+pub struct ObjectStoreReader {
+    store: Arc<dyn ObjectStore>,
+    location: Path,
+    len: u64,
+    runtime: Handle,
+}
+
+impl ObjectStoreReader {
+    pub async fn open(store: Arc<dyn ObjectStore>, location: Path) -> io::Result<Self> {
+        let meta = store.head(&location).await.map_err(io::Error::other)?;
+        Ok(Self {
+            store,
+            location,
+            len: meta.size,
+            runtime: Handle::current(),
+        })
+    }
+
+    async fn fetch(&self, start: u64, len: usize) -> io::Result<Vec<u8>> {
+        let bytes = self
+            .store
+            .get_range(&self.location, start..start + len as u64)
+            .await
+            .map_err(io::Error::other)?;
+        if bytes.len() != len {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(bytes.to_vec())
+    }
+}
+
+impl AsyncSectionReader for ObjectStoreReader {
+    fn len(&self) -> u64 {
+        self.len
+    }
+
+    async fn get_bytes(&self, start: u64, len: usize) -> io::Result<Vec<u8>> {
+        self.fetch(start, len).await
+    }
+}
+
+impl Length for ObjectStoreReader {
+    fn len(&self) -> u64 {
+        self.len
+    }
+}
+
+impl SectionReader for ObjectStoreReader {
+    type T = FetchedRange;
+
+    fn get_read(&self, start: u64, len: usize) -> Self::T {
+        match self.runtime.block_on(self.fetch(start, len)) {
+            Ok(data) => FetchedRange {
+                data: Cursor::new(data),
+                error: None,
+            },
+            Err(e) => FetchedRange {
+                data: Cursor::new(Vec::new()),
+                error: Some(e),
+            },
+        }
+    }
+
+    fn get_cursor(&self, start: u64, len: usize) -> crate::file::reader::Result<Cursor<Vec<u8>>> {
+        let data = self
+            .runtime
+            .block_on(self.fetch(start, len))
+            .context(ReadFixedLength { len })?;
+        Ok(Cursor::new(data))
+    }
+}
+
+/// A fetched byte range; a failed request is reported by the first read.
+#[derive(Debug)]
+pub struct FetchedRange {
+    data: Cursor<Vec<u8>>,
+    error: Option<io::Error>,
+}
+
+impl Read for FetchedRange {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.error.take() {
+            Some(e) => Err(e),
+            None => self.data.read(buf),
+        }
+    }
+}
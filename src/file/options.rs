@@ -0,0 +1,274 @@
+use std::sync::Arc;
+
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, Utc};
+
+use crate::chunk::reader::TSEncoding;
+use crate::file::mods::ModsHandling;
+use crate::query::merge::DuplicatePolicy;
+use crate::utils::pool::BufferPool;
+
+/// Default size of the buffer behind each section read.
+pub const DEFAULT_BUFFER_SIZE: usize = 8 * 1024;
+
+/// Default limit of a chunk or decompressed page buffer.
+pub const DEFAULT_MAX_ALLOCATION: usize = 256 * 1024 * 1024;
+
+/// The unit of the timestamps in a file, set by the `timestamp_precision` of the IoTDB
+/// server that wrote it. Files do not record it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimestampPrecision {
+    #[default]
+    Millisecond,
+    Microsecond,
+    Nanosecond,
+}
+
+impl TimestampPrecision {
+    /// Timestamp units per second.
+    pub fn units_per_second(self) -> i64 {
+        match self {
+            TimestampPrecision::Millisecond => 1_000,
+            TimestampPrecision::Microsecond => 1_000_000,
+            TimestampPrecision::Nanosecond => 1_000_000_000,
+        }
+    }
+
+    /// The instant of a timestamp, `None` if it is outside the range `DateTime` supports.
+    #[cfg(feature = "chrono")]
+    pub fn to_datetime(self, time: i64) -> Option<DateTime<Utc>> {
+        match self {
+            TimestampPrecision::Millisecond => DateTime::from_timestamp_millis(time),
+            TimestampPrecision::Microsecond => DateTime::from_timestamp_micros(time),
+            TimestampPrecision::Nanosecond => Some(DateTime::from_timestamp_nanos(time)),
+        }
+    }
+}
+
+/// How a reader handles parts of a file it cannot read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ParseMode {
+    /// Any unreadable index node, timeseries, chunk or page is returned as an error.
+    #[default]
+    Strict,
+    /// Unreadable parts are skipped and their errors kept as warnings, so the rest of the
+    /// file can still be read.
+    Lenient,
+}
+
+/// The order in which pages yield their points.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimeOrder {
+    /// As stored. IoTDB writes the points of a page in strictly increasing time, but other
+    /// writers may not.
+    #[default]
+    AsStored,
+    /// Stably sorted by time, with one point per timestamp: the policy picks between the
+    /// points of a page sharing it, `LatestWins` keeping the one stored last.
+    Increasing(DuplicatePolicy),
+}
+
+/// Tuning knobs of `TsFileSearchReader`.
+#[derive(Debug, Clone)]
+pub struct ReaderOptions {
+    buffer_size: usize,
+    read_ahead: bool,
+    buffer_pool: Option<Arc<BufferPool>>,
+    max_allocation: usize,
+    coalesce_limit: usize,
+    timestamp_precision: TimestampPrecision,
+    parse_mode: ParseMode,
+    metadata_cache_capacity: usize,
+    bloom_filter: bool,
+    incremental_metadata: bool,
+    mods: ModsHandling,
+    verify_pages: bool,
+    time_encoding: TSEncoding,
+    time_order: TimeOrder,
+}
+
+impl Default for ReaderOptions {
+    fn default() -> Self {
+        Self {
+            buffer_size: DEFAULT_BUFFER_SIZE,
+            read_ahead: false,
+            buffer_pool: None,
+            max_allocation: DEFAULT_MAX_ALLOCATION,
+            coalesce_limit: 0,
+            timestamp_precision: TimestampPrecision::default(),
+            parse_mode: ParseMode::default(),
+            metadata_cache_capacity: 0,
+            bloom_filter: true,
+            incremental_metadata: false,
+            mods: ModsHandling::default(),
+            verify_pages: false,
+            time_encoding: TSEncoding::Ts2diff,
+            time_order: TimeOrder::default(),
+        }
+    }
+}
+
+impl ReaderOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Size of the buffer used when reading file sections.
+    pub fn with_buffer_size(mut self, buffer_size: usize) -> Self {
+        self.buffer_size = buffer_size.max(1);
+        self
+    }
+
+    /// Whether sensor readers fetch the next chunk in the background while the current
+    /// one is decoded, which speeds up sequential chunk scans. Ignored on wasm32, which has
+    /// no threads.
+    pub fn with_read_ahead(mut self, read_ahead: bool) -> Self {
+        self.read_ahead = read_ahead;
+        self
+    }
+
+    /// A pool the sensor readers take chunk read buffers from. Buffers not kept by the
+    /// pages of a chunk are returned to it.
+    pub fn with_buffer_pool(mut self, pool: Arc<BufferPool>) -> Self {
+        self.buffer_pool = Some(pool);
+        self
+    }
+
+    /// Largest chunk or decompressed page, in bytes, a reader allocates a buffer for. Larger
+    /// sizes read from a file are reported as errors, so a corrupt file cannot trigger huge
+    /// allocations.
+    pub fn with_max_allocation(mut self, max_allocation: usize) -> Self {
+        self.max_allocation = max_allocation;
+        self
+    }
+
+    /// Largest number of bytes a sensor reader reads in one request to fetch several
+    /// consecutive chunks at once, which saves round trips on networked storage. `0`, the
+    /// default, reads every chunk on its own.
+    pub fn with_coalesce_limit(mut self, coalesce_limit: usize) -> Self {
+        self.coalesce_limit = coalesce_limit;
+        self
+    }
+
+    /// The unit of the file's timestamps, milliseconds by default.
+    pub fn with_timestamp_precision(mut self, precision: TimestampPrecision) -> Self {
+        self.timestamp_precision = precision;
+        self
+    }
+
+    /// Whether unreadable parts of the file are errors, the default, or skipped. Skipped
+    /// errors are returned by `FileReader::take_warnings`.
+    pub fn with_parse_mode(mut self, mode: ParseMode) -> Self {
+        self.parse_mode = mode;
+        self
+    }
+
+    /// Number of index nodes kept after being read, so repeated lookups of the same devices
+    /// do not read and parse them again. `0`, the default, keeps none.
+    pub fn with_metadata_cache_capacity(mut self, capacity: usize) -> Self {
+        self.metadata_cache_capacity = capacity;
+        self
+    }
+
+    /// Whether the bloom filter of the metadata is parsed and kept in `FileMeta`. Files with
+    /// millions of series have filters of several megabytes.
+    pub fn with_bloom_filter(mut self, bloom_filter: bool) -> Self {
+        self.bloom_filter = bloom_filter;
+        self
+    }
+
+    /// Whether opening a file reads only the root of its metadata index rather than the
+    /// whole metadata, whose bloom filter runs to megabytes in files with millions of
+    /// series. The bloom filter, and the properties of version 4 files, are then left out.
+    /// The other index nodes are read when a query needs them either way, and kept only as
+    /// the metadata cache capacity allows.
+    pub fn with_incremental_metadata(mut self, incremental_metadata: bool) -> Self {
+        self.incremental_metadata = incremental_metadata;
+        self
+    }
+
+    /// Whether readers opened from a path apply the deletions of its `.mods` file.
+    pub fn with_mods(mut self, mods: ModsHandling) -> Self {
+        self.mods = mods;
+        self
+    }
+
+    /// Whether every page decoded is checked against its header and statistics, failing
+    /// with the chunk offset and page of the first mismatch. Meant for validating archived
+    /// files; it costs an extra pass over the decoded timestamps of each page.
+    pub fn with_verify_pages(mut self, verify_pages: bool) -> Self {
+        self.verify_pages = verify_pages;
+        self
+    }
+
+    /// The encoding of the time columns, TS_2DIFF unless the writer was configured with
+    /// another time encoder. Files do not record it for non-aligned series.
+    pub fn with_time_encoding(mut self, encoding: TSEncoding) -> Self {
+        self.time_encoding = encoding;
+        self
+    }
+
+    /// Whether pages are sorted by time and rid of duplicate timestamps as they are
+    /// decoded, for writers that do not keep them strictly increasing. Each page is ordered
+    /// on its own; pages and chunks are expected to follow each other in time.
+    pub fn with_time_order(mut self, order: TimeOrder) -> Self {
+        self.time_order = order;
+        self
+    }
+
+    pub fn buffer_size(&self) -> usize {
+        self.buffer_size
+    }
+
+    pub fn read_ahead(&self) -> bool {
+        self.read_ahead
+    }
+
+    pub fn buffer_pool(&self) -> Option<&Arc<BufferPool>> {
+        self.buffer_pool.as_ref()
+    }
+
+    pub fn max_allocation(&self) -> usize {
+        self.max_allocation
+    }
+
+    pub fn coalesce_limit(&self) -> usize {
+        self.coalesce_limit
+    }
+
+    pub fn timestamp_precision(&self) -> TimestampPrecision {
+        self.timestamp_precision
+    }
+
+    pub fn parse_mode(&self) -> ParseMode {
+        self.parse_mode
+    }
+
+    pub fn metadata_cache_capacity(&self) -> usize {
+        self.metadata_cache_capacity
+    }
+
+    pub fn bloom_filter(&self) -> bool {
+        self.bloom_filter
+    }
+
+    pub fn incremental_metadata(&self) -> bool {
+        self.incremental_metadata
+    }
+
+    pub fn mods(&self) -> ModsHandling {
+        self.mods
+    }
+
+    pub fn verify_pages(&self) -> bool {
+        self.verify_pages
+    }
+
+    pub fn time_encoding(&self) -> TSEncoding {
+        self.time_encoding
+    }
+
+    pub fn time_order(&self) -> TimeOrder {
+        self.time_order
+    }
+}
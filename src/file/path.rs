@@ -0,0 +1,176 @@
+/// An IoTDB style timeseries path pattern, e.g. `root.sg.*.temperature`.
+///
+/// Levels are separated by `.`. A `*` inside a level matches any characters within that
+/// level (`*` alone matches one whole level) and `**` matches one or more levels.
+#[derive(Debug, Clone)]
+pub struct PathPattern {
+    /// A path matches if one of these matches.
+    alternatives: Vec<Alternative>,
+}
+
+#[derive(Debug, Clone)]
+struct Alternative {
+    nodes: Vec<String>,
+    literal_prefix: String,
+}
+
+impl PathPattern {
+    pub fn new(pattern: &str) -> Self {
+        let literal_prefix = match pattern.find('*') {
+            Some(i) => pattern[..i].to_string(),
+            None => pattern.to_string(),
+        };
+        Self {
+            alternatives: vec![Alternative {
+                nodes: pattern.split('.').map(|n| n.to_string()).collect(),
+                literal_prefix,
+            }],
+        }
+    }
+
+    /// A pattern matching every timeseries below `prefix`, e.g. `root.sg1`.
+    pub fn prefix(prefix: &str) -> Self {
+        Self::new(&format!("{}.**", prefix))
+    }
+
+    /// A pattern matching the paths any of `patterns` matches, and nothing without
+    /// patterns.
+    pub fn any(patterns: impl IntoIterator<Item = PathPattern>) -> Self {
+        Self {
+            alternatives: patterns
+                .into_iter()
+                .flat_map(|pattern| pattern.alternatives)
+                .collect(),
+        }
+    }
+
+    /// A pattern matching exactly `sensors` of `device`, so that readers and exports
+    /// given it look up only these timeseries in the index and read only their chunks.
+    pub fn sensors<S: AsRef<str>>(device: &str, sensors: &[S]) -> Self {
+        Self::any(
+            sensors
+                .iter()
+                .map(|sensor| Self::new(&format!("{}.{}", device, sensor.as_ref()))),
+        )
+    }
+
+    /// Whether the full path `device.sensor` matches.
+    pub fn matches(&self, path: &str) -> bool {
+        let levels: Vec<&str> = path.split('.').collect();
+        self.alternatives
+            .iter()
+            .any(|alternative| match_nodes(&alternative.nodes, &levels))
+    }
+
+    /// Whether some sensor of `device` could match.
+    pub fn may_match_device(&self, device: &str) -> bool {
+        let levels: Vec<&str> = device.split('.').collect();
+        self.alternatives
+            .iter()
+            .any(|alternative| match_prefix(&alternative.nodes, &levels))
+    }
+
+    /// Whether some name in `[lo, hi)` could match, `hi = None` meaning unbounded. Used to
+    /// prune index entries, whose children cover exactly such a range.
+    pub fn may_match_range(&self, lo: &str, hi: Option<&str>) -> bool {
+        self.alternatives
+            .iter()
+            .any(|alternative| alternative.may_match_range(lo, hi))
+    }
+}
+
+impl Alternative {
+    fn may_match_range(&self, lo: &str, hi: Option<&str>) -> bool {
+        let prefix = self.literal_prefix.as_str();
+        let below_hi = |s: &str| hi.is_none_or(|h| s < h);
+
+        // names starting with the literal prefix
+        if (lo < prefix || lo.starts_with(prefix)) && below_hi(prefix) {
+            return true;
+        }
+        // names that are an ancestor of the literal prefix, i.e. devices of deeper paths
+        prefix
+            .match_indices('.')
+            .map(|(i, _)| &prefix[..i])
+            .any(|ancestor| lo <= ancestor && below_hi(ancestor))
+    }
+}
+
+fn match_nodes(pattern: &[String], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((p, rest)) if p == "**" => (1..=path.len()).any(|i| match_nodes(rest, &path[i..])),
+        Some((p, rest)) => match path.split_first() {
+            Some((node, tail)) => match_node(p, node) && match_nodes(rest, tail),
+            None => false,
+        },
+    }
+}
+
+fn match_prefix(pattern: &[String], path: &[&str]) -> bool {
+    match (pattern.split_first(), path.split_first()) {
+        (None, _) => false,
+        (Some(_), None) => true,
+        (Some((p, _)), Some(_)) if p == "**" => true,
+        (Some((p, rest)), Some((node, tail))) => match_node(p, node) && match_prefix(rest, tail),
+    }
+}
+
+fn match_node(pattern: &str, node: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == node;
+    }
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    if node.len() < first.len() + last.len() || !node.starts_with(first) || !node.ends_with(last) {
+        return false;
+    }
+    let mut rest = &node[first.len()..node.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PathPattern;
+
+    #[test]
+    fn match_patterns() {
+        let p = PathPattern::new("root.sg.*.temperature");
+        assert!(p.matches("root.sg.d1.temperature"));
+        assert!(!p.matches("root.sg.d1.humidity"));
+        assert!(!p.matches("root.sg.a.b.temperature"));
+        assert!(p.may_match_device("root.sg.d1"));
+        assert!(!p.may_match_device("root.sg2.d1"));
+
+        let p = PathPattern::prefix("root.sg1");
+        assert!(p.matches("root.sg1.d1.s1"));
+        assert!(!p.matches("root.sg10.d1.s1"));
+        assert!(PathPattern::new("root.sg.d*.s1").matches("root.sg.d12.s1"));
+    }
+
+    #[test]
+    fn prune_ranges() {
+        let p = PathPattern::new("root.sg1.d2.s1");
+        assert!(p.may_match_range("root.sg1.d1", Some("root.sg1.d3")));
+        assert!(!p.may_match_range("root.sg1.d3", None));
+        assert!(!p.may_match_range("root.sg0", Some("root.sg1")));
+        assert!(p.may_match_range("root.sg1.d2.s0", None));
+    }
+
+    #[test]
+    fn sensors_of_a_device() {
+        let p = PathPattern::sensors("root.sg.d1", &["s1", "s3"]);
+        assert!(p.matches("root.sg.d1.s1") && p.matches("root.sg.d1.s3"));
+        assert!(!p.matches("root.sg.d1.s2"));
+        assert!(p.may_match_device("root.sg.d1"));
+        assert!(!p.may_match_range("root.sg.d1.s4", None));
+        assert!(p.may_match_range("root.sg.d1.s2", Some("root.sg.d1.s4")));
+        assert!(!PathPattern::sensors::<&str>("root.sg.d1", &[]).matches("root.sg.d1.s1"));
+    }
+}
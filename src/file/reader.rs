@@ -1,27 +1,54 @@
+#[cfg(feature = "fs")]
 use std::fs::File;
 use std::io;
 use std::io::{Cursor, Read};
+use std::sync::mpsc::Receiver;
+
+use bytes::Bytes;
 
 use crate::chunk;
-use snafu::{ResultExt, Snafu};
+use snafu::{ensure, ResultExt, Snafu};
 
-use crate::chunk::reader::PageHeader;
-use crate::encoding::decoder::Field;
+use crate::chunk::reader::{ChunkHeader, PageHeader};
+use crate::encoding::decoder::{ColumnBatch, Field};
+use crate::error::{self, TsFileError};
 use crate::file::metadata::{
-    ChunkMetadata, MetadataIndexNodeType, TimeseriesMetadata, TsFileMetadata,
+    ChunkMetadata, DeviceSchema, MetadataIndexNodeType, TimeseriesMetadata, TsFileMetadata,
 };
-use crate::utils::io::FileSource;
+use crate::file::options::TimestampPrecision;
+use crate::file::path::PathPattern;
+use crate::file::summary::FileSummary;
+use crate::query::filter::Filter;
+use crate::query::rows::{self, DeviceRows};
+#[cfg(feature = "fs")]
+use crate::utils::io::{read_exact_at, FileSource};
 
 #[derive(Debug, Snafu)]
+#[snafu(visibility(pub(crate)))]
 pub enum Error {
     #[snafu(display("Unable to read fixed length {} data: {}", len, source))]
     ReadFixedLength { len: usize, source: io::Error },
+    #[snafu(display(
+        "Read of {} bytes at {} exceeds file of {} bytes",
+        len,
+        start,
+        file_len
+    ))]
+    ReadPastEnd {
+        start: u64,
+        len: usize,
+        file_len: u64,
+    },
 }
 
-type Result<T, E = Error> = std::result::Result<T, E>;
+pub(crate) type Result<T, E = Error> = std::result::Result<T, E>;
 
 pub trait Length {
     fn len(&self) -> u64;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 }
 
 pub trait TryClone: Sized {
@@ -32,18 +59,162 @@ pub trait SectionReader: Length {
     type T: Read;
     fn get_read(&self, start: u64, len: usize) -> Self::T;
     fn get_cursor(&self, start: u64, len: usize) -> Result<Cursor<Vec<u8>>>;
+
+    /// Reads `len` bytes at `start` into `buf`, replacing its contents and reusing its
+    /// allocation.
+    fn read_into(&self, start: u64, len: usize, buf: &mut Vec<u8>) -> Result<()> {
+        check_range(self, start, len)?;
+        buf.clear();
+        buf.resize(len, 0);
+        self.get_read(start, len)
+            .read_exact(buf)
+            .context(ReadFixedLength { len })
+    }
+}
+
+/// Fails unless `len` bytes at `start` lie within `reader`, so lengths read from a corrupt
+/// file cannot make a read allocate more than the file holds.
+pub(crate) fn check_range<R: Length + ?Sized>(reader: &R, start: u64, len: usize) -> Result<()> {
+    let file_len = reader.len();
+    ensure!(
+        start
+            .checked_add(len as u64)
+            .is_some_and(|end| end <= file_len),
+        ReadPastEnd {
+            start,
+            len,
+            file_len
+        }
+    );
+    Ok(())
 }
 
 pub trait FileReader {
     fn metadata(&self) -> &TsFileMetadata;
-    fn device_meta_iter(&self) -> Box<dyn DeviceMetadataIter<Item = MetadataIndexNodeType>>;
+    /// The leaf device nodes of the index. In strict mode a node that cannot be read is
+    /// returned as an error and ends the iteration.
+    fn device_meta_iter(
+        &self,
+    ) -> Box<dyn DeviceMetadataIter<Item = error::Result<MetadataIndexNodeType>>>;
     fn get_device_reader();
+
+    /// The unit of the timestamps this reader returns.
+    fn timestamp_precision(&self) -> TimestampPrecision {
+        TimestampPrecision::default()
+    }
+
+    /// Ids of all devices in the file, in index order.
+    fn devices(&self) -> error::Result<Vec<String>> {
+        let mut devices = Vec::new();
+        for node in self.device_meta_iter() {
+            if let MetadataIndexNodeType::LeafDevice(c) = node? {
+                devices.extend(c.children().iter().map(|entry| entry.name().to_string()));
+            }
+        }
+        Ok(devices)
+    }
+
+    /// The timeseries of `device`. In strict mode a node that cannot be read is returned as
+    /// an error and ends the iteration.
     fn sensor_meta_iter(
         &self,
         device: &str,
-    ) -> Box<dyn SensorMetadataIter<Item = TimeseriesMetadata>>;
+    ) -> Box<dyn SensorMetadataIter<Item = error::Result<TimeseriesMetadata>>>;
+
+    /// The sensors of `device` and whether it is aligned.
+    fn device_schema(&self, device: &str) -> error::Result<DeviceSchema> {
+        let mut schema = DeviceSchema::default();
+        for series in self.sensor_meta_iter(device) {
+            schema.add(&series?);
+        }
+        Ok(schema)
+    }
+
+    /// Every `(device, chunk metadata)` of the file, time columns included, in the order the
+    /// chunks are stored.
+    fn chunk_metadata_iter(&self) -> error::Result<std::vec::IntoIter<(String, ChunkMetadata)>> {
+        let mut chunks = Vec::new();
+        for device in self.devices()? {
+            for series in self.sensor_meta_iter(&device) {
+                for chunk in series?.chunk_metadata_list() {
+                    chunks.push((device.clone(), chunk));
+                }
+            }
+        }
+        chunks.sort_by_key(|(_, chunk)| chunk.offset_chunk_header());
+        Ok(chunks.into_iter())
+    }
+
+    /// `(start_time, end_time)` of all series of the file, both inclusive, taken from the
+    /// timeseries statistics. `None` for a file without series.
+    fn time_range(&self) -> error::Result<Option<(i64, i64)>> {
+        let mut range: Option<(i64, i64)> = None;
+        for device in self.devices()? {
+            for series in self.sensor_meta_iter(&device) {
+                let (start, end) = series?.statistic().time_range();
+                range = Some(match range {
+                    Some((min, max)) => (min.min(start), max.max(end)),
+                    None => (start, end),
+                });
+            }
+        }
+        Ok(range)
+    }
 
-    fn get_sensor_reader(&self, device: &str, sensor: &str) -> Option<Box<dyn SensorReader>>;
+    fn get_sensor_reader(
+        &self,
+        device: &str,
+        sensor: &str,
+    ) -> error::Result<Option<Box<dyn SensorReader>>>;
+
+    /// Latest `(sensor, timestamp, value)` of every sensor of `device`, taken from the
+    /// timeseries statistics without reading any chunk data.
+    fn last_points(&self, device: &str) -> error::Result<Vec<(String, i64, Field)>>;
+
+    /// All `(device, timeseries)` pairs matching `pattern`, in index order. Index subtrees
+    /// whose names cannot match are never read.
+    fn match_paths(
+        &self,
+        pattern: &PathPattern,
+    ) -> error::Result<Vec<(String, TimeseriesMetadata)>>;
+
+    /// Reads every timeseries matching `pattern` on `parallelism` worker threads. Each
+    /// decoded page is sent as one `SeriesBatch` with the points `filter` rejects removed;
+    /// the channel is closed once all series are read or the receiver is dropped. On
+    /// wasm32, which has no threads, all series are read before `scan` returns.
+    fn scan(
+        &self,
+        pattern: &PathPattern,
+        filter: Option<Filter>,
+        parallelism: usize,
+    ) -> Receiver<error::Result<SeriesBatch>>;
+
+    /// The rows of every timeseries matching `pattern`, restricted to `filter`, joined on
+    /// their timestamps with one `device.sensor` column each. Pages are decoded as the rows
+    /// are consumed; see `RowChannel` to decode them on a background thread instead.
+    fn stream(&self, pattern: &PathPattern, filter: Option<&Filter>) -> error::Result<DeviceRows> {
+        DeviceRows::of_paths(self, &rows::paths_of(self, pattern)?, filter)
+    }
+
+    /// The point counts, time spans, sizes, compression ratios, encodings and compressions
+    /// of all series, read from the metadata, chunk headers and page headers.
+    fn summary(&self) -> error::Result<FileSummary> {
+        FileSummary::of(self)
+    }
+
+    /// The errors skipped in lenient mode since the last call, see `ParseMode`.
+    fn take_warnings(&self) -> Vec<TsFileError> {
+        Vec::new()
+    }
+}
+
+/// The points of one page of a timeseries, produced by `FileReader::scan`.
+#[derive(Debug)]
+pub struct SeriesBatch {
+    pub device: String,
+    pub sensor: String,
+    pub times: Vec<i64>,
+    pub values: ColumnBatch,
 }
 
 pub trait DeviceMetadataIter: Iterator {}
@@ -64,38 +235,59 @@ pub trait SensorReader {
     fn get_chunk_reader(
         &self,
         i: usize,
-    ) -> std::result::Result<Box<dyn ChunkReader<Item = Box<dyn PageReader>>>, chunk::reader::Error>;
+    ) -> std::result::Result<Box<dyn ChunkReader<Item = PageResult>>, chunk::reader::Error>;
+
+    /// The parsed header and the undecoded page bytes of the i-th chunk, so chunks can be
+    /// copied between files without a decode/encode round trip.
+    fn get_raw_chunk(
+        &self,
+        i: usize,
+    ) -> std::result::Result<(ChunkHeader, Vec<u8>), chunk::reader::Error>;
+
+    /// Restricts the chunk readers created afterwards to points matching `filter`. Chunks
+    /// and pages whose statistics cannot match are skipped without being read or decoded.
+    fn set_filter(&mut self, filter: Filter);
 }
 
-pub trait ChunkReader: Iterator {}
+/// A page of a chunk, or the error that ended reading the chunk's pages.
+pub type PageResult = std::result::Result<Box<dyn PageReader>, chunk::reader::Error>;
+
+/// The `(timestamp, value)` pairs of a page, see `PageReader::point_iter`.
+pub type PointIter =
+    Box<dyn Iterator<Item = std::result::Result<(i64, Field), chunk::reader::Error>>>;
+
+pub trait ChunkReader: Iterator {
+    /// The parsed chunk header, `None` for chunks skipped by a filter without being read.
+    fn header(&self) -> Option<&ChunkHeader>;
+}
 
 pub trait PageReader {
     fn header(&self) -> &PageHeader;
     fn data(&self) -> std::result::Result<(Vec<Field>, Vec<Field>), chunk::reader::Error>;
-}
 
-pub struct RowIter {
-    current_row_group: usize,
-    num_row_groups: usize,
-    iters: Vec<Box<dyn PageReader>>,
-}
+    /// Like `data`, decompressing the page into the caller's `scratch` buffer.
+    fn data_with(
+        &self,
+        scratch: &mut Vec<u8>,
+    ) -> std::result::Result<(Vec<Field>, Vec<Field>), chunk::reader::Error>;
 
-impl RowIter {
-    pub fn new(iters: Vec<Box<dyn PageReader>>) -> Self {
-        Self {
-            current_row_group: 0,
-            num_row_groups: 0,
-            iters,
-        }
-    }
+    /// `(timestamp, value)` pairs decoded lazily, so large pages can be consumed without
+    /// materializing both columns.
+    fn point_iter(&self) -> std::result::Result<PointIter, chunk::reader::Error>;
+
+    /// Like `data`, with the timestamps and values in contiguous typed vectors instead of
+    /// one `Field` per point.
+    fn batch(&self) -> std::result::Result<(Vec<i64>, ColumnBatch), chunk::reader::Error>;
 }
 
+#[cfg(feature = "fs")]
 impl Length for File {
     fn len(&self) -> u64 {
         self.metadata().map(|m| m.len()).unwrap_or(0u64)
     }
 }
 
+#[cfg(feature = "fs")]
 impl SectionReader for File {
     type T = FileSource<File>;
 
@@ -104,17 +296,121 @@ impl SectionReader for File {
     }
 
     fn get_cursor(&self, start: u64, len: usize) -> Result<Cursor<Vec<u8>>> {
-        let mut reader = self.get_read(start, len);
-        let mut data = vec![0; len];
-        reader
-            .read_exact(&mut data)
-            .context(ReadFixedLength { len })?;
+        let mut data = Vec::new();
+        self.read_into(start, len, &mut data)?;
         Ok(Cursor::new(data))
     }
+
+    fn read_into(&self, start: u64, len: usize, buf: &mut Vec<u8>) -> Result<()> {
+        check_range(self, start, len)?;
+        buf.clear();
+        buf.resize(len, 0);
+        read_exact_at(self, buf, start).context(ReadFixedLength { len })
+    }
+}
+
+#[cfg(feature = "fs")]
+/// A `File` whose section reads use a buffer of a configured size.
+#[derive(Debug)]
+pub struct BufferedFile {
+    file: File,
+    buffer_size: usize,
 }
 
+#[cfg(feature = "fs")]
+impl BufferedFile {
+    pub fn new(file: File, buffer_size: usize) -> Self {
+        Self { file, buffer_size }
+    }
+}
+
+#[cfg(feature = "fs")]
+impl Length for BufferedFile {
+    fn len(&self) -> u64 {
+        Length::len(&self.file)
+    }
+}
+
+#[cfg(feature = "fs")]
+impl SectionReader for BufferedFile {
+    type T = FileSource<File>;
+
+    fn get_read(&self, start: u64, length: usize) -> Self::T {
+        FileSource::with_buffer_size(&self.file, start, length, self.buffer_size)
+    }
+
+    fn get_cursor(&self, start: u64, len: usize) -> Result<Cursor<Vec<u8>>> {
+        let mut data = Vec::new();
+        self.read_into(start, len, &mut data)?;
+        Ok(Cursor::new(data))
+    }
+
+    fn read_into(&self, start: u64, len: usize, buf: &mut Vec<u8>) -> Result<()> {
+        check_range(self, start, len)?;
+        buf.clear();
+        buf.resize(len, 0);
+        read_exact_at(&self.file, buf, start).context(ReadFixedLength { len })
+    }
+}
+
+#[cfg(feature = "fs")]
 impl TryClone for File {
     fn try_clone(&self) -> std::io::Result<Self> {
         self.try_clone()
     }
 }
+
+#[cfg(feature = "fs")]
+impl TryClone for BufferedFile {
+    fn try_clone(&self) -> std::io::Result<Self> {
+        Ok(Self::new(self.file.try_clone()?, self.buffer_size))
+    }
+}
+
+/// A file held in memory, e.g. fetched by a browser. Needs no file system.
+impl Length for Bytes {
+    fn len(&self) -> u64 {
+        Bytes::len(self) as u64
+    }
+}
+
+impl TryClone for Bytes {
+    fn try_clone(&self) -> std::io::Result<Self> {
+        Ok(self.clone())
+    }
+}
+
+impl SectionReader for Bytes {
+    type T = Cursor<Bytes>;
+
+    fn get_read(&self, start: u64, len: usize) -> Self::T {
+        let start = (start as usize).min(Bytes::len(self));
+        let end = start.saturating_add(len).min(Bytes::len(self));
+        Cursor::new(self.slice(start..end))
+    }
+
+    fn get_cursor(&self, start: u64, len: usize) -> Result<Cursor<Vec<u8>>> {
+        check_range(self, start, len)?;
+        let start = start as usize;
+        Ok(Cursor::new(self[start..start + len].to_vec()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bytes_sections_stay_in_bounds() {
+        let data = Bytes::from_static(b"TsFile\x03");
+        assert_eq!(data.get_cursor(1, 3).unwrap().into_inner(), b"sFi");
+        assert!(matches!(
+            data.get_cursor(5, 3),
+            Err(Error::ReadPastEnd { file_len: 7, .. })
+        ));
+        let mut buf = Vec::new();
+        assert!(data.read_into(6, 2, &mut buf).is_err());
+        data.read_into(6, 1, &mut buf).unwrap();
+        assert_eq!(buf, [3]);
+    }
+}
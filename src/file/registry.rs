@@ -0,0 +1,124 @@
+//! Decoders and decompressors registered at runtime, for the encodings and compressions of
+//! forked or experimental IoTDB builds. Registrations are process wide, apply to readers
+//! opened before and after them, and take precedence over the built-in codecs of the same
+//! id.
+
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock, RwLock};
+
+use crate::encoding::decoder::Decoder;
+use crate::file::compress::Decompressor;
+use crate::file::metadata::TSDataType;
+
+/// Creates the decoder of a data type, `None` for types the encoding does not support,
+/// which are then decoded by the built-in decoder of the id.
+pub type DecoderFactory = dyn Fn(&TSDataType) -> Option<Box<dyn Decoder>> + Send + Sync;
+
+fn decoders() -> &'static RwLock<HashMap<u8, Arc<DecoderFactory>>> {
+    static DECODERS: OnceLock<RwLock<HashMap<u8, Arc<DecoderFactory>>>> = OnceLock::new();
+    DECODERS.get_or_init(Default::default)
+}
+
+fn decompressors() -> &'static RwLock<HashMap<u8, Arc<dyn Decompressor>>> {
+    static DECOMPRESSORS: OnceLock<RwLock<HashMap<u8, Arc<dyn Decompressor>>>> = OnceLock::new();
+    DECOMPRESSORS.get_or_init(Default::default)
+}
+
+/// Decodes the pages whose chunk header has `encoding_id` with the decoders of `factory`,
+/// replacing an earlier registration of the id.
+pub fn register_decoder<F>(encoding_id: u8, factory: F)
+where
+    F: Fn(&TSDataType) -> Option<Box<dyn Decoder>> + Send + Sync + 'static,
+{
+    decoders()
+        .write()
+        .unwrap()
+        .insert(encoding_id, Arc::new(factory));
+}
+
+/// Decompresses the pages whose chunk header has `compression_id` with `codec`, replacing
+/// an earlier registration of the id.
+pub fn register_decompressor(compression_id: u8, codec: Arc<dyn Decompressor>) {
+    decompressors()
+        .write()
+        .unwrap()
+        .insert(compression_id, codec);
+}
+
+pub(crate) fn decoder(encoding_id: u8, data_type: &TSDataType) -> Option<Box<dyn Decoder>> {
+    // the factory runs outside the lock, so it may itself look up decoders
+    let factory = decoders().read().unwrap().get(&encoding_id).cloned()?;
+    factory(data_type)
+}
+
+pub(crate) fn decompressor(compression_id: u8) -> Option<Arc<dyn Decompressor>> {
+    decompressors()
+        .read()
+        .unwrap()
+        .get(&compression_id)
+        .cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::chunk::reader::{CompressionType, TSEncoding};
+    use crate::encoding::decoder::{value_decoder, ColumnBatch, Error as DecodeError};
+    use crate::file::compress::{self, decompressor};
+
+    struct Reversed;
+
+    impl Decompressor for Reversed {
+        fn decompress<'a>(
+            &self,
+            data: &'a [u8],
+            _: usize,
+        ) -> Result<Cow<'a, [u8]>, compress::Error> {
+            Ok(Cow::Owned(data.iter().rev().copied().collect()))
+        }
+    }
+
+    struct Widened;
+
+    impl Decoder for Widened {
+        fn new() -> Self {
+            Widened
+        }
+
+        fn decode_batch(&self, data: &mut Cursor<&[u8]>) -> Result<ColumnBatch, DecodeError> {
+            Ok(ColumnBatch::Int32(
+                data.get_ref().iter().map(|b| *b as i32).collect(),
+            ))
+        }
+    }
+
+    #[test]
+    fn registered_codecs_are_used() {
+        let unknown = CompressionType::new(200);
+        assert_eq!(unknown, CompressionType::Other(200));
+        assert!(decompressor(&unknown).is_err());
+        register_decompressor(200, Arc::new(Reversed));
+        let page = decompressor(&unknown)
+            .unwrap()
+            .decompress(&[1, 2], 2)
+            .unwrap();
+        assert_eq!(page, [2, 1].as_slice());
+
+        let encoding = TSEncoding::new(200);
+        let decode = || {
+            value_decoder(&encoding, &TSDataType::Int32).decode_batch(&mut Cursor::new(&[3, 4][..]))
+        };
+        assert!(decode().is_err());
+        register_decoder(200, |data_type| match data_type {
+            TSDataType::Int32 => Some(Box::new(Widened::new()) as Box<dyn Decoder>),
+            _ => None,
+        });
+        assert!(matches!(decode().unwrap(), ColumnBatch::Int32(v) if v == [3, 4]));
+        assert!(value_decoder(&encoding, &TSDataType::Int64)
+            .decode_batch(&mut Cursor::new(&[][..]))
+            .is_err());
+    }
+}
@@ -0,0 +1,195 @@
+use std::io::{self, Cursor, Write};
+use std::sync::Arc;
+
+use snafu::{ResultExt, Snafu};
+
+use crate::chunk::reader::{ChunkHeader, DefaultChunkReader};
+use crate::file::metadata::TSDataType;
+use crate::file::metadata_writer;
+use crate::file::metadata_writer::MetadataWriter;
+use crate::file::options::DEFAULT_MAX_ALLOCATION;
+use crate::file::reader::SectionReader;
+use crate::file::scanner;
+use crate::file::scanner::{ScanItem, TsFileScanner};
+use crate::file::statistics::Statistic;
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Unable to scan file: {}", source))]
+    ScanFile { source: scanner::Error },
+    #[snafu(display("Unable to copy the data section: {}", source))]
+    CopyData { source: io::Error },
+    #[snafu(display("Unable to write metadata: {}", source))]
+    WriteMetadata { source: metadata_writer::Error },
+}
+
+type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// What `repair` kept of a file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RepairSummary {
+    data_len: u64,
+    dropped_len: u64,
+    chunk_groups: usize,
+    chunks: usize,
+}
+
+impl RepairSummary {
+    /// Bytes of the data section kept, head included.
+    pub fn data_len(&self) -> u64 {
+        self.data_len
+    }
+
+    /// Bytes after the kept data section, dropped along with any old metadata.
+    pub fn dropped_len(&self) -> u64 {
+        self.dropped_len
+    }
+
+    pub fn chunk_groups(&self) -> usize {
+        self.chunk_groups
+    }
+
+    pub fn chunks(&self) -> usize {
+        self.chunks
+    }
+}
+
+/// A chunk group read in full by the scanner.
+struct ChunkGroup {
+    offset: u64,
+    device: String,
+    chunks: Vec<(u64, ChunkHeader, u64)>,
+}
+
+/// Writes a sealed copy of a file whose metadata is missing or damaged, e.g. after a
+/// crash while writing. The data section is scanned up to the last chunk group that is
+/// complete and whose pages all decode; that prefix is copied to `out` and the metadata
+/// index and footer are rebuilt from statistics of the decoded points.
+///
+/// A chunk group counts as complete once the next chunk group, plan index or the
+/// metadata separator starts, as in the self check of IoTDB writers, so the last group of
+/// a file that ends in the data section is dropped.
+pub fn repair<R: SectionReader, W: Write>(reader: R, mut out: W) -> Result<(W, RepairSummary)> {
+    let len = reader.len();
+    let mut scanner = TsFileScanner::new(reader).context(ScanFile)?;
+    let mut end = scanner.position();
+    let mut groups = Vec::new();
+    let mut current: Option<ChunkGroup> = None;
+    loop {
+        match scanner.next() {
+            Some(Ok(ScanItem::ChunkGroup { offset, device })) => {
+                groups.extend(current.take());
+                end = offset;
+                current = Some(ChunkGroup {
+                    offset,
+                    device,
+                    chunks: Vec::new(),
+                });
+            }
+            Some(Ok(ScanItem::Chunk {
+                offset,
+                header,
+                data_offset,
+            })) => match current.as_mut() {
+                Some(group) => group.chunks.push((offset, header, data_offset)),
+                // a chunk outside of any chunk group
+                None => break,
+            },
+            Some(Ok(ScanItem::PlanIndex { .. })) | Some(Ok(ScanItem::Version { .. })) => {
+                groups.extend(current.take());
+                end = scanner.position();
+            }
+            Some(Ok(ScanItem::Separator { offset })) => {
+                groups.extend(current.take());
+                end = offset;
+                break;
+            }
+            Some(Err(_)) | None => break,
+        }
+    }
+
+    let mut writer = MetadataWriter::new();
+    let mut summary = RepairSummary {
+        data_len: end,
+        dropped_len: 0,
+        chunk_groups: 0,
+        chunks: 0,
+    };
+    for group in groups {
+        let (offset, device) = (group.offset, group.device.clone());
+        let chunks = match decode_group(&scanner, group) {
+            Some(chunks) => chunks,
+            None => {
+                summary.data_len = offset;
+                break;
+            }
+        };
+        summary.chunk_groups += 1;
+        summary.chunks += chunks.len();
+        for chunk in chunks.into_iter().filter(|c| c.statistic.count() > 0) {
+            writer
+                .add_chunk(
+                    &device,
+                    &chunk.measurement,
+                    &chunk.data_type,
+                    chunk.offset as i64,
+                    chunk.statistic,
+                )
+                .context(WriteMetadata)?;
+        }
+    }
+    summary.dropped_len = len - summary.data_len;
+
+    io::copy(
+        &mut scanner.get_ref().get_read(0, summary.data_len as usize),
+        &mut out,
+    )
+    .context(CopyData)?;
+    let out = writer
+        .finish(summary.data_len, out)
+        .context(WriteMetadata)?;
+    Ok((out, summary))
+}
+
+/// A chunk whose pages decoded, with the statistics of its points.
+struct DecodedChunk {
+    offset: u64,
+    measurement: String,
+    data_type: TSDataType,
+    statistic: Statistic,
+}
+
+/// Decodes the chunks of a group, `None` if a chunk cannot be read or decoded.
+fn decode_group<R: SectionReader>(
+    scanner: &TsFileScanner<R>,
+    group: ChunkGroup,
+) -> Option<Vec<DecodedChunk>> {
+    let mut chunks = Vec::with_capacity(group.chunks.len());
+    for (offset, header, data_offset) in group.chunks {
+        let data = scanner.read_chunk_data(data_offset, &header).ok()?;
+        let measurement = header.measurement_id().to_string();
+        let data_type = header.data_type().clone();
+        let mut statistic = Statistic::empty(&data_type);
+        let pages = DefaultChunkReader::new(
+            Cursor::new(data),
+            header,
+            Arc::new(statistic.clone()),
+            None,
+            DEFAULT_MAX_ALLOCATION,
+        )
+        .ok()?;
+        for page in pages {
+            let (times, values) = page.ok()?.batch().ok()?;
+            if !statistic.update_batch(&times, &values) {
+                return None;
+            }
+        }
+        chunks.push(DecodedChunk {
+            offset,
+            measurement,
+            data_type,
+            statistic,
+        });
+    }
+    Some(chunks)
+}
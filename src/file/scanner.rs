@@ -0,0 +1,219 @@
+use std::borrow::BorrowMut;
+use std::cmp;
+
+use byteorder::{BigEndian, ReadBytesExt};
+use snafu::{ensure, ResultExt, Snafu};
+
+use crate::chunk;
+use crate::chunk::reader::{
+    ChunkHeader, ALIGNED_COLUMN_MASK, CHUNK_HEADER, ONLY_ONE_PAGE_CHUNK_HEADER,
+};
+use crate::file::reader;
+use crate::file::reader::SectionReader;
+use crate::utils::cursor;
+use crate::utils::cursor::VarIntReader;
+use crate::{MAGIC_STRING, VERSION_NUMBER, VERSION_NUMBER_V4};
+
+const CHUNK_GROUP_HEADER: u8 = 0;
+const SEPARATOR: u8 = 2;
+const VERSION: u8 = 3;
+const OPERATION_INDEX_RANGE: u8 = 4;
+
+/// Largest marker record (chunk group or chunk header) the scanner reads in one go.
+const HEADER_WINDOW: u64 = 1024;
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Invalid head magic or version at offset 0"))]
+    InvalidHead,
+    #[snafu(display("Unable to read data at offset {}: {}", offset, source))]
+    ReadSection { offset: u64, source: reader::Error },
+    #[snafu(display("Unable to read data at offset {}: {}", offset, source))]
+    ReadCursorData { offset: u64, source: std::io::Error },
+    #[snafu(display("Unable to read device id at offset {}: {}", offset, source))]
+    ReadDeviceId { offset: u64, source: cursor::Error },
+    #[snafu(display("Unable to parser chunk header at offset {}: {}", offset, source))]
+    ParserChunkHeader {
+        offset: u64,
+        source: chunk::reader::Error,
+    },
+    #[snafu(display("Unknown marker {} at offset {}", marker, offset))]
+    UnknownMarker { offset: u64, marker: u8 },
+    #[snafu(display("Chunk at offset {} ends after the end of file {}", offset, len))]
+    TruncatedChunk { offset: u64, len: u64 },
+}
+
+type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// A structure found while scanning the data section of a file.
+#[derive(Debug)]
+pub enum ScanItem {
+    ChunkGroup {
+        offset: u64,
+        device: String,
+    },
+    /// A chunk header, its pages start at `data_offset`.
+    Chunk {
+        offset: u64,
+        header: ChunkHeader,
+        data_offset: u64,
+    },
+    PlanIndex {
+        offset: u64,
+        min: i64,
+        max: i64,
+    },
+    Version {
+        offset: u64,
+        version: i64,
+    },
+    /// The end of the data section, the metadata index follows.
+    Separator {
+        offset: u64,
+    },
+}
+
+/// Walks a file's chunk groups and chunks in file order without using the footer index,
+/// so files whose index is missing or corrupt can still be inspected.
+///
+/// Iteration stops at the separator, the end of the file or the first error.
+pub struct TsFileScanner<R: SectionReader> {
+    reader: R,
+    pos: u64,
+    len: u64,
+    version: u8,
+    done: bool,
+}
+
+impl<R: SectionReader> TsFileScanner<R> {
+    pub fn new(reader: R) -> Result<Self> {
+        let len = reader.len();
+        let head_len = MAGIC_STRING.len() + 1;
+        ensure!(len >= head_len as u64, InvalidHead);
+        let head = reader
+            .get_cursor(0, head_len)
+            .context(ReadSection { offset: 0_u64 })?
+            .into_inner();
+        let version = head[MAGIC_STRING.len()];
+        ensure!(
+            &head[..MAGIC_STRING.len()] == MAGIC_STRING.as_bytes()
+                && (version == VERSION_NUMBER || version == VERSION_NUMBER_V4),
+            InvalidHead
+        );
+
+        Ok(Self {
+            reader,
+            pos: head_len as u64,
+            len,
+            version,
+            done: false,
+        })
+    }
+
+    /// Offset of the next structure to be read.
+    pub fn position(&self) -> u64 {
+        self.pos
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Reads the undecoded pages of a chunk returned by the scanner.
+    pub fn read_chunk_data(&self, data_offset: u64, header: &ChunkHeader) -> Result<Vec<u8>> {
+        let mut data = Vec::new();
+        self.reader
+            .read_into(data_offset, header.data_size() as usize, &mut data)
+            .context(ReadSection {
+                offset: data_offset,
+            })?;
+        Ok(data)
+    }
+
+    fn read_item(&mut self) -> Result<ScanItem> {
+        let offset = self.pos;
+        let window = cmp::min(HEADER_WINDOW, self.len - offset) as usize;
+        let mut cursor = self
+            .reader
+            .get_cursor(offset, window)
+            .context(ReadSection { offset })?;
+
+        let marker = cursor.get_ref()[0];
+        let item = match marker {
+            CHUNK_GROUP_HEADER => {
+                cursor.set_position(1);
+                let device = match self.version {
+                    VERSION_NUMBER_V4 => cursor.read_device_id(),
+                    _ => cursor.read_varint_string(),
+                }
+                .context(ReadDeviceId { offset })?;
+                self.pos += cursor.position();
+                ScanItem::ChunkGroup { offset, device }
+            }
+            marker
+                if matches!(
+                    marker & !ALIGNED_COLUMN_MASK,
+                    CHUNK_HEADER | ONLY_ONE_PAGE_CHUNK_HEADER
+                ) =>
+            {
+                let header = ChunkHeader::try_from(cursor.borrow_mut())
+                    .context(ParserChunkHeader { offset })?;
+                let data_offset = offset + cursor.position();
+                let end = data_offset + header.data_size() as u64;
+                ensure!(
+                    end <= self.len,
+                    TruncatedChunk {
+                        offset,
+                        len: self.len
+                    }
+                );
+                self.pos = end;
+                ScanItem::Chunk {
+                    offset,
+                    header,
+                    data_offset,
+                }
+            }
+            VERSION => {
+                cursor.set_position(1);
+                let version = cursor
+                    .read_i64::<BigEndian>()
+                    .context(ReadCursorData { offset })?;
+                self.pos += cursor.position();
+                ScanItem::Version { offset, version }
+            }
+            OPERATION_INDEX_RANGE => {
+                cursor.set_position(1);
+                let min = cursor
+                    .read_i64::<BigEndian>()
+                    .context(ReadCursorData { offset })?;
+                let max = cursor
+                    .read_i64::<BigEndian>()
+                    .context(ReadCursorData { offset })?;
+                self.pos += cursor.position();
+                ScanItem::PlanIndex { offset, min, max }
+            }
+            SEPARATOR => {
+                self.done = true;
+                ScanItem::Separator { offset }
+            }
+            marker => return UnknownMarker { offset, marker }.fail(),
+        };
+        Ok(item)
+    }
+}
+
+impl<R: SectionReader> Iterator for TsFileScanner<R> {
+    type Item = Result<ScanItem>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done || self.pos >= self.len {
+            return None;
+        }
+        let item = self.read_item();
+        if item.is_err() {
+            self.done = true;
+        }
+        Some(item)
+    }
+}
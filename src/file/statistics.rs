@@ -1,12 +1,13 @@
 use std::borrow::BorrowMut;
 use std::convert::TryFrom;
-use std::io::Cursor;
+use std::io::{Cursor, Read, Write};
 
+use crate::encoding::decoder::{ColumnBatch, Field};
+use crate::file::metadata::TSDataType;
 use crate::utils::cursor;
-use crate::utils::cursor::VarIntReader;
-use byteorder::{BigEndian, ReadBytesExt};
-use snafu::{ResultExt, Snafu};
-use varint::VarintRead;
+use crate::utils::cursor::{VarIntReader, VarIntWriter};
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use snafu::{ensure, ResultExt, Snafu};
 
 #[derive(Debug, Snafu)]
 pub enum Error {
@@ -16,9 +17,37 @@ pub enum Error {
     ReadCursorData { source: std::io::Error },
     #[snafu(display("Unable to read cursor data: {}", source))]
     ReadVarData { source: cursor::Error },
+    #[snafu(display("Unable to write statistics: {}", source))]
+    WriteData { source: std::io::Error },
+    #[snafu(display("Unable to merge {} statistics into {} statistics", other, target))]
+    MergeMismatch { target: String, other: String },
+    #[snafu(display(
+        "Binary value of {} bytes exceeds the {} remaining bytes",
+        len,
+        remaining
+    ))]
+    LengthExceedsData { len: i32, remaining: u64 },
 }
 
-#[derive(Debug)]
+/// Statistics that can be built point by point, combined across pages and chunks, and
+/// written back in the TsFile layout.
+pub trait Statistics: Sized {
+    type Value;
+
+    /// Statistics of no points.
+    fn empty() -> Self;
+
+    fn update(&mut self, time: i64, value: Self::Value);
+
+    fn merge(&mut self, other: &Self);
+
+    fn serialize<W: Write>(&self, out: &mut W) -> Result<(), Error>;
+
+    fn deserialize(cursor: &mut Cursor<Vec<u8>>) -> Result<Self, Error>;
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Statistic {
     Boolean(BooleanStatistics),
     Int32(IntegerStatistics),
@@ -26,9 +55,186 @@ pub enum Statistic {
     FLOAT(FloatStatistics),
     DOUBLE(DoubleStatistics),
     TEXT(BinaryStatistics),
+    /// The statistics of the time column of an aligned device.
+    Time(TimeStatistics),
 }
 
-#[derive(Debug)]
+impl Statistic {
+    fn type_name(&self) -> &'static str {
+        match self {
+            Statistic::Boolean(_) => "boolean",
+            Statistic::Int32(_) => "Int32",
+            Statistic::Int64(_) => "Int64",
+            Statistic::FLOAT(_) => "FLOAT",
+            Statistic::DOUBLE(_) => "DOUBLE",
+            Statistic::TEXT(_) => "TEXT",
+            Statistic::Time(_) => "time",
+        }
+    }
+
+    /// Statistics of no points of `data_type`.
+    pub fn empty(data_type: &TSDataType) -> Self {
+        match data_type {
+            TSDataType::Boolean => Statistic::Boolean(BooleanStatistics::empty()),
+            TSDataType::Int32 => Statistic::Int32(IntegerStatistics::empty()),
+            TSDataType::Int64 => Statistic::Int64(LongStatistics::empty()),
+            TSDataType::FLOAT => Statistic::FLOAT(FloatStatistics::empty()),
+            TSDataType::DOUBLE => Statistic::DOUBLE(DoubleStatistics::empty()),
+            TSDataType::TEXT => Statistic::TEXT(BinaryStatistics::empty()),
+            TSDataType::VECTOR => Statistic::Time(TimeStatistics::empty()),
+        }
+    }
+
+    /// Adds the points of a decoded page; false if its values are of another data type.
+    pub fn update_batch(&mut self, times: &[i64], values: &ColumnBatch) -> bool {
+        match (self, values) {
+            (Statistic::Boolean(s), ColumnBatch::Boolean(v)) => {
+                times.iter().zip(v).for_each(|(t, v)| s.update(*t, *v))
+            }
+            (Statistic::Int32(s), ColumnBatch::Int32(v)) => {
+                times.iter().zip(v).for_each(|(t, v)| s.update(*t, *v))
+            }
+            (Statistic::Int64(s), ColumnBatch::Int64(v)) => {
+                times.iter().zip(v).for_each(|(t, v)| s.update(*t, *v))
+            }
+            (Statistic::FLOAT(s), ColumnBatch::FLOAT(v)) => {
+                times.iter().zip(v).for_each(|(t, v)| s.update(*t, *v))
+            }
+            (Statistic::DOUBLE(s), ColumnBatch::DOUBLE(v)) => {
+                times.iter().zip(v).for_each(|(t, v)| s.update(*t, *v))
+            }
+            (Statistic::TEXT(s), ColumnBatch::TEXT(v)) => times
+                .iter()
+                .zip(v)
+                .for_each(|(t, v)| s.update(*t, String::from_utf8_lossy(v).into_owned())),
+            (Statistic::Time(s), _) => times.iter().for_each(|t| s.update(*t, ())),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Merges statistics of the same data type.
+    pub fn merge(&mut self, other: &Statistic) -> Result<(), Error> {
+        match (self, other) {
+            (Statistic::Boolean(s), Statistic::Boolean(o)) => s.merge(o),
+            (Statistic::Int32(s), Statistic::Int32(o)) => s.merge(o),
+            (Statistic::Int64(s), Statistic::Int64(o)) => s.merge(o),
+            (Statistic::FLOAT(s), Statistic::FLOAT(o)) => s.merge(o),
+            (Statistic::DOUBLE(s), Statistic::DOUBLE(o)) => s.merge(o),
+            (Statistic::TEXT(s), Statistic::TEXT(o)) => s.merge(o),
+            (Statistic::Time(s), Statistic::Time(o)) => s.merge(o),
+            (target, other) => {
+                return MergeMismatch {
+                    target: target.type_name(),
+                    other: other.type_name(),
+                }
+                .fail()
+            }
+        }
+        Ok(())
+    }
+
+    pub fn serialize<W: Write>(&self, out: &mut W) -> Result<(), Error> {
+        match self {
+            Statistic::Boolean(s) => s.serialize(out),
+            Statistic::Int32(s) => s.serialize(out),
+            Statistic::Int64(s) => s.serialize(out),
+            Statistic::FLOAT(s) => s.serialize(out),
+            Statistic::DOUBLE(s) => s.serialize(out),
+            Statistic::TEXT(s) => s.serialize(out),
+            Statistic::Time(s) => s.serialize(out),
+        }
+    }
+
+    pub fn header(&self) -> &StatisticHeader {
+        match self {
+            Statistic::Boolean(s) => &s.header,
+            Statistic::Int32(s) => &s.header,
+            Statistic::Int64(s) => &s.header,
+            Statistic::FLOAT(s) => &s.header,
+            Statistic::DOUBLE(s) => &s.header,
+            Statistic::TEXT(s) => &s.header,
+            Statistic::Time(s) => &s.header,
+        }
+    }
+
+    pub fn start_time(&self) -> i64 {
+        self.header().start_time
+    }
+
+    pub fn end_time(&self) -> i64 {
+        self.header().end_time
+    }
+
+    /// `(start_time, end_time)`, both inclusive.
+    pub fn time_range(&self) -> (i64, i64) {
+        (self.start_time(), self.end_time())
+    }
+
+    pub fn count(&self) -> i32 {
+        self.header().count
+    }
+
+    pub fn min_as_f64(&self) -> Option<f64> {
+        self.value_range().and_then(|(min, _)| min.as_f64())
+    }
+
+    pub fn max_as_f64(&self) -> Option<f64> {
+        self.value_range().and_then(|(_, max)| max.as_f64())
+    }
+
+    /// The sum of the values; booleans count as 1 for true.
+    pub fn sum_as_f64(&self) -> Option<f64> {
+        match self {
+            Statistic::Boolean(s) => Some(s.sum_value as f64),
+            Statistic::Int32(s) => Some(s.sum_value as f64),
+            Statistic::Int64(s) => Some(s.sum_value),
+            Statistic::FLOAT(s) => Some(s.sum_value),
+            Statistic::DOUBLE(s) => Some(s.sum_value),
+            Statistic::TEXT(_) | Statistic::Time(_) => None,
+        }
+    }
+
+    /// `(min, max)` of the values, for the types that record them.
+    pub fn value_range(&self) -> Option<(Field, Field)> {
+        match self {
+            Statistic::Int32(s) => Some((Field::Int32(s.min_value), Field::Int32(s.max_value))),
+            Statistic::Int64(s) => Some((Field::Int64(s.min_value), Field::Int64(s.max_value))),
+            Statistic::FLOAT(s) => Some((Field::FLOAT(s.min_value), Field::FLOAT(s.max_value))),
+            Statistic::DOUBLE(s) => Some((Field::DOUBLE(s.min_value), Field::DOUBLE(s.max_value))),
+            Statistic::Boolean(_) | Statistic::TEXT(_) | Statistic::Time(_) => None,
+        }
+    }
+
+    /// The value of the first point; the first timestamp for a time column.
+    pub fn first_value(&self) -> Field {
+        match self {
+            Statistic::Boolean(s) => Field::Boolean(s.first_value),
+            Statistic::Int32(s) => Field::Int32(s.first_value),
+            Statistic::Int64(s) => Field::Int64(s.first_value),
+            Statistic::FLOAT(s) => Field::FLOAT(s.first_value),
+            Statistic::DOUBLE(s) => Field::DOUBLE(s.first_value),
+            Statistic::TEXT(s) => Field::TEXT(s.first_value.clone().into_bytes()),
+            Statistic::Time(s) => Field::Int64(s.header.start_time),
+        }
+    }
+
+    /// The value of the last point; the last timestamp for a time column.
+    pub fn last_value(&self) -> Field {
+        match self {
+            Statistic::Boolean(s) => Field::Boolean(s.last_value),
+            Statistic::Int32(s) => Field::Int32(s.last_value),
+            Statistic::Int64(s) => Field::Int64(s.last_value),
+            Statistic::FLOAT(s) => Field::FLOAT(s.last_value),
+            Statistic::DOUBLE(s) => Field::DOUBLE(s.last_value),
+            Statistic::TEXT(s) => Field::TEXT(s.last_value.clone().into_bytes()),
+            Statistic::Time(s) => Field::Int64(s.header.end_time),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct StatisticHeader {
     is_empty: bool,
     count: i32,
@@ -36,14 +242,23 @@ pub struct StatisticHeader {
     end_time: i64,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct BinaryStatistics {
     header: StatisticHeader,
     first_value: String,
     last_value: String,
 }
 
-#[derive(Debug)]
+/// Statistics of timestamps only, which is all the time column of an aligned device records.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TimeStatistics {
+    header: StatisticHeader,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct BooleanStatistics {
     header: StatisticHeader,
     first_value: bool,
@@ -51,7 +266,8 @@ pub struct BooleanStatistics {
     sum_value: i64,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct IntegerStatistics {
     header: StatisticHeader,
     min_value: i32,
@@ -61,7 +277,8 @@ pub struct IntegerStatistics {
     sum_value: i64,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct LongStatistics {
     header: StatisticHeader,
     min_value: i64,
@@ -71,7 +288,8 @@ pub struct LongStatistics {
     sum_value: f64,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct DoubleStatistics {
     header: StatisticHeader,
     min_value: f64,
@@ -81,7 +299,8 @@ pub struct DoubleStatistics {
     sum_value: f64,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct FloatStatistics {
     header: StatisticHeader,
     min_value: f32,
@@ -91,6 +310,216 @@ pub struct FloatStatistics {
     sum_value: f64,
 }
 
+impl StatisticHeader {
+    fn empty() -> Self {
+        Self {
+            is_empty: true,
+            count: 0,
+            start_time: i64::MAX,
+            end_time: i64::MIN,
+        }
+    }
+
+    /// Records a point at `time`, returning whether it is now the first and the last point.
+    fn update(&mut self, time: i64) -> (bool, bool) {
+        let first = self.is_empty || time < self.start_time;
+        let last = self.is_empty || time >= self.end_time;
+        if first {
+            self.start_time = time;
+        }
+        if last {
+            self.end_time = time;
+        }
+        self.count += 1;
+        self.is_empty = false;
+        (first, last)
+    }
+
+    /// Merges `other`, returning whether its first and last values replace ours.
+    fn merge(&mut self, other: &StatisticHeader) -> (bool, bool) {
+        if other.is_empty {
+            return (false, false);
+        }
+        let first = self.is_empty || other.start_time < self.start_time;
+        let last = self.is_empty || other.end_time >= self.end_time;
+        if first {
+            self.start_time = other.start_time;
+        }
+        if last {
+            self.end_time = other.end_time;
+        }
+        self.count += other.count;
+        self.is_empty = false;
+        (first, last)
+    }
+
+    fn serialize<W: Write>(&self, out: &mut W) -> Result<(), Error> {
+        out.write_unsigned_varint_32(self.count as u32)
+            .context(WriteData)?;
+        out.write_i64::<BigEndian>(self.start_time)
+            .context(WriteData)?;
+        out.write_i64::<BigEndian>(self.end_time).context(WriteData)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.is_empty
+    }
+
+    pub fn count(&self) -> i32 {
+        self.count
+    }
+
+    pub fn start_time(&self) -> i64 {
+        self.start_time
+    }
+
+    pub fn end_time(&self) -> i64 {
+        self.end_time
+    }
+}
+
+impl BinaryStatistics {
+    pub fn header(&self) -> &StatisticHeader {
+        &self.header
+    }
+
+    pub fn first_value(&self) -> &str {
+        &self.first_value
+    }
+
+    pub fn last_value(&self) -> &str {
+        &self.last_value
+    }
+}
+
+impl TimeStatistics {
+    pub fn header(&self) -> &StatisticHeader {
+        &self.header
+    }
+}
+
+impl BooleanStatistics {
+    pub fn header(&self) -> &StatisticHeader {
+        &self.header
+    }
+
+    pub fn first_value(&self) -> bool {
+        self.first_value
+    }
+
+    pub fn last_value(&self) -> bool {
+        self.last_value
+    }
+
+    pub fn sum_value(&self) -> i64 {
+        self.sum_value
+    }
+}
+
+impl IntegerStatistics {
+    pub fn header(&self) -> &StatisticHeader {
+        &self.header
+    }
+
+    pub fn min_value(&self) -> i32 {
+        self.min_value
+    }
+
+    pub fn max_value(&self) -> i32 {
+        self.max_value
+    }
+
+    pub fn first_value(&self) -> i32 {
+        self.first_value
+    }
+
+    pub fn last_value(&self) -> i32 {
+        self.last_value
+    }
+
+    pub fn sum_value(&self) -> i64 {
+        self.sum_value
+    }
+}
+
+impl LongStatistics {
+    pub fn header(&self) -> &StatisticHeader {
+        &self.header
+    }
+
+    pub fn min_value(&self) -> i64 {
+        self.min_value
+    }
+
+    pub fn max_value(&self) -> i64 {
+        self.max_value
+    }
+
+    pub fn first_value(&self) -> i64 {
+        self.first_value
+    }
+
+    pub fn last_value(&self) -> i64 {
+        self.last_value
+    }
+
+    pub fn sum_value(&self) -> f64 {
+        self.sum_value
+    }
+}
+
+impl DoubleStatistics {
+    pub fn header(&self) -> &StatisticHeader {
+        &self.header
+    }
+
+    pub fn min_value(&self) -> f64 {
+        self.min_value
+    }
+
+    pub fn max_value(&self) -> f64 {
+        self.max_value
+    }
+
+    pub fn first_value(&self) -> f64 {
+        self.first_value
+    }
+
+    pub fn last_value(&self) -> f64 {
+        self.last_value
+    }
+
+    pub fn sum_value(&self) -> f64 {
+        self.sum_value
+    }
+}
+
+impl FloatStatistics {
+    pub fn header(&self) -> &StatisticHeader {
+        &self.header
+    }
+
+    pub fn min_value(&self) -> f32 {
+        self.min_value
+    }
+
+    pub fn max_value(&self) -> f32 {
+        self.max_value
+    }
+
+    pub fn first_value(&self) -> f32 {
+        self.first_value
+    }
+
+    pub fn last_value(&self) -> f32 {
+        self.last_value
+    }
+
+    pub fn sum_value(&self) -> f64 {
+        self.sum_value
+    }
+}
+
 impl TryFrom<&mut Cursor<Vec<u8>>> for StatisticHeader {
     type Error = Error;
 
@@ -185,7 +614,271 @@ impl TryFrom<&'_ mut Cursor<Vec<u8>>> for LongStatistics {
 impl TryFrom<&'_ mut Cursor<Vec<u8>>> for BinaryStatistics {
     type Error = Error;
 
-    fn try_from(_value: &'_ mut Cursor<Vec<u8>>) -> Result<Self, Self::Error> {
-        todo!()
+    fn try_from(cursor: &'_ mut Cursor<Vec<u8>>) -> Result<Self, Self::Error> {
+        Ok(Self {
+            header: StatisticHeader::try_from(cursor.borrow_mut())?,
+            first_value: read_binary(cursor)?,
+            last_value: read_binary(cursor)?,
+        })
+    }
+}
+
+impl TryFrom<&'_ mut Cursor<Vec<u8>>> for TimeStatistics {
+    type Error = Error;
+
+    fn try_from(cursor: &'_ mut Cursor<Vec<u8>>) -> Result<Self, Self::Error> {
+        Ok(Self {
+            header: StatisticHeader::try_from(cursor)?,
+        })
+    }
+}
+
+/// A binary value stored as an i32 length followed by its bytes.
+fn read_binary(cursor: &mut Cursor<Vec<u8>>) -> Result<String, Error> {
+    let len = cursor.read_i32::<BigEndian>().context(ReadCursorData)?;
+    let remaining = cursor.remaining();
+    ensure!(
+        len >= 0 && len as u64 <= remaining,
+        LengthExceedsData { len, remaining }
+    );
+    let mut data = vec![0; len as usize];
+    cursor.read_exact(&mut data).context(ReadCursorData)?;
+    Ok(String::from_utf8_lossy(&data).into_owned())
+}
+
+impl Statistics for BooleanStatistics {
+    type Value = bool;
+
+    fn empty() -> Self {
+        Self {
+            header: StatisticHeader::empty(),
+            first_value: false,
+            last_value: false,
+            sum_value: 0,
+        }
+    }
+
+    fn update(&mut self, time: i64, value: bool) {
+        let (first, last) = self.header.update(time);
+        if first {
+            self.first_value = value;
+        }
+        if last {
+            self.last_value = value;
+        }
+        self.sum_value += value as i64;
+    }
+
+    fn merge(&mut self, other: &Self) {
+        let (first, last) = self.header.merge(&other.header);
+        if first {
+            self.first_value = other.first_value;
+        }
+        if last {
+            self.last_value = other.last_value;
+        }
+        self.sum_value += other.sum_value;
+    }
+
+    fn serialize<W: Write>(&self, out: &mut W) -> Result<(), Error> {
+        self.header.serialize(out)?;
+        out.write_u8(self.first_value as u8).context(WriteData)?;
+        out.write_u8(self.last_value as u8).context(WriteData)?;
+        out.write_i64::<BigEndian>(self.sum_value)
+            .context(WriteData)
+    }
+
+    fn deserialize(cursor: &mut Cursor<Vec<u8>>) -> Result<Self, Error> {
+        Self::try_from(cursor)
+    }
+}
+
+impl Statistics for TimeStatistics {
+    type Value = ();
+
+    fn empty() -> Self {
+        Self {
+            header: StatisticHeader::empty(),
+        }
+    }
+
+    fn update(&mut self, time: i64, _: ()) {
+        self.header.update(time);
+    }
+
+    fn merge(&mut self, other: &Self) {
+        self.header.merge(&other.header);
+    }
+
+    fn serialize<W: Write>(&self, out: &mut W) -> Result<(), Error> {
+        self.header.serialize(out)
+    }
+
+    fn deserialize(cursor: &mut Cursor<Vec<u8>>) -> Result<Self, Error> {
+        Self::try_from(cursor)
+    }
+}
+
+impl Statistics for BinaryStatistics {
+    type Value = String;
+
+    fn empty() -> Self {
+        Self {
+            header: StatisticHeader::empty(),
+            first_value: String::new(),
+            last_value: String::new(),
+        }
+    }
+
+    fn update(&mut self, time: i64, value: String) {
+        let (first, last) = self.header.update(time);
+        if first {
+            self.first_value = value.clone();
+        }
+        if last {
+            self.last_value = value;
+        }
+    }
+
+    fn merge(&mut self, other: &Self) {
+        let (first, last) = self.header.merge(&other.header);
+        if first {
+            self.first_value = other.first_value.clone();
+        }
+        if last {
+            self.last_value = other.last_value.clone();
+        }
+    }
+
+    fn serialize<W: Write>(&self, out: &mut W) -> Result<(), Error> {
+        self.header.serialize(out)?;
+        for value in [&self.first_value, &self.last_value] {
+            out.write_i32::<BigEndian>(value.len() as i32)
+                .context(WriteData)?;
+            out.write_all(value.as_bytes()).context(WriteData)?;
+        }
+        Ok(())
+    }
+
+    fn deserialize(cursor: &mut Cursor<Vec<u8>>) -> Result<Self, Error> {
+        Self::try_from(cursor)
+    }
+}
+
+/// Min/max/first/last/sum statistics of the numeric types, which differ only in the
+/// value and sum types.
+macro_rules! numeric_statistics {
+    ($name:ident, $value:ty, $sum:ty, $write_value:ident, $write_sum:ident) => {
+        impl Statistics for $name {
+            type Value = $value;
+
+            fn empty() -> Self {
+                Self {
+                    header: StatisticHeader::empty(),
+                    min_value: <$value>::default(),
+                    max_value: <$value>::default(),
+                    first_value: <$value>::default(),
+                    last_value: <$value>::default(),
+                    sum_value: <$sum>::default(),
+                }
+            }
+
+            fn update(&mut self, time: i64, value: $value) {
+                if self.header.is_empty || value < self.min_value {
+                    self.min_value = value;
+                }
+                if self.header.is_empty || value > self.max_value {
+                    self.max_value = value;
+                }
+                let (first, last) = self.header.update(time);
+                if first {
+                    self.first_value = value;
+                }
+                if last {
+                    self.last_value = value;
+                }
+                self.sum_value += value as $sum;
+            }
+
+            fn merge(&mut self, other: &Self) {
+                if other.header.is_empty {
+                    return;
+                }
+                if self.header.is_empty || other.min_value < self.min_value {
+                    self.min_value = other.min_value;
+                }
+                if self.header.is_empty || other.max_value > self.max_value {
+                    self.max_value = other.max_value;
+                }
+                let (first, last) = self.header.merge(&other.header);
+                if first {
+                    self.first_value = other.first_value;
+                }
+                if last {
+                    self.last_value = other.last_value;
+                }
+                self.sum_value += other.sum_value;
+            }
+
+            fn serialize<W: Write>(&self, out: &mut W) -> Result<(), Error> {
+                self.header.serialize(out)?;
+                for value in [
+                    self.min_value,
+                    self.max_value,
+                    self.first_value,
+                    self.last_value,
+                ] {
+                    out.$write_value::<BigEndian>(value).context(WriteData)?;
+                }
+                out.$write_sum::<BigEndian>(self.sum_value)
+                    .context(WriteData)
+            }
+
+            fn deserialize(cursor: &mut Cursor<Vec<u8>>) -> Result<Self, Error> {
+                Self::try_from(cursor)
+            }
+        }
+    };
+}
+
+numeric_statistics!(IntegerStatistics, i32, i64, write_i32, write_i64);
+numeric_statistics!(LongStatistics, i64, f64, write_i64, write_f64);
+numeric_statistics!(FloatStatistics, f32, f64, write_f32, write_f64);
+numeric_statistics!(DoubleStatistics, f64, f64, write_f64, write_f64);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_and_roundtrip() {
+        let mut left = IntegerStatistics::empty();
+        left.update(10, 5);
+        left.update(20, -3);
+        let mut right = IntegerStatistics::empty();
+        right.update(5, 7);
+        left.merge(&right);
+
+        let mut data = Vec::new();
+        left.serialize(&mut data).unwrap();
+        let read = IntegerStatistics::deserialize(&mut Cursor::new(data)).unwrap();
+        assert_eq!(read.header().count(), 3);
+        assert_eq!(
+            (read.header().start_time(), read.header().end_time()),
+            (5, 20)
+        );
+        assert_eq!((read.min_value(), read.max_value()), (-3, 7));
+        assert_eq!((read.first_value(), read.last_value()), (7, -3));
+        assert_eq!(read.sum_value(), 9);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serialize_to_json() {
+        let mut stats = IntegerStatistics::empty();
+        stats.update(10, 5);
+        let json = serde_json::to_value(Statistic::Int32(stats)).unwrap();
+        assert_eq!(json["Int32"]["header"]["count"], 1);
+        assert_eq!(json["Int32"]["max_value"], 5);
     }
 }
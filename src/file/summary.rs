@@ -0,0 +1,212 @@
+use std::sync::Arc;
+
+use crate::chunk::reader::{CompressionType, TSEncoding};
+use crate::error::{self, TsFileError};
+use crate::file::metadata::TSDataType;
+use crate::file::reader::FileReader;
+use crate::file::statistics::Statistic;
+
+/// The size, encoding and statistics of one timeseries, from its chunk and page headers.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SeriesSummary {
+    device: String,
+    sensor: String,
+    data_type: TSDataType,
+    statistic: Arc<Statistic>,
+    chunks: usize,
+    pages: usize,
+    data_size: u64,
+    uncompressed_size: u64,
+    compressed_size: u64,
+    encodings: Vec<TSEncoding>,
+    compressions: Vec<CompressionType>,
+}
+
+impl SeriesSummary {
+    pub fn device(&self) -> &str {
+        &self.device
+    }
+
+    pub fn sensor(&self) -> &str {
+        &self.sensor
+    }
+
+    pub fn data_type(&self) -> &TSDataType {
+        &self.data_type
+    }
+
+    /// The timeseries statistics.
+    pub fn statistic(&self) -> &Arc<Statistic> {
+        &self.statistic
+    }
+
+    /// Points of the series, deleted ones included.
+    pub fn points(&self) -> u64 {
+        self.statistic.count().max(0) as u64
+    }
+
+    /// `(start_time, end_time)` of the series, both inclusive.
+    pub fn time_range(&self) -> (i64, i64) {
+        self.statistic.time_range()
+    }
+
+    pub fn chunks(&self) -> usize {
+        self.chunks
+    }
+
+    pub fn pages(&self) -> usize {
+        self.pages
+    }
+
+    /// Bytes of the chunks on disk after their headers, page headers included.
+    pub fn data_size(&self) -> u64 {
+        self.data_size
+    }
+
+    /// Bytes of the pages once decompressed.
+    pub fn uncompressed_size(&self) -> u64 {
+        self.uncompressed_size
+    }
+
+    /// Bytes of the pages as stored.
+    pub fn compressed_size(&self) -> u64 {
+        self.compressed_size
+    }
+
+    /// `uncompressed_size / compressed_size`, `None` for a series without page data.
+    pub fn compression_ratio(&self) -> Option<f64> {
+        ratio(self.uncompressed_size, self.compressed_size)
+    }
+
+    /// The distinct value encodings of the chunks, in chunk order.
+    pub fn encodings(&self) -> &[TSEncoding] {
+        &self.encodings
+    }
+
+    /// The distinct compressions of the chunks, in chunk order.
+    pub fn compressions(&self) -> &[CompressionType] {
+        &self.compressions
+    }
+}
+
+/// The series of a file with their sizes and statistics, see `FileReader::summary`.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct FileSummary {
+    series: Vec<SeriesSummary>,
+}
+
+impl FileSummary {
+    /// Reads the chunk and page headers of every series of `reader`; no page is decoded.
+    /// The time columns of aligned devices are left out.
+    pub fn of<F: FileReader + ?Sized>(reader: &F) -> error::Result<Self> {
+        let mut series = Vec::new();
+        for device in reader.devices()? {
+            for timeseries in reader.sensor_meta_iter(&device) {
+                let timeseries = timeseries?;
+                if timeseries.is_time_column() {
+                    continue;
+                }
+                let sensor = timeseries.measurement_id().to_string();
+                let mut summary = SeriesSummary {
+                    device: device.clone(),
+                    sensor: sensor.clone(),
+                    data_type: timeseries.data_type().clone(),
+                    statistic: timeseries.statistic(),
+                    chunks: 0,
+                    pages: 0,
+                    data_size: 0,
+                    uncompressed_size: 0,
+                    compressed_size: 0,
+                    encodings: Vec::new(),
+                    compressions: Vec::new(),
+                };
+                if let Some(sensor_reader) = reader.get_sensor_reader(&device, &sensor)? {
+                    summary.chunks = sensor_reader.number_of_chunks();
+                    let in_series = |e| TsFileError::from(e).in_series(&device, Some(&sensor));
+                    for i in 0..summary.chunks {
+                        let chunk_reader = sensor_reader.get_chunk_reader(i).map_err(in_series)?;
+                        if let Some(header) = chunk_reader.header() {
+                            summary.data_size += header.data_size() as u64;
+                            if !summary.encodings.contains(header.encoding_type()) {
+                                summary.encodings.push(*header.encoding_type());
+                            }
+                            if !summary.compressions.contains(header.compression_type()) {
+                                summary.compressions.push(header.compression_type().clone());
+                            }
+                        }
+                        for page in chunk_reader {
+                            let page = page.map_err(in_series)?;
+                            summary.pages += 1;
+                            summary.uncompressed_size += page.header().uncompressed_size() as u64;
+                            summary.compressed_size += page.header().compressed_size() as u64;
+                        }
+                    }
+                }
+                series.push(summary);
+            }
+        }
+        Ok(Self { series })
+    }
+
+    /// The series in index order.
+    pub fn series(&self) -> &[SeriesSummary] {
+        &self.series
+    }
+
+    pub fn points(&self) -> u64 {
+        self.series.iter().map(SeriesSummary::points).sum()
+    }
+
+    /// `(start_time, end_time)` of all series, both inclusive. `None` without series.
+    pub fn time_range(&self) -> Option<(i64, i64)> {
+        self.series
+            .iter()
+            .map(SeriesSummary::time_range)
+            .reduce(|(min, max), (start, end)| (min.min(start), max.max(end)))
+    }
+
+    /// Bytes of all chunks on disk after their headers.
+    pub fn data_size(&self) -> u64 {
+        self.series.iter().map(SeriesSummary::data_size).sum()
+    }
+
+    /// The compression ratio of all pages, `None` for a file without page data.
+    pub fn compression_ratio(&self) -> Option<f64> {
+        let uncompressed = self.series.iter().map(|s| s.uncompressed_size).sum();
+        let compressed = self.series.iter().map(|s| s.compressed_size).sum();
+        ratio(uncompressed, compressed)
+    }
+}
+
+fn ratio(uncompressed: u64, compressed: u64) -> Option<f64> {
+    (compressed > 0).then(|| uncompressed as f64 / compressed as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file::tsfile_search_reader::{test_file, TsFileSearchReader};
+
+    #[test]
+    fn sizes_from_chunk_and_page_headers() {
+        let file = test_file(&[("root.sg.d1", "s1", &[1, 5])]);
+        let reader = TsFileSearchReader::new(bytes::Bytes::from(file)).unwrap();
+
+        let summary = reader.summary().unwrap();
+        let series = &summary.series()[0];
+        assert_eq!((series.device(), series.sensor()), ("root.sg.d1", "s1"));
+        assert_eq!(
+            (series.chunks(), series.pages(), series.points()),
+            (1, 1, 2)
+        );
+        // the page header, then the time column length, one TS_2DIFF block and two values
+        assert_eq!(series.data_size(), 29);
+        assert_eq!(series.uncompressed_size(), 27);
+        assert_eq!(series.compression_ratio(), Some(1.0));
+        assert_eq!(series.encodings(), [TSEncoding::Plain]);
+        assert_eq!(series.compressions(), [CompressionType::Uncompressed]);
+        assert_eq!(summary.time_range(), Some((1, 5)));
+    }
+}
@@ -1,35 +1,60 @@
 use std::cmp::Ordering;
-use std::convert::TryFrom;
+#[cfg(feature = "fs")]
 use std::fs::File;
-use std::io::{Cursor, Read};
-use std::path::Path;
-use std::sync::Arc;
+use std::io::Cursor;
+#[cfg(feature = "fs")]
+use std::path::{Path, PathBuf};
+use std::slice;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex, OnceLock};
+#[cfg(not(target_arch = "wasm32"))]
+use std::thread;
 
-use snafu::{ResultExt, Snafu};
+use snafu::{ensure, ResultExt, Snafu};
 
 use crate::chunk::reader::TsFileSensorReader;
+use crate::encoding::decoder::Field;
+use crate::error::{self, ErrorContext, TsFileError, Warnings};
 use crate::file::footer;
+use crate::file::index::IndexVisitor;
 use crate::file::metadata::MetadataIndexNodeType::*;
 use crate::file::metadata::{
-    MetaDataIndexNode, MetadataIndexEntry, MetadataIndexNodeType, TimeseriesMetadata,
-    TimeseriesMetadataType, TsFileMetadata,
+    ChunkBounds, EntryFormat, MetaDataIndexNode, MetadataIndexEntry, MetadataIndexNodeType,
+    TimeseriesMetadata, TsFileMetadata,
 };
+use crate::file::mods::Deletion;
+#[cfg(feature = "fs")]
+use crate::file::mods::{self, ModsHandling};
+use crate::file::options::{ParseMode, ReaderOptions, TimestampPrecision};
+use crate::file::path::PathPattern;
+#[cfg(feature = "fs")]
+use crate::file::reader::BufferedFile;
 use crate::file::reader::{
-    DeviceMetadataIter, FileReader, SectionReader, SensorMetadataIter, SensorReader,
+    DeviceMetadataIter, FileReader, SectionReader, SensorMetadataIter, SensorReader, SeriesBatch,
+    TryClone,
 };
+use crate::query::filter::Filter;
+use crate::utils::cache::LruCache;
 
 #[derive(Debug, Snafu)]
 pub enum Error {
+    #[cfg(feature = "fs")]
     #[snafu(display("Unable to open file: {}", source))]
     OpenFile { source: std::io::Error },
     #[snafu(display("Unable to read data: {}", source))]
     ReadData { source: std::io::Error },
     #[snafu(display("Unable to parser footer: {}", source))]
     ParserFooter { source: footer::Error },
+    #[snafu(display("Invalid index range {}..{}", start, end))]
+    InvalidIndexRange { start: i64, end: i64 },
+    #[cfg(feature = "fs")]
+    #[snafu(display("Unable to read deletions: {}", source))]
+    ReadMods { source: mods::Error },
 }
 
 type Result<T, E = Error> = std::result::Result<T, E>;
 
+#[cfg(feature = "fs")]
 impl TryFrom<File> for TsFileSearchReader<File> {
     type Error = Error;
 
@@ -38,7 +63,8 @@ impl TryFrom<File> for TsFileSearchReader<File> {
     }
 }
 
-impl<'a> TryFrom<&'a Path> for TsFileSearchReader<File> {
+#[cfg(feature = "fs")]
+impl TryFrom<&Path> for TsFileSearchReader<File> {
     type Error = Error;
 
     fn try_from(path: &Path) -> Result<Self> {
@@ -47,6 +73,7 @@ impl<'a> TryFrom<&'a Path> for TsFileSearchReader<File> {
     }
 }
 
+#[cfg(feature = "fs")]
 impl TryFrom<String> for TsFileSearchReader<File> {
     type Error = Error;
 
@@ -55,7 +82,8 @@ impl TryFrom<String> for TsFileSearchReader<File> {
     }
 }
 
-impl<'a> TryFrom<&'a str> for TsFileSearchReader<File> {
+#[cfg(feature = "fs")]
+impl TryFrom<&str> for TsFileSearchReader<File> {
     type Error = Error;
 
     fn try_from(path: &str) -> Result<Self> {
@@ -63,22 +91,71 @@ impl<'a> TryFrom<&'a str> for TsFileSearchReader<File> {
     }
 }
 
+/// A reader of one file. Clones are cheap handles sharing the parsed footer, the index
+/// cache and the section reader, for serving concurrent queries from several threads; see
+/// `TryClone` for handles with section readers of their own.
 pub struct TsFileSearchReader<R: SectionReader> {
     reader: Arc<R>,
-    metadata: TsFileMetadata,
+    metadata: Arc<TsFileMetadata>,
+    options: ReaderOptions,
+    warnings: Warnings,
+    cache: IndexCache,
+    deletions: Arc<[Deletion]>,
     all_devices: Vec<String>,
+    chunk_bounds: Arc<OnceLock<ChunkBounds>>,
 }
 
-impl<R: 'static + SectionReader> TsFileSearchReader<R> {
+/// Index nodes read by a reader and its iterators, by offset.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct IndexCache(Option<Arc<Mutex<LruCache<i64, MetadataIndexNodeType>>>>);
+
+impl IndexCache {
+    fn new(capacity: usize) -> Self {
+        match capacity {
+            0 => IndexCache(None),
+            _ => IndexCache(Some(Arc::new(Mutex::new(LruCache::new(capacity))))),
+        }
+    }
+
+    fn get(&self, offset: i64) -> Option<MetadataIndexNodeType> {
+        self.0.as_ref()?.lock().unwrap().get(&offset)
+    }
+
+    fn insert(&self, offset: i64, node: &MetadataIndexNodeType) {
+        if let Some(cache) = &self.0 {
+            cache.lock().unwrap().insert(offset, node.clone());
+        }
+    }
+}
+
+/// Collects the offsets of the chunks of every timeseries in the index.
+#[derive(Default)]
+struct ChunkOffsets(Vec<i64>);
+
+impl IndexVisitor for ChunkOffsets {
+    fn visit_timeseries(&mut self, _: &str, series: &TimeseriesMetadata, _: u64, _: usize) {
+        let offsets = series
+            .chunk_metadata()
+            .iter()
+            .map(|c| c.offset_chunk_header());
+        self.0.extend(offsets);
+    }
+}
+
+impl<R: 'static + SectionReader + Send + Sync> TsFileSearchReader<R> {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip(self, root))
+    )]
     fn binary_search_meta(
         &self,
         root: MetadataIndexNodeType,
         device: String,
         sensor: String,
-    ) -> Option<Vec<TimeseriesMetadata>> {
+    ) -> error::Result<Option<Vec<TimeseriesMetadata>>> {
         let binary_search = |c: &MetaDataIndexNode,
                              calc: Box<dyn Fn(&MetadataIndexEntry) -> Ordering>|
-         -> Option<(i64, i64, usize)> {
+         -> Option<(i64, i64, EntryFormat)> {
             let index = match c.children().binary_search_by(calc) {
                 Ok(r) => r,
                 Err(r) => {
@@ -95,108 +172,478 @@ impl<R: 'static + SectionReader> TsFileSearchReader<R> {
             } else {
                 c.children().get(index + 1)?.offset() - start
             };
-            Some((start, len, index))
+            Some((start, len, c.child_format()))
         };
 
         let mut stack = vec![root];
-        while !stack.is_empty() {
-            let index = match stack.pop()? {
+        while let Some(node) = stack.pop() {
+            let index = match node {
                 InternalDevice(c) | LeafDevice(c) | InternalMeasurement(c) => {
                     binary_search(&c, Box::new(|x| x.name().cmp(&device)))
                 }
                 LeafMeasurement(c) => {
-                    return match binary_search(&c, Box::new(|x| x.name().cmp(&sensor))) {
-                        None => None,
-                        Some(_) => {
-                            let mut result = Vec::new();
-                            for i in 0..c.children().len() {
-                                let start = c.children().get(i).unwrap();
-                                let end = if i == c.children().len() - 1 {
-                                    c.end_offset()
-                                } else {
-                                    c.children().get(i + 1)?.offset()
-                                };
-                                let len = (end - start.offset()) as usize;
-                                match self.reader.get_cursor(start.offset() as u64, len) {
-                                    Ok(mut cursor) => {
-                                        while cursor.position() < len as u64 {
-                                            if let Ok(t) = TimeseriesMetadata::new(&mut cursor) {
-                                                result.push(t);
-                                            }
-                                        }
-                                    }
-                                    Err(_) => return None,
-                                }
-                            }
-                            Some(result)
-                        }
-                    };
+                    // an entry starts a run of timeseries; only the run holding the sensor
+                    // is read, and only the sensor's own timeseries is kept
+                    let (start, len, _) =
+                        match binary_search(&c, Box::new(|x| x.name().cmp(&sensor))) {
+                            Some(index) => index,
+                            None => return Ok(None),
+                        };
+                    let result: Vec<_> = read_timeseries(
+                        self.reader.as_ref(),
+                        &self.warnings,
+                        &device,
+                        start,
+                        start + len,
+                    )?
+                    .into_iter()
+                    .filter(|series| series.measurement_id() == sensor)
+                    .collect();
+                    return Ok((!result.is_empty()).then_some(result));
                 }
             };
-            match index {
-                None => {
-                    return None;
-                }
-                Some((s, len, _)) => {
-                    let mut reader = self.reader.get_read(s as u64, len as usize);
-                    let mut data = vec![0; len as usize];
-                    reader.read_exact(&mut data);
-                    if let Ok(result) = MetadataIndexNodeType::new(&mut Cursor::new(data)) {
-                        stack.push(result);
-                    }
+            let (start, len, format) = match index {
+                Some(index) => index,
+                None => return Ok(None),
+            };
+            let node = read_node(
+                self.reader.as_ref(),
+                &self.cache,
+                format,
+                start,
+                start + len,
+            )
+            .in_series(&device, None);
+            match self.warnings.skip(node)? {
+                Some(node) => stack.push(node),
+                None => return Ok(None),
+            }
+        }
+        Ok(None)
+    }
+
+    /// The time column of `device` when `series` are value columns of its aligned chunks,
+    /// which take their timestamps from it.
+    fn time_column(
+        &self,
+        device: &str,
+        series: &[TimeseriesMetadata],
+    ) -> error::Result<Vec<TimeseriesMetadata>> {
+        if !series.iter().any(TimeseriesMetadata::is_value_column) {
+            return Ok(Vec::new());
+        }
+        let time_column = self.binary_search_meta(
+            self.metadata.file_meta().metadata_index().clone(),
+            device.to_string(),
+            String::new(),
+        )?;
+        Ok(time_column.unwrap_or_default())
+    }
+
+    /// Sets the chunk size bounds of `series` from the offsets of every chunk in the file,
+    /// which are read with the whole index the first time any handle needs them.
+    fn bound_chunks(&self, series: &mut [TimeseriesMetadata]) {
+        let bounds = self.chunk_bounds.get_or_init(|| {
+            // an unreadable part of the index only loosens the bounds of the chunks before
+            // its own, so its errors are left to the queries reading it
+            let mut offsets = ChunkOffsets::default();
+            let _ = self.clone().visit_index(&mut offsets);
+            ChunkBounds::new(offsets.0, self.metadata.file_meta().meta_offset())
+        });
+        for series in series {
+            series.set_chunk_bounds(bounds);
+        }
+    }
+
+    /// Walks the metadata index tree depth first, reading only the nodes and timeseries
+    /// `visitor` descends into. In lenient mode unreadable ones are skipped and kept as
+    /// warnings.
+    pub fn visit_index<V: IndexVisitor>(&self, visitor: &mut V) -> error::Result<()> {
+        let root = self.metadata.file_meta().metadata_index().clone();
+        self.visit_node(visitor, &root, None, None, 0)
+    }
+
+    fn visit_node<V: IndexVisitor>(
+        &self,
+        visitor: &mut V,
+        node: &MetadataIndexNodeType,
+        range: Option<(i64, i64)>,
+        device: Option<&str>,
+        depth: usize,
+    ) -> error::Result<()> {
+        if !visitor.enter_node(node, range, depth) {
+            return Ok(());
+        }
+        let c = match node {
+            InternalDevice(c) | LeafDevice(c) | InternalMeasurement(c) | LeafMeasurement(c) => c,
+        };
+        for (i, entry) in c.children().iter().enumerate() {
+            if !visitor.visit_entry(node, entry, depth) {
+                continue;
+            }
+            let (start, end) = c.child_range(i).expect("i indexes a child");
+            if let LeafMeasurement(_) = node {
+                let device = device.unwrap_or_default();
+                let series =
+                    read_timeseries_at(self.reader.as_ref(), &self.warnings, device, start, end)?;
+                for (offset, series) in series {
+                    visitor.visit_timeseries(device, &series, offset, depth + 1);
                 }
+                continue;
+            }
+            // the entries of a device leaf name the devices of the subtrees below them
+            let device = match node {
+                LeafDevice(_) => Some(entry.name()),
+                _ => device,
+            };
+            let child = read_node(
+                self.reader.as_ref(),
+                &self.cache,
+                c.child_format(),
+                start,
+                end,
+            );
+            let child = match device {
+                Some(device) => child.in_series(device, None),
+                None => child,
+            };
+            if let Some(child) = self.warnings.skip(child)? {
+                self.visit_node(visitor, &child, Some((start, end)), device, depth + 1)?;
             }
         }
-        None
+        visitor.leave_node(node, depth);
+        Ok(())
     }
+
+    fn sensor_reader(
+        &self,
+        device: &str,
+        sensor: &str,
+        series: Vec<TimeseriesMetadata>,
+    ) -> TsFileSensorReader<R> {
+        sensor_reader(
+            self.reader.clone(),
+            &self.options,
+            &self.warnings,
+            deletions_of(&self.deletions, device, sensor),
+            series,
+        )
+    }
+
+    /// Leaves the points removed by `deletions` out of the chunks read, as the `.mods` file
+    /// of the TsFile would with `ModsHandling::Apply`.
+    pub fn with_deletions(mut self, deletions: Vec<Deletion>) -> Self {
+        self.deletions = deletions.into();
+        self
+    }
+}
+
+/// A reader of the chunks of `series`, set up as `options` asks.
+fn sensor_reader<R: SectionReader>(
+    reader: Arc<R>,
+    options: &ReaderOptions,
+    warnings: &Warnings,
+    deletions: Vec<Deletion>,
+    series: Vec<TimeseriesMetadata>,
+) -> TsFileSensorReader<R> {
+    TsFileSensorReader::new(reader, series)
+        .with_read_ahead(options.read_ahead())
+        .with_buffer_pool(options.buffer_pool().cloned())
+        .with_max_allocation(options.max_allocation())
+        .with_coalesce_limit(options.coalesce_limit())
+        .with_warnings(warnings.clone())
+        .with_deletions(deletions)
+        .with_verify_pages(options.verify_pages())
+        .with_time_encoding(options.time_encoding())
+        .with_time_order(options.time_order())
+}
+
+/// The deletions applying to `device.sensor`.
+fn deletions_of(deletions: &[Deletion], device: &str, sensor: &str) -> Vec<Deletion> {
+    deletions
+        .iter()
+        .filter(|d| d.matches(device, sensor))
+        .cloned()
+        .collect()
 }
 
-impl<R: 'static + SectionReader> FileReader for TsFileSearchReader<R> {
+impl<R: 'static + SectionReader + Send + Sync> FileReader for TsFileSearchReader<R> {
     fn metadata(&self) -> &TsFileMetadata {
         &self.metadata
     }
 
-    fn device_meta_iter(&self) -> Box<dyn DeviceMetadataIter<Item = MetadataIndexNodeType>> {
+    fn device_meta_iter(
+        &self,
+    ) -> Box<dyn DeviceMetadataIter<Item = error::Result<MetadataIndexNodeType>>> {
         let stack = vec![self.metadata.file_meta().metadata_index().clone()];
-        Box::new(DeviceMetadataReader::new(self.reader.clone(), stack))
+        Box::new(
+            DeviceMetadataReader::new(self.reader.clone(), stack)
+                .with_warnings(self.warnings.clone()),
+        )
     }
 
     fn get_device_reader() {
         todo!()
     }
 
+    fn timestamp_precision(&self) -> TimestampPrecision {
+        self.options.timestamp_precision()
+    }
+
     fn sensor_meta_iter(
         &self,
         device: &str,
-    ) -> Box<dyn SensorMetadataIter<Item = TimeseriesMetadata>> {
+    ) -> Box<dyn SensorMetadataIter<Item = error::Result<TimeseriesMetadata>>> {
         let stack = vec![self.metadata.file_meta().metadata_index().clone()];
-        Box::new(SensorMetadataReader::new(
-            self.reader.clone(),
-            stack,
-            device.to_string(),
-        ))
+        Box::new(
+            SensorMetadataReader::new(self.reader.clone(), stack, device.to_string())
+                .with_warnings(self.warnings.clone())
+                .with_cache(self.cache.clone()),
+        )
     }
 
-    fn get_sensor_reader(&self, device: &str, sensor: &str) -> Option<Box<dyn SensorReader>> {
-        match self.binary_search_meta(
+    fn get_sensor_reader(
+        &self,
+        device: &str,
+        sensor: &str,
+    ) -> error::Result<Option<Box<dyn SensorReader>>> {
+        let series = self.binary_search_meta(
             self.metadata.file_meta().metadata_index().clone(),
             device.to_string(),
             sensor.to_string(),
-        ) {
-            None => None,
-            Some(time_series) => Some(Box::new(TsFileSensorReader::new(
-                self.reader.clone(),
-                time_series,
-            ))),
+        )?;
+        let mut series = match series {
+            Some(series) => series,
+            None => return Ok(None),
+        };
+        let mut time_column = self.time_column(device, &series)?;
+        self.bound_chunks(&mut series);
+        self.bound_chunks(&mut time_column);
+        Ok(Some(Box::new(
+            self.sensor_reader(device, sensor, series)
+                .with_time_column(time_column),
+        )))
+    }
+
+    fn last_points(&self, device: &str) -> error::Result<Vec<(String, i64, Field)>> {
+        self.sensor_meta_iter(device)
+            .filter(|s| !matches!(s, Ok(s) if s.is_time_column()))
+            .map(|s| {
+                let s = s?;
+                let statistic = s.statistic();
+                Ok((
+                    s.measurement_id().to_string(),
+                    statistic.end_time(),
+                    statistic.last_value(),
+                ))
+            })
+            .collect()
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip(self)))]
+    fn match_paths(
+        &self,
+        pattern: &PathPattern,
+    ) -> error::Result<Vec<(String, TimeseriesMetadata)>> {
+        let mut result = Vec::new();
+        // (node, exclusive upper bound of the names below it, device it belongs to)
+        let mut stack: Vec<(MetadataIndexNodeType, Option<String>, Option<String>)> = vec![(
+            self.metadata.file_meta().metadata_index().clone(),
+            None,
+            None,
+        )];
+
+        while let Some((node, hi, device)) = stack.pop() {
+            let mut children = Vec::new();
+            match &node {
+                InternalDevice(c) | LeafDevice(c) => {
+                    for (i, entry) in c.children().iter().enumerate() {
+                        let (end, next) = match c.children().get(i + 1) {
+                            Some(n) => (n.offset(), Some(n.name())),
+                            None => (c.end_offset(), hi.as_deref()),
+                        };
+                        // a device's measurement subtree starts with an unbounded range
+                        let (matched, child_hi, child_device) = match node {
+                            LeafDevice(_) => (
+                                pattern.may_match_device(entry.name()),
+                                None,
+                                Some(entry.name().to_string()),
+                            ),
+                            _ => (
+                                pattern.may_match_range(entry.name(), next),
+                                next.map(|n| n.to_string()),
+                                None,
+                            ),
+                        };
+                        if !matched {
+                            continue;
+                        }
+                        let child = read_node(
+                            self.reader.as_ref(),
+                            &self.cache,
+                            c.child_format(),
+                            entry.offset(),
+                            end,
+                        );
+                        if let Some(child) = self.warnings.skip(child)? {
+                            children.push((child, child_hi, child_device));
+                        }
+                    }
+                }
+                InternalMeasurement(c) | LeafMeasurement(c) => {
+                    let device = match &device {
+                        Some(d) => d,
+                        None => continue,
+                    };
+                    for (i, entry) in c.children().iter().enumerate() {
+                        let (end, next) = match c.children().get(i + 1) {
+                            Some(n) => (n.offset(), Some(n.name())),
+                            None => (c.end_offset(), hi.as_deref()),
+                        };
+                        let lo = format!("{}.{}", device, entry.name());
+                        let next_path = next.map(|n| format!("{}.{}", device, n));
+                        if !pattern.may_match_range(&lo, next_path.as_deref()) {
+                            continue;
+                        }
+                        if let InternalMeasurement(_) = node {
+                            let child = read_node(
+                                self.reader.as_ref(),
+                                &self.cache,
+                                c.child_format(),
+                                entry.offset(),
+                                end,
+                            )
+                            .in_series(device, None);
+                            if let Some(child) = self.warnings.skip(child)? {
+                                children.push((
+                                    child,
+                                    next.map(|n| n.to_string()),
+                                    Some(device.clone()),
+                                ));
+                            }
+                            continue;
+                        }
+                        let series = read_timeseries(
+                            self.reader.as_ref(),
+                            &self.warnings,
+                            device,
+                            entry.offset(),
+                            end,
+                        )?;
+                        for t in series.into_iter().filter(|t| !t.is_time_column()) {
+                            let path = format!("{}.{}", device, t.measurement_id());
+                            if pattern.matches(&path) {
+                                result.push((device.clone(), t));
+                            }
+                        }
+                    }
+                }
+            }
+            // visit children in index order
+            stack.extend(children.into_iter().rev());
         }
+        Ok(result)
     }
-}
 
-impl<R: 'static + SectionReader> TsFileSearchReader<R> {}
+    fn scan(
+        &self,
+        pattern: &PathPattern,
+        filter: Option<Filter>,
+        parallelism: usize,
+    ) -> Receiver<error::Result<SeriesBatch>> {
+        let parallelism = parallelism.max(1);
+        // bounded, so workers stop reading while the consumer falls behind
+        #[cfg(not(target_arch = "wasm32"))]
+        let (sender, receiver) = mpsc::sync_channel(parallelism * 4);
+        // without threads every batch is read before returning, so none may wait
+        #[cfg(target_arch = "wasm32")]
+        let (sender, receiver) = mpsc::channel();
+        let series = self.match_paths(pattern).and_then(|series| {
+            series
+                .into_iter()
+                .map(|(device, mut timeseries)| {
+                    let mut time_column =
+                        self.time_column(&device, slice::from_ref(&timeseries))?;
+                    self.bound_chunks(slice::from_mut(&mut timeseries));
+                    self.bound_chunks(&mut time_column);
+                    Ok((device, timeseries, time_column))
+                })
+                .collect::<error::Result<Vec<_>>>()
+        });
+        let series = match series {
+            Ok(series) => Arc::new(Mutex::new(series.into_iter())),
+            Err(e) => {
+                let _ = sender.send(Err(e));
+                return receiver;
+            }
+        };
+        for _ in 0..parallelism {
+            let (series, sender, filter) = (series.clone(), sender.clone(), filter.clone());
+            let (reader, options) = (self.reader.clone(), self.options.clone());
+            let (warnings, deletions) = (self.warnings.clone(), self.deletions.clone());
+            let work = move || loop {
+                let next = series.lock().unwrap().next();
+                let (device, timeseries, time_column) = match next {
+                    Some(next) => next,
+                    None => return,
+                };
+                let sensor = timeseries.measurement_id().to_string();
+                let mut sensor_reader = sensor_reader(
+                    reader.clone(),
+                    &options,
+                    &warnings,
+                    deletions_of(&deletions, &device, &sensor),
+                    vec![timeseries],
+                )
+                .with_time_column(time_column);
+                if let Some(filter) = &filter {
+                    sensor_reader.set_filter(filter.clone());
+                }
+                for i in 0..sensor_reader.number_of_chunks() {
+                    let pages = match sensor_reader.get_chunk_reader(i) {
+                        Ok(pages) => pages,
+                        Err(e) => {
+                            let e = TsFileError::from(e).in_series(&device, Some(&sensor));
+                            if sender.send(Err(e)).is_err() {
+                                return;
+                            }
+                            continue;
+                        }
+                    };
+                    for page in pages {
+                        let batch = match page.and_then(|page| page.batch()) {
+                            Ok((times, _)) if times.is_empty() => continue,
+                            Ok((times, values)) => Ok(SeriesBatch {
+                                device: device.clone(),
+                                sensor: sensor.clone(),
+                                times,
+                                values,
+                            }),
+                            Err(e) => Err(TsFileError::from(e).in_series(&device, Some(&sensor))),
+                        };
+                        if sender.send(batch).is_err() {
+                            return;
+                        }
+                    }
+                }
+            };
+            #[cfg(not(target_arch = "wasm32"))]
+            thread::spawn(work);
+            #[cfg(target_arch = "wasm32")]
+            work();
+        }
+        receiver
+    }
+
+    fn take_warnings(&self) -> Vec<TsFileError> {
+        self.warnings.take()
+    }
+}
 
 pub struct DeviceMetadataReader<R: SectionReader> {
     reader: Arc<R>,
     stack: Vec<MetadataIndexNodeType>,
+    warnings: Warnings,
 }
 
 pub struct SensorMetadataReader<R: SectionReader> {
@@ -204,6 +651,8 @@ pub struct SensorMetadataReader<R: SectionReader> {
     stack: Vec<MetadataIndexNodeType>,
     ts_stack: Vec<TimeseriesMetadata>,
     device: String,
+    warnings: Warnings,
+    cache: IndexCache,
 }
 
 impl<R: SectionReader> DeviceMetadataIter for DeviceMetadataReader<R> {}
@@ -212,7 +661,16 @@ impl<R: SectionReader> SensorMetadataIter for SensorMetadataReader<R> {}
 
 impl<R: SectionReader> DeviceMetadataReader<R> {
     pub fn new(reader: Arc<R>, stack: Vec<MetadataIndexNodeType>) -> Self {
-        Self { reader, stack }
+        Self {
+            reader,
+            stack,
+            warnings: Warnings::default(),
+        }
+    }
+
+    pub(crate) fn with_warnings(mut self, warnings: Warnings) -> Self {
+        self.warnings = warnings;
+        self
     }
 }
 
@@ -223,119 +681,732 @@ impl<R: SectionReader> SensorMetadataReader<R> {
             stack,
             ts_stack: Vec::new(),
             device,
+            warnings: Warnings::default(),
+            cache: IndexCache::default(),
         }
     }
+
+    pub(crate) fn with_warnings(mut self, warnings: Warnings) -> Self {
+        self.warnings = warnings;
+        self
+    }
+
+    pub(crate) fn with_cache(mut self, cache: IndexCache) -> Self {
+        self.cache = cache;
+        self
+    }
 }
 
 impl<R: SectionReader> Iterator for DeviceMetadataReader<R> {
-    type Item = MetadataIndexNodeType;
+    type Item = error::Result<MetadataIndexNodeType>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.stack.is_empty() {
-            return None;
+        let result = self.next_leaf();
+        if result.is_err() {
+            // a node that cannot be read ends the iteration
+            self.stack.clear();
         }
-        while !self.stack.is_empty() {
-            match self.stack.pop()? {
+        result.transpose()
+    }
+}
+
+impl<R: SectionReader> DeviceMetadataReader<R> {
+    fn next_leaf(&mut self) -> error::Result<Option<MetadataIndexNodeType>> {
+        while let Some(node) = self.stack.pop() {
+            match node {
                 InternalDevice(c) => {
-                    let start = c.children().get(0).unwrap();
-                    let end = c.end_offset();
-                    let len = (end - start.offset()) as usize;
-                    if let Ok(mut cursor) = self.reader.get_cursor(start.offset() as u64, len) {
-                        let mut types = Vec::new();
-                        for _ in 0..c.children().len() {
-                            if let Ok(t) = MetadataIndexNodeType::new(&mut cursor) {
-                                types.push(t);
-                            }
-                        }
-                        while !types.is_empty() {
-                            self.stack.push(types.pop()?);
-                        }
+                    let start = match c.children().first() {
+                        Some(start) => start.offset(),
+                        None => continue,
+                    };
+                    let section = read_section(self.reader.as_ref(), start, c.end_offset());
+                    let mut cursor = match self.warnings.skip(section)? {
+                        Some(cursor) => cursor,
+                        None => continue,
+                    };
+                    let mut children = Vec::with_capacity(c.children().len());
+                    for entry in c.children() {
+                        // parse each child at its own offset, so a corrupt one hides no other
+                        cursor.set_position(entry.offset().wrapping_sub(start) as u64);
+                        let child =
+                            MetadataIndexNodeType::with_format(&mut cursor, c.child_format())
+                                .at_offset(entry.offset() as u64);
+                        children.extend(self.warnings.skip(child)?);
                     }
+                    self.stack.extend(children.into_iter().rev());
                 }
-                LeafDevice(c) => {
-                    return Some(MetadataIndexNodeType::LeafDevice(c));
-                }
+                LeafDevice(c) => return Ok(Some(MetadataIndexNodeType::LeafDevice(c))),
                 _ => {}
             }
         }
-        None
+        Ok(None)
     }
 }
 
 impl<R: SectionReader> Iterator for SensorMetadataReader<R> {
-    type Item = TimeseriesMetadata;
+    type Item = error::Result<TimeseriesMetadata>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if !self.ts_stack.is_empty() {
-            return self.ts_stack.pop();
+        if let Some(timeseries) = self.ts_stack.pop() {
+            return Some(Ok(timeseries));
         }
-
-        if self.stack.is_empty() {
-            return None;
+        match self.read_leaf() {
+            Ok(()) => self.ts_stack.pop().map(Ok),
+            Err(e) => {
+                // a node that cannot be read ends the iteration
+                self.stack.clear();
+                self.ts_stack.clear();
+                Some(Err(e))
+            }
         }
+    }
+}
 
-        while !self.stack.is_empty() {
-            match self.stack.pop()? {
+impl<R: SectionReader> SensorMetadataReader<R> {
+    /// Descends to the next measurement leaf of the device and reads its timeseries.
+    fn read_leaf(&mut self) -> error::Result<()> {
+        while let Some(node) = self.stack.pop() {
+            match node {
                 InternalDevice(c) | LeafDevice(c) | InternalMeasurement(c) => {
                     let index = match c
                         .children()
                         .binary_search_by(|x| x.name().cmp(&self.device))
                     {
                         Ok(r) => r,
-                        Err(r) => {
-                            if r == 0 {
-                                return None;
-                            } else {
-                                r - 1
-                            }
+                        Err(0) => {
+                            self.stack.clear();
+                            return Ok(());
                         }
+                        Err(r) => r - 1,
                     };
-
-                    let child_num = c.children().len();
-
-                    let start = c.children().get(index)?.offset();
-                    let len = if index == child_num - 1 {
-                        c.end_offset() - start
-                    } else {
-                        c.children().get(index + 1)?.offset() - start
+                    let start = c.children()[index].offset();
+                    let end = match c.children().get(index + 1) {
+                        Some(next) => next.offset(),
+                        None => c.end_offset(),
                     };
-                    if let Ok(mut cursor) = self.reader.get_cursor(start as u64, len as usize) {
-                        if let Ok(t) = MetadataIndexNodeType::new(&mut cursor) {
-                            self.stack.push(t);
-                        }
+                    let node = read_node(
+                        self.reader.as_ref(),
+                        &self.cache,
+                        c.child_format(),
+                        start,
+                        end,
+                    )
+                    .in_series(&self.device, None);
+                    match self.warnings.skip(node)? {
+                        Some(node) => self.stack.push(node),
+                        // the rest of the device cannot be found without this node
+                        None => self.stack.clear(),
                     }
                 }
                 LeafMeasurement(c) => {
-                    for i in 0..c.children().len() {
-                        let start = c.children().get(i).unwrap();
-                        let end = if i == c.children().len() - 1 {
-                            c.end_offset()
-                        } else {
-                            c.children().get(i + 1)?.offset()
+                    for (i, entry) in c.children().iter().enumerate() {
+                        let end = match c.children().get(i + 1) {
+                            Some(next) => next.offset(),
+                            None => c.end_offset(),
                         };
-                        let len = (end - start.offset()) as usize;
-                        if let Ok(mut cursor) = self.reader.get_cursor(start.offset() as u64, len) {
-                            while cursor.position() < len as u64 {
-                                if let Ok(t) = TimeseriesMetadata::new(&mut cursor) {
-                                    self.ts_stack.push(t);
-                                }
-                            }
-                        }
+                        self.ts_stack.extend(read_timeseries(
+                            self.reader.as_ref(),
+                            &self.warnings,
+                            &self.device,
+                            entry.offset(),
+                            end,
+                        )?);
                     }
+                    return Ok(());
                 }
             }
         }
-        self.ts_stack.pop()
+        Ok(())
+    }
+}
+
+/// The bytes of the file between two offsets read from the index.
+fn read_section<R: SectionReader>(
+    reader: &R,
+    start: i64,
+    end: i64,
+) -> error::Result<Cursor<Vec<u8>>> {
+    ensure!(0 <= start && start <= end, InvalidIndexRange { start, end });
+    trace_event!(offset = start, bytes = end - start, "read index");
+    reader
+        .get_cursor(start as u64, (end - start) as usize)
+        .at_offset(start as u64)
+}
+
+/// The index node between two offsets, from `cache` if it was read before.
+fn read_node<R: SectionReader>(
+    reader: &R,
+    cache: &IndexCache,
+    format: EntryFormat,
+    start: i64,
+    end: i64,
+) -> error::Result<MetadataIndexNodeType> {
+    if let Some(node) = cache.get(start) {
+        trace_event!(offset = start, "index node cached");
+        return Ok(node);
+    }
+    let mut cursor = read_section(reader, start, end)?;
+    let node = MetadataIndexNodeType::with_format(&mut cursor, format).at_offset(start as u64)?;
+    cache.insert(start, &node);
+    Ok(node)
+}
+
+/// The timeseries of `device` between two offsets of a measurement leaf. In lenient mode
+/// the ones after an unreadable timeseries are skipped, as they cannot be located.
+fn read_timeseries<R: SectionReader>(
+    reader: &R,
+    warnings: &Warnings,
+    device: &str,
+    start: i64,
+    end: i64,
+) -> error::Result<Vec<TimeseriesMetadata>> {
+    let series = read_timeseries_at(reader, warnings, device, start, end)?;
+    Ok(series.into_iter().map(|(_, series)| series).collect())
+}
+
+/// `read_timeseries`, with the offset of each timeseries.
+fn read_timeseries_at<R: SectionReader>(
+    reader: &R,
+    warnings: &Warnings,
+    device: &str,
+    start: i64,
+    end: i64,
+) -> error::Result<Vec<(u64, TimeseriesMetadata)>> {
+    let mut series = Vec::new();
+    let section = read_section(reader, start, end).in_series(device, None);
+    let mut cursor = match warnings.skip(section)? {
+        Some(cursor) => cursor,
+        None => return Ok(series),
+    };
+    let len = cursor.get_ref().len() as u64;
+    while cursor.position() < len {
+        let offset = start as u64 + cursor.position();
+        let timeseries = TimeseriesMetadata::new(&mut cursor)
+            .at_offset(offset)
+            .in_series(device, None);
+        match warnings.skip(timeseries)? {
+            Some(timeseries) => series.push((offset, timeseries)),
+            None => break,
+        }
     }
+    Ok(series)
 }
 
-impl<R: 'static + SectionReader> TsFileSearchReader<R> {
+impl<R: 'static + SectionReader + Send + Sync> TsFileSearchReader<R> {
     pub fn new(file: R) -> Result<Self> {
-        let metadata = footer::parser_metadata(&file).context(ParserFooter)?;
+        Self::with_options(file, ReaderOptions::default())
+    }
+
+    pub fn with_options(file: R, options: ReaderOptions) -> Result<Self> {
+        let metadata = match options.incremental_metadata() {
+            true => footer::parser_metadata_index(&file),
+            false => footer::parser_metadata_with_bloom_filter(&file, options.bloom_filter()),
+        }
+        .context(ParserFooter)?;
+        let warnings = match options.parse_mode() {
+            ParseMode::Strict => Warnings::default(),
+            ParseMode::Lenient => Warnings::lenient(),
+        };
         Ok(Self {
             reader: Arc::new(file),
-            metadata,
+            metadata: Arc::new(metadata),
+            cache: IndexCache::new(options.metadata_cache_capacity()),
+            options,
+            warnings,
+            deletions: Arc::new([]),
             all_devices: vec![],
+            chunk_bounds: Arc::default(),
         })
     }
 }
+
+impl<R: SectionReader> Clone for TsFileSearchReader<R> {
+    /// A handle to the same file. It records its own warnings, so the errors skipped by
+    /// one handle are not taken by another.
+    fn clone(&self) -> Self {
+        Self {
+            reader: self.reader.clone(),
+            metadata: self.metadata.clone(),
+            options: self.options.clone(),
+            warnings: self.warnings.fresh(),
+            cache: self.cache.clone(),
+            deletions: self.deletions.clone(),
+            all_devices: self.all_devices.clone(),
+            chunk_bounds: self.chunk_bounds.clone(),
+        }
+    }
+}
+
+impl<R: SectionReader + TryClone> TryClone for TsFileSearchReader<R> {
+    /// A handle like `clone` that reads through a clone of the section reader, e.g. its own
+    /// file descriptor, rather than sharing it.
+    fn try_clone(&self) -> std::io::Result<Self> {
+        Ok(Self {
+            reader: Arc::new(self.reader.try_clone()?),
+            ..self.clone()
+        })
+    }
+}
+
+#[cfg(feature = "fs")]
+impl TsFileSearchReader<BufferedFile> {
+    /// Opens the file at `path`, reading it with the buffer size of `options`.
+    pub fn open(path: impl AsRef<Path>, options: ReaderOptions) -> Result<Self> {
+        Self::builder(path).with_options(options).build()
+    }
+
+    /// A builder of a reader of the file at `path`, starting from the default options.
+    pub fn builder(path: impl AsRef<Path>) -> ReaderBuilder {
+        ReaderBuilder {
+            path: path.as_ref().to_path_buf(),
+            options: ReaderOptions::default(),
+        }
+    }
+}
+
+/// Opens a `TsFileSearchReader` of a file with the knobs of `ReaderOptions`.
+#[cfg(feature = "fs")]
+#[derive(Debug, Clone)]
+pub struct ReaderBuilder {
+    path: PathBuf,
+    options: ReaderOptions,
+}
+
+#[cfg(feature = "fs")]
+impl ReaderBuilder {
+    /// Replaces all options set so far.
+    pub fn with_options(mut self, options: ReaderOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn with_buffer_size(mut self, buffer_size: usize) -> Self {
+        self.options = self.options.with_buffer_size(buffer_size);
+        self
+    }
+
+    pub fn with_metadata_cache_capacity(mut self, capacity: usize) -> Self {
+        self.options = self.options.with_metadata_cache_capacity(capacity);
+        self
+    }
+
+    pub fn with_bloom_filter(mut self, bloom_filter: bool) -> Self {
+        self.options = self.options.with_bloom_filter(bloom_filter);
+        self
+    }
+
+    pub fn with_incremental_metadata(mut self, incremental_metadata: bool) -> Self {
+        self.options = self.options.with_incremental_metadata(incremental_metadata);
+        self
+    }
+
+    pub fn with_mods(mut self, mods: ModsHandling) -> Self {
+        self.options = self.options.with_mods(mods);
+        self
+    }
+
+    pub fn with_timestamp_precision(mut self, precision: TimestampPrecision) -> Self {
+        self.options = self.options.with_timestamp_precision(precision);
+        self
+    }
+
+    pub fn with_parse_mode(mut self, mode: ParseMode) -> Self {
+        self.options = self.options.with_parse_mode(mode);
+        self
+    }
+
+    pub fn build(self) -> Result<TsFileSearchReader<BufferedFile>> {
+        let file = File::open(&self.path).context(OpenFile)?;
+        let deletions = match self.options.mods() {
+            ModsHandling::Apply => mods::read_mods(&self.path).context(ReadMods)?,
+            ModsHandling::Ignore => Vec::new(),
+        };
+        let buffer_size = self.options.buffer_size();
+        let reader =
+            TsFileSearchReader::with_options(BufferedFile::new(file, buffer_size), self.options)?;
+        Ok(reader.with_deletions(deletions))
+    }
+}
+
+/// A file of uncompressed chunks, one for each `(device, sensor, times)` in file order,
+/// holding the INT32 value `time * 10` at each time, followed by the metadata indexing
+/// them. Times are TS_2DIFF encoded and values PLAIN. An empty sensor is the time column of
+/// an aligned device: the sensors of the device after it are its value columns, null in
+/// the rows whose time they lack. Aligned columns are split into pages of four rows, value
+/// pages without any value being written as IoTDB does, as two zero sizes.
+#[cfg(test)]
+pub(crate) fn test_file(series: &[(&str, &str, &[i64])]) -> Vec<u8> {
+    use crate::chunk::reader::{CHUNK_HEADER, ONLY_ONE_PAGE_CHUNK_HEADER};
+    use crate::encoding::decoder::ColumnBatch;
+    use crate::file::metadata::TSDataType;
+    use crate::file::metadata_writer::MetadataWriter;
+    use crate::file::statistics::Statistic;
+    use crate::utils::cursor::VarIntWriter;
+    use crate::{MAGIC_STRING, VERSION_NUMBER};
+
+    /// One block of IoTDB's LongDeltaEncoder: the number of packed deltas, their width,
+    /// the smallest delta and the first time, then each delta less the smallest.
+    fn ts2diff(times: &[i64]) -> Vec<u8> {
+        let Some(&first) = times.first() else {
+            return Vec::new();
+        };
+        let deltas: Vec<_> = times.windows(2).map(|w| w[1].wrapping_sub(w[0])).collect();
+        let min = deltas.iter().copied().min().unwrap_or(0);
+        let packed: Vec<_> = deltas.iter().map(|d| d.wrapping_sub(min) as u64).collect();
+        let width = 64 - packed.iter().max().unwrap_or(&0).leading_zeros() as usize;
+        let mut block = (packed.len() as i32).to_be_bytes().to_vec();
+        block.extend((width as i32).to_be_bytes());
+        block.extend(min.to_be_bytes());
+        block.extend(first.to_be_bytes());
+        let mut bits = vec![0; (packed.len() * width).div_ceil(8)];
+        for (i, value) in packed.iter().enumerate() {
+            for bit in 0..width {
+                if value >> (width - 1 - bit) & 1 == 1 {
+                    let at = i * width + bit;
+                    bits[at / 8] |= 0x80 >> (at % 8);
+                }
+            }
+        }
+        block.extend(bits);
+        block
+    }
+
+    /// Appends the values of the points at `times` to `page`, with their statistics.
+    fn int_values(mut page: Vec<u8>, times: &[i64]) -> (Vec<u8>, Statistic) {
+        let values: Vec<_> = times.iter().map(|&t| t as i32 * 10).collect();
+        for value in &values {
+            page.write_signed_varint_32(*value).unwrap();
+        }
+        let mut statistic = Statistic::empty(&TSDataType::Int32);
+        statistic.update_batch(times, &ColumnBatch::Int32(values));
+        (page, statistic)
+    }
+
+    const ALIGNED_PAGE_ROWS: usize = 4;
+
+    let mut file = MAGIC_STRING.as_bytes().to_vec();
+    file.push(VERSION_NUMBER);
+    let mut writer = MetadataWriter::new();
+    let mut time_column: Option<(&str, &[i64])> = None;
+    for &(device, sensor, times) in series {
+        let rows = time_column
+            .filter(|(d, _)| *d == device)
+            .map(|(_, rows)| rows);
+        // the data and statistics of each page, `None` for value pages without values
+        let mut pages = Vec::new();
+        let (mask, data_type, encoding) = match rows {
+            _ if sensor.is_empty() => {
+                time_column = Some((device, times));
+                for rows in times.chunks(ALIGNED_PAGE_ROWS) {
+                    let mut statistic = Statistic::empty(&TSDataType::VECTOR);
+                    statistic.update_batch(rows, &ColumnBatch::Int64(rows.to_vec()));
+                    pages.push(Some((ts2diff(rows), statistic)));
+                }
+                (0x80, TSDataType::VECTOR, 4)
+            }
+            Some(rows) => {
+                for rows in rows.chunks(ALIGNED_PAGE_ROWS) {
+                    let present: Vec<_> =
+                        rows.iter().copied().filter(|t| times.contains(t)).collect();
+                    if present.is_empty() {
+                        pages.push(None);
+                        continue;
+                    }
+                    // the row count and a bitmap of the rows holding a value
+                    let mut page = (rows.len() as i32).to_be_bytes().to_vec();
+                    let mut bitmap = vec![0; rows.len().div_ceil(8)];
+                    for (row, time) in rows.iter().enumerate() {
+                        if times.contains(time) {
+                            bitmap[row / 8] |= 0x80 >> (row % 8);
+                        }
+                    }
+                    page.extend(bitmap);
+                    pages.push(Some(int_values(page, &present)));
+                }
+                (0x40, TSDataType::Int32, 0)
+            }
+            None => {
+                let time = ts2diff(times);
+                let mut page = Vec::new();
+                page.write_unsigned_varint_32(time.len() as u32).unwrap();
+                page.extend(time);
+                pages.push(Some(int_values(page, times)));
+                (0, TSDataType::Int32, 0)
+            }
+        };
+
+        let one_page = pages.len() == 1;
+        let mut statistic = Statistic::empty(&data_type);
+        let mut data = Vec::new();
+        for page in pages {
+            let Some((page, page_statistic)) = page else {
+                data.extend([0, 0]);
+                continue;
+            };
+            data.write_unsigned_varint_32(page.len() as u32).unwrap();
+            data.write_unsigned_varint_32(page.len() as u32).unwrap();
+            if !one_page {
+                page_statistic.serialize(&mut data).unwrap();
+            }
+            statistic.merge(&page_statistic).unwrap();
+            data.extend(page);
+        }
+        let offset = file.len() as i64;
+        file.push(match one_page {
+            true => ONLY_ONE_PAGE_CHUNK_HEADER | mask,
+            false => CHUNK_HEADER | mask,
+        });
+        file.write_signed_varint_32(sensor.len() as i32).unwrap();
+        file.extend(sensor.as_bytes());
+        file.write_unsigned_varint_32(data.len() as u32).unwrap();
+        file.extend([data_type.int_id(), 0, encoding]);
+        file.extend(data);
+        writer
+            .add_chunk(device, sensor, &data_type, offset, statistic)
+            .unwrap();
+    }
+    writer.finish(file.len() as u64, file).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reader_is_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<TsFileSearchReader<bytes::Bytes>>();
+        #[cfg(feature = "fs")]
+        assert_send_sync::<TsFileSearchReader<File>>();
+    }
+
+    #[test]
+    fn lenient_mode_skips_unreadable_series() {
+        let mut file = test_file(&[("root.sg.d1", "s1", &[1]), ("root.sg.d2", "s1", &[1, 2])]);
+
+        // overwrite the measurement index node of d1
+        let reader = TsFileSearchReader::new(bytes::Bytes::from(file.clone())).unwrap();
+        let leaf = match reader.device_meta_iter().next().unwrap().unwrap() {
+            LeafDevice(c) => c,
+            _ => panic!("expected a leaf device node"),
+        };
+        let (start, end) = (leaf.children()[0].offset(), leaf.children()[1].offset());
+        file[start as usize..end as usize].fill(0x7f);
+        let file = bytes::Bytes::from(file);
+
+        let strict = TsFileSearchReader::new(file.clone()).unwrap();
+        assert!(strict.match_paths(&PathPattern::new("**")).is_err());
+        assert!(strict
+            .sensor_meta_iter("root.sg.d1")
+            .next()
+            .unwrap()
+            .is_err());
+
+        let options = ReaderOptions::new().with_parse_mode(ParseMode::Lenient);
+        let lenient = TsFileSearchReader::with_options(file, options).unwrap();
+        let paths = lenient.match_paths(&PathPattern::new("**")).unwrap();
+        assert_eq!(paths.len(), 1);
+        assert_eq!(paths[0].0, "root.sg.d2");
+        let rows: Vec<_> = lenient
+            .stream(&PathPattern::new("root.sg.d2.*"), None)
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                (1, vec![Some(Field::Int32(10))]),
+                (2, vec![Some(Field::Int32(20))]),
+            ]
+        );
+        assert!(lenient.sensor_meta_iter("root.sg.d1").next().is_none());
+        let warnings = lenient.take_warnings();
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(warnings[0].offset(), Some(start as u64));
+        assert!(lenient.take_warnings().is_empty());
+    }
+
+    #[test]
+    fn aligned_device_schema() {
+        use crate::file::metadata::TSDataType;
+
+        let file = test_file(&[("root.sg.d1", "", &[1]), ("root.sg.d1", "s1", &[1])]);
+        let reader = TsFileSearchReader::new(bytes::Bytes::from(file)).unwrap();
+
+        let schema = reader.device_schema("root.sg.d1").unwrap();
+        assert!(schema.is_aligned());
+        assert_eq!(schema.sensors(), [("s1".to_string(), TSDataType::Int32)]);
+        let paths = reader.match_paths(&PathPattern::new("**")).unwrap();
+        assert_eq!(paths.len(), 1);
+        assert_eq!(paths[0].1.measurement_id(), "s1");
+        let last = reader.last_points("root.sg.d1").unwrap();
+        assert_eq!(last, [("s1".to_string(), 1, Field::Int32(10))]);
+        let chunks: Vec<_> = reader.chunk_metadata_iter().unwrap().collect();
+        assert_eq!(chunks.len(), 2);
+        assert!(chunks.iter().all(|(device, _)| device == "root.sg.d1"));
+        assert_eq!(chunks[0].1.time_range(), (1, 1));
+        assert_eq!(reader.time_range().unwrap(), Some((1, 1)));
+    }
+
+    #[test]
+    fn aligned_nulls_follow_the_value_bitmaps() {
+        // the time column, then s1 with values in rows 0 and 2 and s2 with a value in row 1
+        let file = test_file(&[
+            ("root.sg.d1", "", &[1, 2, 3]),
+            ("root.sg.d1", "s1", &[1, 3]),
+            ("root.sg.d1", "s2", &[2]),
+        ]);
+        let reader = TsFileSearchReader::new(bytes::Bytes::from(file)).unwrap();
+
+        let rows: Vec<_> = reader
+            .stream(&PathPattern::new("root.sg.d1.*"), None)
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                (1, vec![Some(Field::Int32(10)), None]),
+                (2, vec![None, Some(Field::Int32(20))]),
+                (3, vec![Some(Field::Int32(30)), None]),
+            ]
+        );
+
+        let batches: Vec<_> = reader
+            .scan(&PathPattern::new("root.sg.d1.s1"), None, 1)
+            .into_iter()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].times, [1, 3]);
+    }
+
+    #[test]
+    fn empty_value_pages_keep_their_rows_null() {
+        // pages of rows 1-4, 5-8 and 9-10: the middle page of s1 holds no value
+        let times: Vec<_> = (1..=10).collect();
+        let file = test_file(&[
+            ("root.sg.d1", "", &times),
+            ("root.sg.d1", "s1", &[2, 10]),
+            ("root.sg.d1", "s2", &[6]),
+        ]);
+        let reader = TsFileSearchReader::new(bytes::Bytes::from(file)).unwrap();
+
+        let rows: Vec<_> = reader
+            .stream(&PathPattern::new("root.sg.d1.*"), None)
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                (2, vec![Some(Field::Int32(20)), None]),
+                (6, vec![None, Some(Field::Int32(60))]),
+                (10, vec![Some(Field::Int32(100)), None]),
+            ]
+        );
+    }
+
+    #[test]
+    fn read_ahead_serves_chunks_in_any_order() {
+        let file = test_file(&[
+            ("root.sg.d1", "s1", &[1, 2]),
+            ("root.sg.d1", "s1", &[3]),
+            ("root.sg.d1", "s1", &[4, 5]),
+            ("root.sg.d1", "s1", &[6]),
+        ]);
+        let options = ReaderOptions::new().with_read_ahead(true);
+        let reader = TsFileSearchReader::with_options(bytes::Bytes::from(file), options).unwrap();
+        let s1 = reader
+            .get_sensor_reader("root.sg.d1", "s1")
+            .unwrap()
+            .unwrap();
+
+        let times = |i| -> Vec<i64> {
+            s1.get_chunk_reader(i)
+                .unwrap()
+                .flat_map(|page| page.unwrap().batch().unwrap().0)
+                .collect()
+        };
+        // chunk 2 skips the one read ahead, chunk 0 is read behind it
+        assert_eq!(times(0), [1, 2]);
+        assert_eq!(times(2), [4, 5]);
+        assert_eq!(times(3), [6]);
+        assert_eq!(times(0), [1, 2]);
+        assert_eq!(times(1), [3]);
+    }
+
+    #[test]
+    fn clones_share_the_metadata_across_threads() {
+        let file = test_file(&[("root.sg.d1", "s1", &[1]), ("root.sg.d2", "s1", &[2])]);
+        let reader = TsFileSearchReader::new(bytes::Bytes::from(file)).unwrap();
+
+        let handles: Vec<_> = (0..4)
+            .map(|i| {
+                let handle = match i % 2 {
+                    0 => reader.clone(),
+                    _ => reader.try_clone().unwrap(),
+                };
+                assert!(Arc::ptr_eq(&handle.metadata, &reader.metadata));
+                thread::spawn(move || {
+                    handle
+                        .stream(&PathPattern::new("**"), None)
+                        .unwrap()
+                        .map(Result::unwrap)
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        for handle in handles {
+            assert_eq!(
+                handle.join().unwrap(),
+                [
+                    (1, vec![Some(Field::Int32(10)), None]),
+                    (2, vec![None, Some(Field::Int32(20))]),
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn sensor_lookup_reads_only_its_timeseries() {
+        let file = test_file(&[
+            ("root.sg.d1", "s1", &[1]),
+            ("root.sg.d1", "s2", &[1]),
+            ("root.sg.d1", "s3", &[1]),
+        ]);
+        let reader = TsFileSearchReader::new(bytes::Bytes::from(file)).unwrap();
+
+        let s2 = reader
+            .get_sensor_reader("root.sg.d1", "s2")
+            .unwrap()
+            .unwrap();
+        let offsets: Vec<_> = s2
+            .metadata()
+            .iter()
+            .map(|c| c.offset_chunk_header())
+            .collect();
+        assert_eq!(offsets, [43]);
+        // bounded by the chunk of s3, the next of the file
+        assert_eq!(s2.metadata()[0].max_size(), Some(36));
+        assert!(reader
+            .get_sensor_reader("root.sg.d1", "s0")
+            .unwrap()
+            .is_none());
+        assert!(reader
+            .get_sensor_reader("root.sg.d1", "s22")
+            .unwrap()
+            .is_none());
+
+        let pattern = PathPattern::sensors("root.sg.d1", &["s3", "s1"]);
+        let paths = reader.match_paths(&pattern).unwrap();
+        let sensors: Vec<_> = paths.iter().map(|(_, t)| t.measurement_id()).collect();
+        assert_eq!(sensors, ["s1", "s3"]);
+        let rows: Vec<_> = reader
+            .stream(&PathPattern::sensors("root.sg.d1", &["s2"]), None)
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(rows, [(1, vec![Some(Field::Int32(10))])]);
+    }
+}
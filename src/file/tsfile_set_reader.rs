@@ -0,0 +1,248 @@
+use std::collections::BTreeSet;
+#[cfg(feature = "fs")]
+use std::fs;
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+#[cfg(feature = "fs")]
+use snafu::ResultExt;
+use snafu::Snafu;
+
+use crate::error;
+#[cfg(feature = "fs")]
+use crate::file::options::ReaderOptions;
+use crate::file::options::TimestampPrecision;
+#[cfg(feature = "fs")]
+use crate::file::reader::BufferedFile;
+use crate::file::reader::{FileReader, SectionReader};
+use crate::file::tsfile_search_reader::{self, TsFileSearchReader};
+use crate::query::filter::Filter;
+use crate::query::merge::{DuplicatePolicy, MergedPoints};
+use crate::query::rows::{DeviceRows, SeriesPoints};
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[cfg(feature = "fs")]
+    #[snafu(display("Unable to list directory {}: {}", path, source))]
+    ReadDir {
+        path: String,
+        source: std::io::Error,
+    },
+    #[snafu(display("Unable to open {}: {}", path, source))]
+    OpenFile {
+        path: String,
+        source: tsfile_search_reader::Error,
+    },
+}
+
+#[cfg(feature = "fs")]
+type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Reads the TsFiles of one storage group as a whole, like the sequence and unsequence
+/// files IoTDB keeps in its data directories. The files are ordered from the earliest
+/// written to the latest, unsequence files after sequence files, and where a series has
+/// points at the same time in several files the `DuplicatePolicy` picks the value.
+pub struct TsFileSetReader<R: SectionReader> {
+    files: Vec<TsFileSearchReader<R>>,
+    unsequence: Vec<TsFileSearchReader<R>>,
+    policy: DuplicatePolicy,
+}
+
+impl<R: 'static + SectionReader + Send + Sync> TsFileSetReader<R> {
+    /// A set of opened sequence files, ordered from the earliest written to the latest.
+    pub fn new(files: Vec<TsFileSearchReader<R>>) -> Self {
+        Self {
+            files,
+            unsequence: Vec::new(),
+            policy: DuplicatePolicy::default(),
+        }
+    }
+
+    /// Adds unsequence files, whose time ranges may overlap those of the other files,
+    /// ordered from the earliest written to the latest.
+    pub fn with_unsequence(mut self, files: Vec<TsFileSearchReader<R>>) -> Self {
+        self.unsequence = files;
+        self
+    }
+
+    pub fn with_duplicate_policy(mut self, policy: DuplicatePolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn files(&self) -> &[TsFileSearchReader<R>] {
+        &self.files
+    }
+
+    pub fn unsequence_files(&self) -> &[TsFileSearchReader<R>] {
+        &self.unsequence
+    }
+
+    /// All files in write order.
+    fn all_files(&self) -> impl Iterator<Item = &TsFileSearchReader<R>> {
+        self.files.iter().chain(&self.unsequence)
+    }
+
+    /// Ids of the devices of all files, sorted.
+    pub fn devices(&self) -> error::Result<Vec<String>> {
+        let mut devices = BTreeSet::new();
+        for file in self.all_files() {
+            devices.extend(file.devices()?);
+        }
+        Ok(devices.into_iter().collect())
+    }
+
+    /// `(start_time, end_time)` of all series of all files, both inclusive.
+    pub fn time_range(&self) -> error::Result<Option<(i64, i64)>> {
+        let mut range: Option<(i64, i64)> = None;
+        for file in self.all_files() {
+            if let Some((start, end)) = file.time_range()? {
+                range = Some(match range {
+                    Some((min, max)) => (min.min(start), max.max(end)),
+                    None => (start, end),
+                });
+            }
+        }
+        Ok(range)
+    }
+
+    /// The unit of the timestamps, that of the first file.
+    pub fn timestamp_precision(&self) -> TimestampPrecision {
+        self.files
+            .first()
+            .map(|file| file.timestamp_precision())
+            .unwrap_or_default()
+    }
+
+    /// The points of `device.sensor` in all files matching `filter`, in time order. Files
+    /// without the series are not read.
+    pub fn series(
+        &self,
+        device: &str,
+        sensor: &str,
+        filter: Option<&Filter>,
+    ) -> error::Result<SeriesPoints> {
+        let mut sources = Vec::new();
+        for file in self.all_files() {
+            if let Some(mut reader) = file.get_sensor_reader(device, sensor)? {
+                if let Some(filter) = filter {
+                    reader.set_filter(filter.clone());
+                }
+                sources.push(SeriesPoints::new(Some(reader)));
+            }
+        }
+        let points = MergedPoints::new(sources).with_policy(self.policy);
+        Ok(SeriesPoints::from_points(Box::new(points)))
+    }
+
+    /// The rows of `sensors` of `device` in all files, see `DeviceRows`.
+    pub fn rows(
+        &self,
+        device: &str,
+        sensors: Vec<String>,
+        filter: Option<&Filter>,
+    ) -> error::Result<DeviceRows> {
+        let series = sensors
+            .iter()
+            .map(|sensor| self.series(device, sensor, filter))
+            .collect::<error::Result<_>>()?;
+        Ok(DeviceRows::from_series(
+            sensors,
+            series,
+            self.timestamp_precision(),
+        ))
+    }
+}
+
+#[cfg(feature = "fs")]
+impl TsFileSetReader<BufferedFile> {
+    /// Opens the `.tsfile` files of `dir` as sequence files, see `tsfiles_of` for their
+    /// order.
+    pub fn open_dir(dir: impl AsRef<Path>, options: ReaderOptions) -> Result<Self> {
+        Self::open_files(tsfiles_of(dir.as_ref())?, options)
+    }
+
+    /// Opens the files of a sequence and an unsequence directory of the same storage
+    /// group, e.g. `data/sequence/root.sg/0/0` and `data/unsequence/root.sg/0/0`.
+    pub fn open_dirs(
+        sequence: impl AsRef<Path>,
+        unsequence: impl AsRef<Path>,
+        options: ReaderOptions,
+    ) -> Result<Self> {
+        let unsequence = Self::open_files(tsfiles_of(unsequence.as_ref())?, options.clone())?;
+        Ok(Self::open_dir(sequence, options)?.with_unsequence(unsequence.files))
+    }
+
+    /// Opens `paths`, ordered from the earliest written to the latest.
+    pub fn open_files<P: AsRef<Path>>(
+        paths: impl IntoIterator<Item = P>,
+        options: ReaderOptions,
+    ) -> Result<Self> {
+        let files = paths
+            .into_iter()
+            .map(|path| {
+                let path = path.as_ref();
+                TsFileSearchReader::open(path, options.clone()).context(OpenFile {
+                    path: path.display().to_string(),
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self::new(files))
+    }
+}
+
+/// The `.tsfile` files of `dir`, ordered by the creation time and version of their IoTDB
+/// names, `{time}-{version}-{compactions}.tsfile`. Other names sort first.
+#[cfg(feature = "fs")]
+fn tsfiles_of(dir: &Path) -> Result<Vec<std::path::PathBuf>> {
+    let read_dir = || ReadDir {
+        path: dir.display().to_string(),
+    };
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(dir).with_context(read_dir)? {
+        let path = entry.with_context(read_dir)?.path();
+        if path.extension().is_some_and(|e| e == "tsfile") {
+            paths.push(path);
+        }
+    }
+    paths.sort_by_cached_key(|path| (write_order(path), path.clone()));
+    Ok(paths)
+}
+
+/// The creation time and version at the start of an IoTDB TsFile name.
+#[cfg(feature = "fs")]
+fn write_order(path: &Path) -> Option<(i64, i64)> {
+    let name = path.file_stem()?.to_str()?;
+    let mut fields = name.split('-').map(|field| field.parse::<i64>().ok());
+    Some((fields.next()??, fields.next()??))
+}
+
+#[cfg(all(test, feature = "fs"))]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn files_are_ordered_by_time_and_version() {
+        let mut paths: Vec<PathBuf> = [
+            "1600000000100-2-0-0.tsfile",
+            "1600000000100-10-0-0.tsfile",
+            "1600000000099-11-0-0.tsfile",
+            "other.tsfile",
+        ]
+        .iter()
+        .map(PathBuf::from)
+        .collect();
+        paths.sort_by_cached_key(|path| (write_order(path), path.clone()));
+        let names: Vec<_> = paths.iter().map(|p| p.to_str().unwrap()).collect();
+        assert_eq!(
+            names,
+            [
+                "other.tsfile",
+                "1600000000099-11-0-0.tsfile",
+                "1600000000100-2-0-0.tsfile",
+                "1600000000100-10-0-0.tsfile"
+            ]
+        );
+    }
+}
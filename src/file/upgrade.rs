@@ -0,0 +1,536 @@
+use std::borrow::Cow;
+use std::io::{self, BufReader, Cursor, Read, Write};
+use std::sync::Arc;
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use snafu::{ensure, ResultExt, Snafu};
+
+use crate::chunk;
+use crate::chunk::reader::{ChunkHeader, CompressionType, DefaultChunkReader, TSEncoding};
+use crate::file::compress;
+use crate::file::compress::{compressor, decompressor};
+use crate::file::metadata::TSDataType;
+use crate::file::metadata_writer;
+use crate::file::metadata_writer::MetadataWriter;
+use crate::file::options::DEFAULT_MAX_ALLOCATION;
+use crate::file::reader;
+use crate::file::reader::SectionReader;
+use crate::file::statistics;
+use crate::file::statistics::Statistic;
+use crate::utils::cursor::{VarIntReader, VarIntWriter};
+use crate::{MAGIC_STRING, VERSION_NUMBER, VERSION_NUMBER_V2};
+
+const CHUNK_GROUP_FOOTER: u8 = 0;
+const CHUNK_HEADER: u8 = 1;
+const SEPARATOR: u8 = 2;
+const VERSION: u8 = 3;
+
+const CHUNK_GROUP_HEADER: u8 = 0;
+const PLAIN: u8 = 0;
+const UNCOMPRESSED: u8 = 0;
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Unable to read the head: {}", source))]
+    ReadHead { source: reader::Error },
+    #[snafu(display("Not a version 2 TsFile, head is {:?}", head))]
+    NotVersion2 { head: String },
+    #[snafu(display("Unable to read data at offset {}: {}", offset, source))]
+    ReadData { offset: u64, source: io::Error },
+    #[snafu(display("Unknown marker {} at offset {}", marker, offset))]
+    UnknownMarker { offset: u64, marker: u8 },
+    #[snafu(display("Length {} at offset {} exceeds the file", len, offset))]
+    InvalidLength { offset: u64, len: i64 },
+    #[snafu(display(
+        "Chunk at offset {} has {} bytes of pages, its header records {}",
+        offset,
+        found,
+        expected
+    ))]
+    ChunkSizeMismatch {
+        offset: u64,
+        expected: i32,
+        found: u64,
+    },
+    #[snafu(display("Chunks at offset {} have no chunk group footer", offset))]
+    MissingFooter { offset: u64 },
+    #[snafu(display("Unable to compress a page of chunk at {}: {}", offset, source))]
+    CompressPage {
+        offset: u64,
+        source: compress::Error,
+    },
+    #[snafu(display("Unable to decompress a page of chunk at {}: {}", offset, source))]
+    DecompressPage {
+        offset: u64,
+        source: compress::Error,
+    },
+    #[snafu(display("Unable to re-encode a page of chunk at {}: {}", offset, source))]
+    ReencodePage { offset: u64, source: io::Error },
+    #[snafu(display("Unable to decode a page of chunk at {}: {}", offset, source))]
+    DecodePage {
+        offset: u64,
+        source: chunk::reader::Error,
+    },
+    #[snafu(display("Page of chunk at {} decodes to values of another type", offset))]
+    MismatchedValues { offset: u64 },
+    #[snafu(display("Unable to write data: {}", source))]
+    WriteData { source: io::Error },
+    #[snafu(display("Unable to write statistics: {}", source))]
+    WriteStatistics { source: statistics::Error },
+    #[snafu(display("Unable to write metadata: {}", source))]
+    WriteMetadata { source: metadata_writer::Error },
+}
+
+type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// What `upgrade_v2` wrote.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpgradeSummary {
+    chunk_groups: usize,
+    chunks: usize,
+    reencoded_chunks: usize,
+}
+
+impl UpgradeSummary {
+    pub fn chunk_groups(&self) -> usize {
+        self.chunk_groups
+    }
+
+    pub fn chunks(&self) -> usize {
+        self.chunks
+    }
+
+    /// Chunks whose PLAIN INT32 or TEXT values were re-encoded, which are compressed again
+    /// or, for LZO, written uncompressed.
+    pub fn reencoded_chunks(&self) -> usize {
+        self.reencoded_chunks
+    }
+}
+
+/// Rewrites a version 2 file (IoTDB 0.10 and 0.11) as a version 3 file.
+///
+/// The data section is read in file order. Chunk headers, page headers and statistics
+/// are converted, page data is copied as is except for PLAIN INT32 and TEXT values, whose
+/// integers changed from 4 bytes to varints and which are compressed again. Statistics
+/// are recomputed from the decoded points and the metadata is rebuilt with
+/// `MetadataWriter`; versions and the old metadata are dropped.
+pub fn upgrade_v2<R: SectionReader, W: Write>(
+    reader: R,
+    mut out: W,
+) -> Result<(W, UpgradeSummary)> {
+    let len = reader.len();
+    let head_len = (MAGIC_STRING.len() + VERSION_NUMBER_V2.len()) as u64;
+    let head = reader
+        .get_cursor(0, head_len.min(len) as usize)
+        .context(ReadHead)?
+        .into_inner();
+    let head = String::from_utf8_lossy(&head).to_string();
+    ensure!(
+        head == format!("{}{}", MAGIC_STRING, VERSION_NUMBER_V2),
+        NotVersion2 { head }
+    );
+
+    let mut input = Input {
+        read: BufReader::new(reader.get_read(head_len, (len - head_len) as usize)),
+        position: head_len,
+        len,
+    };
+    out.write_all(MAGIC_STRING.as_bytes()).context(WriteData)?;
+    out.write_u8(VERSION_NUMBER).context(WriteData)?;
+    let mut position = MAGIC_STRING.len() as u64 + 1;
+
+    let mut writer = MetadataWriter::new();
+    let mut summary = UpgradeSummary {
+        chunk_groups: 0,
+        chunks: 0,
+        reencoded_chunks: 0,
+    };
+    // the device of a v2 chunk group comes after its chunks, in the footer
+    let mut chunks = Vec::new();
+    loop {
+        let offset = input.position;
+        match input.read_u8()? {
+            CHUNK_HEADER => chunks.push(ChunkV2::read(&mut input, offset)?),
+            CHUNK_GROUP_FOOTER => {
+                let device = input.read_string()?;
+                input.read_i64()?;
+                input.read_i32()?;
+
+                let mut group = vec![CHUNK_GROUP_HEADER];
+                write_string(&mut group, &device)?;
+                let mut group_chunks = 0;
+                for chunk in chunks.drain(..) {
+                    let measurement = chunk.measurement.clone();
+                    let data_type = chunk.data_type.clone();
+                    summary.reencoded_chunks += chunk.needs_reencoding() as usize;
+                    if let Some((bytes, statistic)) = chunk.rewrite()? {
+                        let chunk_offset = position + group.len() as u64;
+                        group.extend(bytes);
+                        writer
+                            .add_chunk(
+                                &device,
+                                &measurement,
+                                &data_type,
+                                chunk_offset as i64,
+                                statistic,
+                            )
+                            .context(WriteMetadata)?;
+                        group_chunks += 1;
+                    }
+                }
+                if group_chunks > 0 {
+                    out.write_all(&group).context(WriteData)?;
+                    position += group.len() as u64;
+                    summary.chunk_groups += 1;
+                    summary.chunks += group_chunks;
+                }
+            }
+            VERSION => {
+                input.read_i64()?;
+            }
+            SEPARATOR => break,
+            marker => return UnknownMarker { offset, marker }.fail(),
+        }
+    }
+    if let Some(chunk) = chunks.first() {
+        return MissingFooter {
+            offset: chunk.offset,
+        }
+        .fail();
+    }
+
+    let out = writer.finish(position, out).context(WriteMetadata)?;
+    Ok((out, summary))
+}
+
+/// The data section being read, with the file offset of the next byte.
+struct Input<R> {
+    read: R,
+    position: u64,
+    len: u64,
+}
+
+impl<R: Read> Input<R> {
+    fn read<T>(&mut self, n: u64, f: impl FnOnce(&mut R) -> io::Result<T>) -> Result<T> {
+        let value = f(&mut self.read).context(ReadData {
+            offset: self.position,
+        })?;
+        self.position += n;
+        Ok(value)
+    }
+
+    fn read_u8(&mut self) -> Result<u8> {
+        self.read(1, |r| r.read_u8())
+    }
+
+    fn read_i16(&mut self) -> Result<i16> {
+        self.read(2, |r| r.read_i16::<BigEndian>())
+    }
+
+    fn read_i32(&mut self) -> Result<i32> {
+        self.read(4, |r| r.read_i32::<BigEndian>())
+    }
+
+    fn read_i64(&mut self) -> Result<i64> {
+        self.read(8, |r| r.read_i64::<BigEndian>())
+    }
+
+    fn read_bytes(&mut self, len: i64) -> Result<Vec<u8>> {
+        ensure!(
+            len >= 0 && len as u64 <= self.len - self.position,
+            InvalidLength {
+                offset: self.position,
+                len
+            }
+        );
+        let mut bytes = vec![0; len as usize];
+        self.read(len as u64, |r| r.read_exact(&mut bytes))?;
+        Ok(bytes)
+    }
+
+    /// A string as its 4 byte length and UTF-8 bytes.
+    fn read_string(&mut self) -> Result<String> {
+        let len = self.read_i32()?;
+        let bytes = self.read_bytes(len as i64)?;
+        Ok(String::from_utf8_lossy(&bytes).to_string())
+    }
+}
+
+/// A chunk as written by version 2, with its compressed pages.
+struct ChunkV2 {
+    offset: u64,
+    measurement: String,
+    data_type: TSDataType,
+    compression: u8,
+    encoding: u8,
+    pages: Vec<(u32, Vec<u8>)>,
+}
+
+impl ChunkV2 {
+    /// Reads a chunk whose marker at `offset` has been read.
+    fn read<R: Read>(input: &mut Input<R>, offset: u64) -> Result<Self> {
+        let measurement = input.read_string()?;
+        let data_size = input.read_i32()?;
+        let data_type = TSDataType::new(input.read_i16()? as u8);
+        let num_pages = input.read_i32()?;
+        let compression = input.read_i16()? as u8;
+        let encoding = input.read_i16()? as u8;
+
+        let start = input.position;
+        let mut pages = Vec::new();
+        for _ in 0..num_pages {
+            let size_offset = input.position;
+            let uncompressed_size = input.read_i32()?;
+            ensure!(
+                uncompressed_size >= 0 && uncompressed_size as usize <= DEFAULT_MAX_ALLOCATION,
+                InvalidLength {
+                    offset: size_offset,
+                    len: uncompressed_size as i64
+                }
+            );
+            let compressed_size = input.read_i32()?;
+            skip_statistics(input, &data_type)?;
+            let data = input.read_bytes(compressed_size as i64)?;
+            pages.push((uncompressed_size as u32, data));
+        }
+        ensure!(
+            input.position - start == data_size as u64,
+            ChunkSizeMismatch {
+                offset,
+                expected: data_size,
+                found: input.position - start,
+            }
+        );
+        Ok(Self {
+            offset,
+            measurement,
+            data_type,
+            compression,
+            encoding,
+            pages,
+        })
+    }
+
+    fn needs_reencoding(&self) -> bool {
+        self.encoding == PLAIN && matches!(self.data_type, TSDataType::Int32 | TSDataType::TEXT)
+    }
+
+    /// The chunk in version 3 layout with its statistics, `None` if it has no points.
+    fn rewrite(self) -> Result<Option<(Vec<u8>, Statistic)>> {
+        let offset = self.offset;
+        let reencode = self.needs_reencoding();
+        let compression_type = CompressionType::new(self.compression);
+        let decompressor = decompressor(&compression_type).context(DecompressPage { offset })?;
+        // re-encoded pages are compressed again where this crate has a compressor
+        let compressor = match reencode {
+            true => compressor(&compression_type).ok(),
+            false => None,
+        };
+        let compression = match (reencode, &compressor) {
+            (true, None) => UNCOMPRESSED,
+            _ => self.compression,
+        };
+
+        let mut body = Vec::new();
+        let mut statistic = Statistic::empty(&self.data_type);
+        for (uncompressed_size, data) in &self.pages {
+            let page = decompressor
+                .decompress(data, *uncompressed_size as usize)
+                .context(DecompressPage { offset })?;
+            let page = match reencode {
+                true => reencode_plain(&page, &self.data_type).context(ReencodePage { offset })?,
+                false => page.into_owned(),
+            };
+            let page_statistic = self.page_statistic(&page)?;
+            if page_statistic.count() == 0 {
+                continue;
+            }
+            let data = match (reencode, &compressor) {
+                (true, Some(compressor)) => compressor
+                    .compress(&page)
+                    .context(CompressPage { offset })?,
+                (true, None) => Cow::Borrowed(&page[..]),
+                (false, _) => Cow::Borrowed(&data[..]),
+            };
+            body.write_unsigned_varint_32(page.len() as u32)
+                .context(WriteData)?;
+            body.write_unsigned_varint_32(data.len() as u32)
+                .context(WriteData)?;
+            page_statistic
+                .serialize(&mut body)
+                .context(WriteStatistics)?;
+            body.write_all(&data).context(WriteData)?;
+            statistic.merge(&page_statistic).context(WriteStatistics)?;
+        }
+        if statistic.count() == 0 {
+            return Ok(None);
+        }
+
+        let mut chunk = vec![CHUNK_HEADER];
+        write_string(&mut chunk, &self.measurement)?;
+        chunk
+            .write_unsigned_varint_32(body.len() as u32)
+            .context(WriteData)?;
+        chunk.write_u8(self.data_type.int_id()).context(WriteData)?;
+        chunk.write_u8(compression).context(WriteData)?;
+        chunk.write_u8(self.encoding).context(WriteData)?;
+        chunk.extend(body);
+        Ok(Some((chunk, statistic)))
+    }
+
+    /// Decodes a decompressed version 3 page as a single page chunk.
+    fn page_statistic(&self, page: &[u8]) -> Result<Statistic> {
+        let offset = self.offset;
+        let mut data = Vec::with_capacity(page.len() + 10);
+        data.write_unsigned_varint_32(page.len() as u32)
+            .context(WriteData)?;
+        data.write_unsigned_varint_32(page.len() as u32)
+            .context(WriteData)?;
+        data.extend_from_slice(page);
+        let header = ChunkHeader::new(
+            5,
+            self.measurement.clone(),
+            data.len() as u32,
+            self.data_type.clone(),
+            CompressionType::Uncompressed,
+            TSEncoding::new(self.encoding),
+        );
+        let mut statistic = Statistic::empty(&self.data_type);
+        let pages = DefaultChunkReader::new(
+            Cursor::new(data),
+            header,
+            Arc::new(statistic.clone()),
+            None,
+            DEFAULT_MAX_ALLOCATION,
+        )
+        .context(DecodePage { offset })?;
+        for page in pages {
+            let (times, values) = page
+                .and_then(|page| page.batch())
+                .context(DecodePage { offset })?;
+            ensure!(
+                statistic.update_batch(&times, &values),
+                MismatchedValues { offset }
+            );
+        }
+        Ok(statistic)
+    }
+}
+
+/// Skips the statistics of a version 2 page header: count, start and end time, then the
+/// values of the data type.
+fn skip_statistics<R: Read>(input: &mut Input<R>, data_type: &TSDataType) -> Result<()> {
+    let values = match data_type {
+        TSDataType::Boolean => 2,
+        TSDataType::Int32 | TSDataType::FLOAT => 4 * 4 + 8,
+        TSDataType::Int64 | TSDataType::DOUBLE => 5 * 8,
+        TSDataType::TEXT | TSDataType::VECTOR => 0,
+    };
+    input.read_bytes(3 * 8 + values)?;
+    if let TSDataType::TEXT = data_type {
+        // first and last value
+        for _ in 0..2 {
+            let len = input.read_i32()?;
+            input.read_bytes(len as i64)?;
+        }
+    }
+    Ok(())
+}
+
+/// Rewrites the values of a decompressed PLAIN page from 4 byte big endian integers and
+/// text lengths to zigzag varints; the time column is kept.
+fn reencode_plain(page: &[u8], data_type: &TSDataType) -> io::Result<Vec<u8>> {
+    let mut data = Cursor::new(page);
+    let time_len = data.read_unsigned_varint_32()? as usize;
+    let time_end = data.position() as usize + time_len;
+    if time_end > page.len() {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    data.set_position(time_end as u64);
+
+    let mut out = page[..time_end].to_vec();
+    while (data.position() as usize) < page.len() {
+        let value = data.read_i32::<BigEndian>()?;
+        out.write_signed_varint_32(value)?;
+        if let TSDataType::TEXT = data_type {
+            let start = data.position() as usize;
+            let end = start.saturating_add(value.max(0) as usize);
+            if value < 0 || end > page.len() {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            out.extend_from_slice(&page[start..end]);
+            data.set_position(end as u64);
+        }
+    }
+    Ok(out)
+}
+
+/// A string as its zigzag varint length and UTF-8 bytes.
+fn write_string(out: &mut Vec<u8>, value: &str) -> Result<()> {
+    out.write_signed_varint_32(value.len() as i32)
+        .context(WriteData)?;
+    out.write_all(value.as_bytes()).context(WriteData)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file::reader::FileReader;
+    use crate::file::tsfile_search_reader::TsFileSearchReader;
+
+    #[test]
+    fn empty_file_upgrades() {
+        let mut v2 = format!("{}{}", MAGIC_STRING, VERSION_NUMBER_V2).into_bytes();
+        v2.extend([VERSION, 0, 0, 0, 0, 0, 0, 0, 1, SEPARATOR]);
+        let (file, summary) = upgrade_v2(bytes::Bytes::from(v2), Vec::new()).unwrap();
+        assert_eq!(summary.chunks(), 0);
+        let reader = TsFileSearchReader::new(bytes::Bytes::from(file)).unwrap();
+        assert!(reader.devices().unwrap().is_empty());
+    }
+
+    #[test]
+    fn plain_values_are_reencoded() {
+        let mut page = vec![0];
+        for value in [1_i32, -2, 300] {
+            page.extend(value.to_be_bytes());
+        }
+        let page = reencode_plain(&page, &TSDataType::Int32).unwrap();
+        assert_eq!(page, [0, 2, 3, 216, 4]);
+
+        let mut page = vec![0];
+        page.extend(2_i32.to_be_bytes());
+        page.extend(b"ab");
+        let page = reencode_plain(&page, &TSDataType::TEXT).unwrap();
+        assert_eq!(page, [0, 4, b'a', b'b']);
+    }
+
+    #[test]
+    fn reencoded_pages_keep_their_compression() {
+        // one point at time 5: an empty TS_2DIFF block with only its first time
+        let mut time = [0_i32, 0].map(i32::to_be_bytes).concat();
+        time.extend([0_i64, 5].map(i64::to_be_bytes).concat());
+        let mut page = Vec::new();
+        page.write_unsigned_varint_32(time.len() as u32).unwrap();
+        page.extend(&time);
+        page.extend(42_i32.to_be_bytes());
+        let snappy = snap::raw::Encoder::new().compress_vec(&page).unwrap();
+
+        let chunk = ChunkV2 {
+            offset: 0,
+            measurement: "s1".to_string(),
+            data_type: TSDataType::Int32,
+            compression: 1,
+            encoding: PLAIN,
+            pages: vec![(page.len() as u32, snappy)],
+        };
+        let (chunk, statistic) = chunk.rewrite().unwrap().unwrap();
+        assert_eq!(statistic.count(), 1);
+
+        let reencoded = reencode_plain(&page, &TSDataType::Int32).unwrap();
+        let compressed = snap::raw::Encoder::new().compress_vec(&reencoded).unwrap();
+        // marker, measurement, body length, then data type, compression and encoding
+        let header_len = 1 + 3 + 1;
+        assert_eq!(chunk[header_len..header_len + 3], [1, 1, PLAIN]);
+        assert!(chunk.ends_with(&compressed));
+    }
+}
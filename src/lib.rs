@@ -1,19 +1,32 @@
 const MAGIC_STRING: &str = "TsFile";
 const VERSION_NUMBER_V2: &str = "000002";
 const VERSION_NUMBER: u8 = 0x03;
+const VERSION_NUMBER_V4: u8 = 0x04;
 const FOOTER_SIZE: usize = 10;
 
-#[macro_use]
-pub mod chunk;
-pub mod encoding;
-pub mod file;
-pub mod utils;
+/// A `tracing` debug event when the `tracing` feature is enabled, nothing otherwise.
+macro_rules! trace_event {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::debug!($($arg)*);
+    };
+}
 
-mod tests {
-    use crate::file::metadata::MetadataIndexNodeType::{InternalDevice, LeafDevice};
+pub(crate) mod chunk;
+pub(crate) mod convert;
+pub(crate) mod encoding;
+pub mod error;
+pub(crate) mod export;
+pub(crate) mod file;
+pub mod prelude;
+pub(crate) mod query;
+pub(crate) mod utils;
 
+mod tests {
+    #[cfg(feature = "fs")]
     #[test]
     fn it_works() {
+        use crate::file::metadata::MetadataIndexNodeType::{InternalDevice, LeafDevice};
         use crate::file::reader::FileReader;
         use crate::file::tsfile_search_reader::TsFileSearchReader;
 
@@ -22,15 +35,19 @@ mod tests {
             Ok(reader) => {
                 let device_meta = reader.device_meta_iter();
                 device_meta.for_each(|meta| match meta {
-                    InternalDevice(f) | LeafDevice(f) => {
-                        let device_name = f.children().first().expect("123").name();
+                    Ok(InternalDevice(f)) | Ok(LeafDevice(f)) => {
+                        let device_name = f
+                            .children()
+                            .first()
+                            .expect("device node has children")
+                            .name();
                         let sensors = reader.sensor_meta_iter(device_name);
-                        sensors.for_each(|s| {
-                            if let Some(option) =
+                        sensors.flatten().for_each(|s| {
+                            if let Ok(Some(option)) =
                                 reader.get_sensor_reader(device_name, s.measurement_id())
                             {
                                 if let Ok(x) = option.get_chunk_reader(0) {
-                                    x.for_each(|y| println!("{:?}", y.data()));
+                                    x.for_each(|y| println!("{:?}", y.map(|y| y.data())));
                                 }
                             }
                         })
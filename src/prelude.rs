@@ -0,0 +1,63 @@
+//! The public surface of the crate.
+//!
+//! `use tsfile::prelude::*;` brings in the readers, the metadata model, queries, exports,
+//! the upgrade and repair tools and the error types callers are expected to handle. It is
+//! the only public path to them: everything else (decoders, IO helpers, chunk parsing,
+//! metadata writing) is an implementation detail and may change between releases.
+
+pub use crate::chunk::reader::{
+    ChunkHeader, CompressionType, Error as ChunkError, PageHeader, TSEncoding,
+};
+#[cfg(feature = "parquet")]
+pub use crate::convert::to_parquet::{
+    to_parquet, write_device, write_sensors, Error as ParquetError,
+};
+pub use crate::encoding::decoder::{ColumnBatch, Decoder, Error as DecodeError, Field};
+pub use crate::error::{ErrorContext, Location, TsFileError};
+#[cfg(feature = "arrow")]
+pub use crate::export::arrow_ipc::{write_arrow_ipc, Error as ArrowIpcError, IpcFormat};
+pub use crate::export::csv::{write_csv, CsvOptions, Error as CsvError, Layout, TimeFormat};
+pub use crate::export::line_protocol::{write_line_protocol, Error as LineProtocolError};
+#[cfg(all(feature = "async", feature = "fs"))]
+pub use crate::file::async_reader::AsyncFile;
+#[cfg(feature = "async")]
+pub use crate::file::async_reader::{
+    AsyncSectionReader, AsyncTsFileReader, Error as AsyncReaderError,
+};
+#[cfg(feature = "zstd")]
+pub use crate::file::compress::ZstdCompressor;
+pub use crate::file::compress::{
+    compressor, decompressor, Compressor, Decompressor, Error as CompressError,
+};
+pub use crate::file::index::{IndexVisitor, TreePrinter};
+pub use crate::file::metadata::{
+    BloomFilter, ChunkMetadata, ColumnCategory, ColumnSchema, DeviceSchema, FileMeta,
+    MetaDataIndexNode, MetadataIndexEntry, MetadataIndexNodeType, TSDataType, TableSchema,
+    TimeseriesMetadata, TimeseriesMetadataType, TsFileMetadata,
+};
+#[cfg(feature = "fs")]
+pub use crate::file::mods::read_mods;
+pub use crate::file::mods::{parse_mods, Deletion, Error as ModsError, ModsHandling};
+#[cfg(feature = "object-store")]
+pub use crate::file::object_store_reader::{FetchedRange, ObjectStoreReader};
+pub use crate::file::options::{ParseMode, ReaderOptions, TimeOrder, TimestampPrecision};
+pub use crate::file::path::PathPattern;
+#[cfg(feature = "fs")]
+pub use crate::file::reader::BufferedFile;
+pub use crate::file::reader::{
+    ChunkReader, DeviceMetadataIter, DeviceReader, Error as ReadError, FileReader, Length,
+    PageReader, SectionReader, SensorMetadataIter, SensorReader, SeriesBatch, TryClone,
+};
+pub use crate::file::registry::{register_decoder, register_decompressor, DecoderFactory};
+pub use crate::file::repair::{repair, Error as RepairError, RepairSummary};
+pub use crate::file::scanner::{Error as ScannerError, ScanItem, TsFileScanner};
+pub use crate::file::statistics::{Statistic, StatisticHeader, Statistics};
+pub use crate::file::summary::{FileSummary, SeriesSummary};
+pub use crate::file::tsfile_search_reader::{Error as ReaderError, TsFileSearchReader};
+pub use crate::file::tsfile_set_reader::{Error as SetReaderError, TsFileSetReader};
+pub use crate::file::upgrade::{upgrade_v2, Error as UpgradeError, UpgradeSummary};
+pub use crate::query::filter::{Comparison, Filter};
+pub use crate::query::merge::{DuplicatePolicy, MergedPoints};
+pub use crate::query::parser::{Error as QueryError, Predicate, Query, QueryRows};
+pub use crate::query::rows::{DeviceRows, Row, RowChannel, SeriesPoints};
+pub use crate::utils::pool::BufferPool;
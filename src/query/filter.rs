@@ -0,0 +1,214 @@
+use std::cmp::Ordering;
+use std::ops::Not;
+
+use crate::encoding::decoder::Field;
+use crate::file::statistics::Statistic;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    Gt,
+    GtEq,
+    Lt,
+    LtEq,
+    Eq,
+    NotEq,
+}
+
+/// A predicate over `(time, value)` points of one timeseries.
+///
+/// A filter is checked against chunk and page statistics to skip data that cannot match,
+/// and against every decoded point.
+#[derive(Debug, Clone)]
+pub enum Filter {
+    Time(Comparison, i64),
+    Value(Comparison, Field),
+    And(Box<Filter>, Box<Filter>),
+    Or(Box<Filter>, Box<Filter>),
+    Not(Box<Filter>),
+}
+
+impl Filter {
+    pub fn time_gt(time: i64) -> Self {
+        Filter::Time(Comparison::Gt, time)
+    }
+
+    pub fn time_gt_eq(time: i64) -> Self {
+        Filter::Time(Comparison::GtEq, time)
+    }
+
+    pub fn time_lt(time: i64) -> Self {
+        Filter::Time(Comparison::Lt, time)
+    }
+
+    pub fn time_lt_eq(time: i64) -> Self {
+        Filter::Time(Comparison::LtEq, time)
+    }
+
+    pub fn time_eq(time: i64) -> Self {
+        Filter::Time(Comparison::Eq, time)
+    }
+
+    /// `start <= time < end`
+    pub fn time_between(start: i64, end: i64) -> Self {
+        Self::time_gt_eq(start).and(Self::time_lt(end))
+    }
+
+    pub fn value_gt<V: Into<Field>>(value: V) -> Self {
+        Filter::Value(Comparison::Gt, value.into())
+    }
+
+    pub fn value_gt_eq<V: Into<Field>>(value: V) -> Self {
+        Filter::Value(Comparison::GtEq, value.into())
+    }
+
+    pub fn value_lt<V: Into<Field>>(value: V) -> Self {
+        Filter::Value(Comparison::Lt, value.into())
+    }
+
+    pub fn value_lt_eq<V: Into<Field>>(value: V) -> Self {
+        Filter::Value(Comparison::LtEq, value.into())
+    }
+
+    pub fn value_eq<V: Into<Field>>(value: V) -> Self {
+        Filter::Value(Comparison::Eq, value.into())
+    }
+
+    pub fn value_not_eq<V: Into<Field>>(value: V) -> Self {
+        Filter::Value(Comparison::NotEq, value.into())
+    }
+
+    pub fn and(self, other: Filter) -> Self {
+        Filter::And(Box::new(self), Box::new(other))
+    }
+
+    pub fn or(self, other: Filter) -> Self {
+        Filter::Or(Box::new(self), Box::new(other))
+    }
+
+    /// Whether a point may match.
+    pub fn satisfy(&self, time: i64, value: &Field) -> bool {
+        match self {
+            Filter::Time(op, t) => compare(op, Some(time.cmp(t))),
+            Filter::Value(op, v) => compare(op, compare_field(value, v)),
+            Filter::And(l, r) => l.satisfy(time, value) && r.satisfy(time, value),
+            Filter::Or(l, r) => l.satisfy(time, value) || r.satisfy(time, value),
+            Filter::Not(f) => !f.satisfy(time, value),
+        }
+    }
+
+    /// Whether a time matches, for filters on the time alone; value comparisons never match.
+    pub fn satisfy_time(&self, time: i64) -> bool {
+        match self {
+            Filter::Time(op, t) => compare(op, Some(time.cmp(t))),
+            Filter::Value(_, _) => false,
+            Filter::And(l, r) => l.satisfy_time(time) && r.satisfy_time(time),
+            Filter::Or(l, r) => l.satisfy_time(time) || r.satisfy_time(time),
+            Filter::Not(f) => !f.satisfy_time(time),
+        }
+    }
+
+    /// Whether some point summarized by `statistic` may match. `false` means the chunk or
+    /// page can be skipped without reading it.
+    pub fn may_satisfy(&self, statistic: &Statistic) -> bool {
+        match self {
+            Filter::Time(op, t) => may_compare(
+                op,
+                Some(statistic.start_time().cmp(t)),
+                Some(statistic.end_time().cmp(t)),
+            ),
+            Filter::Value(op, v) => match statistic.value_range() {
+                Some((min, max)) => may_compare(op, compare_field(&min, v), compare_field(&max, v)),
+                None => true,
+            },
+            Filter::And(l, r) => l.may_satisfy(statistic) && r.may_satisfy(statistic),
+            Filter::Or(l, r) => l.may_satisfy(statistic) || r.may_satisfy(statistic),
+            // statistics only bound the data, a negated bound prunes nothing
+            Filter::Not(_) => true,
+        }
+    }
+}
+
+impl Not for Filter {
+    type Output = Filter;
+
+    fn not(self) -> Self::Output {
+        Filter::Not(Box::new(self))
+    }
+}
+
+fn compare(op: &Comparison, ordering: Option<Ordering>) -> bool {
+    match ordering {
+        None => false,
+        Some(o) => match op {
+            Comparison::Gt => o == Ordering::Greater,
+            Comparison::GtEq => o != Ordering::Less,
+            Comparison::Lt => o == Ordering::Less,
+            Comparison::LtEq => o != Ordering::Greater,
+            Comparison::Eq => o == Ordering::Equal,
+            Comparison::NotEq => o != Ordering::Equal,
+        },
+    }
+}
+
+/// `min` and `max` are the orderings of the smallest and largest summarized value against
+/// the operand.
+fn may_compare(op: &Comparison, min: Option<Ordering>, max: Option<Ordering>) -> bool {
+    match (min, max) {
+        (Some(min), Some(max)) => match op {
+            Comparison::Gt | Comparison::GtEq => compare(op, Some(max)),
+            Comparison::Lt | Comparison::LtEq => compare(op, Some(min)),
+            Comparison::Eq => min != Ordering::Greater && max != Ordering::Less,
+            Comparison::NotEq => !(min == Ordering::Equal && max == Ordering::Equal),
+        },
+        _ => true,
+    }
+}
+
+fn compare_field(a: &Field, b: &Field) -> Option<Ordering> {
+    match (a, b) {
+        (Field::Boolean(a), Field::Boolean(b)) => Some(a.cmp(b)),
+        (Field::TEXT(a), Field::TEXT(b)) => Some(a.cmp(b)),
+        // integers compare exactly, as f64 rounds those above 2^53
+        (Field::Int32(_) | Field::Int64(_), Field::Int32(_) | Field::Int64(_)) => {
+            Some(integer(a)?.cmp(&integer(b)?))
+        }
+        (a, b) => a.as_f64()?.partial_cmp(&b.as_f64()?),
+    }
+}
+
+fn integer(field: &Field) -> Option<i64> {
+    match field {
+        Field::Int32(v) => Some(*v as i64),
+        Field::Int64(v) => Some(*v),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Filter;
+    use crate::encoding::decoder::Field;
+
+    #[test]
+    fn satisfy_points() {
+        let f = Filter::time_between(10, 20).and(Filter::value_gt(1.5));
+        assert!(f.satisfy(10, &Field::DOUBLE(2.0)));
+        assert!(f.satisfy(15, &Field::Int32(2)));
+        assert!(!f.satisfy(20, &Field::DOUBLE(2.0)));
+        assert!(!f.satisfy(15, &Field::DOUBLE(1.5)));
+        assert!(!f.satisfy(15, &Field::TEXT(b"2".to_vec())));
+        assert!((!Filter::time_eq(3))
+            .or(Filter::value_eq(true))
+            .satisfy(3, &Field::Boolean(true)));
+    }
+
+    #[test]
+    fn integers_compare_exactly() {
+        // 2^53 + 1 and 2^53 are the same f64
+        let f = Filter::value_gt(Field::Int64(9007199254740992));
+        assert!(f.satisfy(1, &Field::Int64(9007199254740993)));
+        assert!(!Filter::value_eq(Field::Int64(9007199254740993))
+            .satisfy(1, &Field::Int64(9007199254740992)));
+        assert!(Filter::value_lt(Field::Int64(i64::MAX)).satisfy(1, &Field::Int32(7)));
+    }
+}
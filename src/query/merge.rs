@@ -0,0 +1,99 @@
+use std::iter::Peekable;
+
+use crate::chunk::reader::Error;
+use crate::encoding::decoder::Field;
+use crate::query::rows::SeriesPoints;
+
+type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Which value a timestamp found in several sources of a merge takes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicatePolicy {
+    /// The value written last, as IoTDB returns it: unsequence data overrides sequence data.
+    #[default]
+    LatestWins,
+    /// The value written first.
+    EarliestWins,
+}
+
+/// The points of one timeseries read from several sources, e.g. the files of a set, in
+/// time order. A timestamp found in more than one source is returned once, its value
+/// chosen by the `DuplicatePolicy`.
+pub struct MergedPoints {
+    sources: Vec<Peekable<SeriesPoints>>,
+    policy: DuplicatePolicy,
+}
+
+impl MergedPoints {
+    /// Merges `sources`, ordered from the earliest written to the latest.
+    pub fn new(sources: Vec<SeriesPoints>) -> Self {
+        Self {
+            sources: sources.into_iter().map(Iterator::peekable).collect(),
+            policy: DuplicatePolicy::default(),
+        }
+    }
+
+    pub fn with_policy(mut self, policy: DuplicatePolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+}
+
+impl Iterator for MergedPoints {
+    type Item = Result<(i64, Field)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut time: Option<i64> = None;
+        for source in &mut self.sources {
+            match source.peek() {
+                Some(Ok((t, _))) => time = Some(time.map_or(*t, |time| time.min(*t))),
+                Some(Err(_)) => return source.next(),
+                None => {}
+            }
+        }
+        let time = time?;
+        let mut point = None;
+        for source in &mut self.sources {
+            if matches!(source.peek(), Some(Ok((t, _))) if *t == time) {
+                let next = source.next();
+                if point.is_none() || self.policy == DuplicatePolicy::LatestWins {
+                    point = next;
+                }
+            }
+        }
+        point
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn points(points: &[(i64, i32)]) -> SeriesPoints {
+        let points: Vec<_> = points
+            .iter()
+            .map(|(t, v)| Ok((*t, Field::Int32(*v))))
+            .collect();
+        SeriesPoints::from_points(Box::new(points.into_iter()))
+    }
+
+    #[test]
+    fn duplicate_timestamps_follow_the_policy() {
+        let sources = || vec![points(&[(1, 1), (3, 1), (5, 1)]), points(&[(2, 2), (3, 2)])];
+        let merged: Vec<_> = MergedPoints::new(sources()).map(|p| p.unwrap()).collect();
+        assert_eq!(
+            merged,
+            [
+                (1, Field::Int32(1)),
+                (2, Field::Int32(2)),
+                (3, Field::Int32(2)),
+                (5, Field::Int32(1))
+            ]
+        );
+
+        let merged = MergedPoints::new(sources()).with_policy(DuplicatePolicy::EarliestWins);
+        let values: Vec<_> = merged.map(|p| p.unwrap().1).collect();
+        assert_eq!(values[2], Field::Int32(1));
+        assert_eq!(values.len(), 4);
+    }
+}
@@ -0,0 +1,4 @@
+pub mod filter;
+pub mod merge;
+pub mod parser;
+pub mod rows;
@@ -0,0 +1,546 @@
+use std::collections::{BTreeSet, HashMap};
+
+use snafu::{ensure, OptionExt, Snafu};
+
+use crate::chunk;
+use crate::encoding::decoder::Field;
+use crate::error;
+use crate::file::reader::FileReader;
+use crate::query::filter::{Comparison, Filter};
+use crate::query::rows::DeviceRows;
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Expected {} at position {}, found {}", expected, position, found))]
+    UnexpectedToken {
+        expected: String,
+        found: String,
+        position: usize,
+    },
+    #[snafu(display("Expected {} at the end of the query", expected))]
+    UnexpectedEnd { expected: String },
+    #[snafu(display("Unterminated string starting at position {}", position))]
+    UnterminatedString { position: usize },
+    #[snafu(display("Invalid number {} at position {}", text, position))]
+    InvalidNumber { text: String, position: usize },
+}
+
+type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// A predicate over the rows of a device.
+#[derive(Debug, Clone)]
+pub enum Predicate {
+    /// A filter on the time of a row.
+    Time(Filter),
+    /// A filter on the value of a sensor; rows without a value of the sensor do not match.
+    Sensor(String, Filter),
+    And(Box<Predicate>, Box<Predicate>),
+    Or(Box<Predicate>, Box<Predicate>),
+    Not(Box<Predicate>),
+}
+
+impl Predicate {
+    /// Whether a row matches, `value` giving the value of a sensor in the row.
+    pub fn matches<'a>(&self, time: i64, value: &impl Fn(&str) -> Option<&'a Field>) -> bool {
+        match self {
+            Predicate::Time(filter) => filter.satisfy_time(time),
+            Predicate::Sensor(sensor, filter) => {
+                value(sensor).is_some_and(|v| filter.satisfy(time, v))
+            }
+            Predicate::And(l, r) => l.matches(time, value) && r.matches(time, value),
+            Predicate::Or(l, r) => l.matches(time, value) || r.matches(time, value),
+            Predicate::Not(p) => !p.matches(time, value),
+        }
+    }
+
+    /// A time filter every matching row satisfies, to skip chunks and pages by their
+    /// statistics before the rows are joined.
+    pub fn time_filter(&self) -> Option<Filter> {
+        match self {
+            Predicate::Time(filter) => Some(filter.clone()),
+            Predicate::Sensor(_, _) => None,
+            Predicate::And(l, r) => match (l.time_filter(), r.time_filter()) {
+                (Some(l), Some(r)) => Some(l.and(r)),
+                (l, r) => l.or(r),
+            },
+            Predicate::Or(l, r) => Some(l.time_filter()?.or(r.time_filter()?)),
+            Predicate::Not(p) => match p.as_ref() {
+                Predicate::Time(filter) => Some(!filter.clone()),
+                _ => None,
+            },
+        }
+    }
+
+    /// The sensors the predicate reads, in order of appearance.
+    pub fn sensors(&self) -> Vec<&str> {
+        let mut sensors = Vec::new();
+        self.collect_sensors(&mut sensors);
+        sensors
+    }
+
+    fn collect_sensors<'a>(&'a self, sensors: &mut Vec<&'a str>) {
+        match self {
+            Predicate::Time(_) => {}
+            Predicate::Sensor(sensor, _) => {
+                if !sensors.contains(&sensor.as_str()) {
+                    sensors.push(sensor);
+                }
+            }
+            Predicate::And(l, r) | Predicate::Or(l, r) => {
+                l.collect_sensors(sensors);
+                r.collect_sensors(sensors);
+            }
+            Predicate::Not(p) => p.collect_sensors(sensors),
+        }
+    }
+}
+
+/// A query over one device:
+///
+/// ```text
+/// select s1, s2 from root.sg.d1 where time >= 1637893124311 and (s1 > 30 or not s2 = 'off')
+/// ```
+///
+/// `select *` selects every sensor of the device. Conditions compare `time` or a sensor,
+/// by name or by full path, with an integer, decimal, `true`/`false` or quoted string,
+/// using `=`, `!=`, `<>`, `<`, `<=`, `>` or `>=`, and combine with `and`, `or`, `not` and
+/// parentheses. Keywords are case insensitive.
+#[derive(Debug, Clone)]
+pub struct Query {
+    sensors: Option<Vec<String>>,
+    device: String,
+    predicate: Option<Predicate>,
+}
+
+impl Query {
+    pub fn parse(query: &str) -> Result<Self> {
+        let mut parser = Parser {
+            tokens: tokenize(query)?,
+            next: 0,
+        };
+        parser.keyword("select")?;
+        let sensors = if parser.eat(&Token::Symbol("*")) {
+            None
+        } else {
+            let mut sensors = vec![parser.name("a sensor")?];
+            while parser.eat(&Token::Symbol(",")) {
+                sensors.push(parser.name("a sensor")?);
+            }
+            Some(sensors)
+        };
+        parser.keyword("from")?;
+        let device = parser.name("a device")?;
+        let predicate = if parser.eat_keyword("where") {
+            Some(parser.or(&device)?)
+        } else {
+            None
+        };
+        if let Some((token, position)) = parser.tokens.get(parser.next) {
+            return UnexpectedToken {
+                expected: "the end of the query",
+                found: token.to_string(),
+                position: *position,
+            }
+            .fail();
+        }
+        let sensors = sensors.map(|sensors| {
+            sensors
+                .into_iter()
+                .map(|sensor| relative(&device, sensor))
+                .collect()
+        });
+        Ok(Self {
+            sensors,
+            device,
+            predicate,
+        })
+    }
+
+    /// The selected sensors, `None` for all sensors of the device.
+    pub fn sensors(&self) -> Option<&[String]> {
+        self.sensors.as_deref()
+    }
+
+    pub fn device(&self) -> &str {
+        &self.device
+    }
+
+    pub fn predicate(&self) -> Option<&Predicate> {
+        self.predicate.as_ref()
+    }
+
+    /// The matching rows of the selected sensors in time order, and the sensor names.
+    pub fn rows<F: FileReader + ?Sized>(
+        &self,
+        reader: &F,
+    ) -> error::Result<(Vec<String>, QueryRows)> {
+        let selected = match &self.sensors {
+            Some(sensors) => sensors.clone(),
+            None => {
+                let mut sensors = BTreeSet::new();
+                for meta in reader.sensor_meta_iter(&self.device) {
+                    let meta = meta?;
+                    if !meta.is_time_column() {
+                        sensors.insert(meta.measurement_id().to_string());
+                    }
+                }
+                sensors.into_iter().collect()
+            }
+        };
+        let mut sensors = selected.clone();
+        if let Some(predicate) = &self.predicate {
+            for sensor in predicate.sensors() {
+                if !sensors.iter().any(|s| s == sensor) {
+                    sensors.push(sensor.to_string());
+                }
+            }
+        }
+        let columns = sensors
+            .iter()
+            .enumerate()
+            .map(|(i, sensor)| (sensor.clone(), i))
+            .collect();
+        let filter = self.predicate.as_ref().and_then(Predicate::time_filter);
+        let rows = DeviceRows::new(reader, &self.device, sensors, filter.as_ref())?;
+        let rows = QueryRows {
+            rows,
+            predicate: self.predicate.clone(),
+            columns,
+            selected: selected.len(),
+        };
+        Ok((selected, rows))
+    }
+}
+
+/// The rows of a `Query`, values in the order of the selected sensors.
+pub struct QueryRows {
+    rows: DeviceRows,
+    predicate: Option<Predicate>,
+    columns: HashMap<String, usize>,
+    selected: usize,
+}
+
+impl Iterator for QueryRows {
+    type Item = std::result::Result<(i64, Vec<Option<Field>>), chunk::reader::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (time, mut values) = match self.rows.next()? {
+                Ok(row) => row,
+                Err(e) => return Some(Err(e)),
+            };
+            let matches = match &self.predicate {
+                Some(predicate) => {
+                    let value = |sensor: &str| values[*self.columns.get(sensor)?].as_ref();
+                    predicate.matches(time, &value)
+                }
+                None => true,
+            };
+            if matches {
+                values.truncate(self.selected);
+                return Some(Ok((time, values)));
+            }
+        }
+    }
+}
+
+/// A sensor named by its full path is made relative to the device.
+fn relative(device: &str, name: String) -> String {
+    match name.strip_prefix(device).and_then(|s| s.strip_prefix('.')) {
+        Some(sensor) => sensor.to_string(),
+        None => name,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    /// A keyword, name or path.
+    Word(String),
+    Number(String),
+    Text(String),
+    Symbol(&'static str),
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Word(w) | Token::Number(w) => write!(f, "{}", w),
+            Token::Text(t) => write!(f, "'{}'", t),
+            Token::Symbol(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+const SYMBOLS: [&str; 12] = [
+    "<=", ">=", "!=", "<>", "==", "<", ">", "=", "(", ")", ",", "*",
+];
+
+fn tokenize(query: &str) -> Result<Vec<(Token, usize)>> {
+    let mut tokens = Vec::new();
+    let mut chars = query.char_indices().peekable();
+    while let Some(&(position, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '\'' || c == '"' {
+            chars.next();
+            let mut text = String::new();
+            loop {
+                match chars.next() {
+                    Some((_, q)) if q == c => {
+                        // a doubled quote stands for itself
+                        if chars.peek().map(|(_, n)| *n) == Some(c) {
+                            chars.next();
+                            text.push(c);
+                        } else {
+                            break;
+                        }
+                    }
+                    Some((_, ch)) => text.push(ch),
+                    None => return UnterminatedString { position }.fail(),
+                }
+            }
+            tokens.push((Token::Text(text), position));
+        } else if let Some(symbol) = SYMBOLS.iter().find(|s| query[position..].starts_with(*s)) {
+            for _ in 0..symbol.len() {
+                chars.next();
+            }
+            tokens.push((Token::Symbol(symbol), position));
+        } else {
+            let mut end = position;
+            while let Some(&(i, ch)) = chars.peek() {
+                if ch.is_whitespace() || ch == '\'' || ch == '"' || "<>!=(),*".contains(ch) {
+                    break;
+                }
+                end = i + ch.len_utf8();
+                chars.next();
+            }
+            let word = &query[position..end];
+            let numeric = word.starts_with(|c: char| c.is_ascii_digit() || c == '-' || c == '+');
+            let token = if numeric {
+                Token::Number(word.to_string())
+            } else {
+                Token::Word(word.to_string())
+            };
+            tokens.push((token, position));
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    next: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.next).map(|(token, _)| token)
+    }
+
+    fn advance(&mut self, expected: &str) -> Result<(Token, usize)> {
+        let token = self.tokens.get(self.next).cloned().context(UnexpectedEnd {
+            expected: expected.to_string(),
+        })?;
+        self.next += 1;
+        Ok(token)
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        let found = self.peek() == Some(token);
+        if found {
+            self.next += 1;
+        }
+        found
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Word(w)) if w.eq_ignore_ascii_case(keyword));
+        if found {
+            self.next += 1;
+        }
+        found
+    }
+
+    fn keyword(&mut self, keyword: &str) -> Result<()> {
+        let (token, position) = self.advance(keyword)?;
+        ensure!(
+            matches!(&token, Token::Word(w) if w.eq_ignore_ascii_case(keyword)),
+            UnexpectedToken {
+                expected: keyword,
+                found: token.to_string(),
+                position,
+            }
+        );
+        Ok(())
+    }
+
+    fn name(&mut self, expected: &str) -> Result<String> {
+        match self.advance(expected)? {
+            (Token::Word(name), _) => Ok(name),
+            (token, position) => UnexpectedToken {
+                expected,
+                found: token.to_string(),
+                position,
+            }
+            .fail(),
+        }
+    }
+
+    fn or(&mut self, device: &str) -> Result<Predicate> {
+        let mut predicate = self.and(device)?;
+        while self.eat_keyword("or") {
+            predicate = Predicate::Or(Box::new(predicate), Box::new(self.and(device)?));
+        }
+        Ok(predicate)
+    }
+
+    fn and(&mut self, device: &str) -> Result<Predicate> {
+        let mut predicate = self.unary(device)?;
+        while self.eat_keyword("and") {
+            predicate = Predicate::And(Box::new(predicate), Box::new(self.unary(device)?));
+        }
+        Ok(predicate)
+    }
+
+    fn unary(&mut self, device: &str) -> Result<Predicate> {
+        if self.eat_keyword("not") {
+            return Ok(Predicate::Not(Box::new(self.unary(device)?)));
+        }
+        if self.eat(&Token::Symbol("(")) {
+            let predicate = self.or(device)?;
+            let (token, position) = self.advance(")")?;
+            ensure!(
+                token == Token::Symbol(")"),
+                UnexpectedToken {
+                    expected: ")",
+                    found: token.to_string(),
+                    position,
+                }
+            );
+            return Ok(predicate);
+        }
+        self.comparison(device)
+    }
+
+    fn comparison(&mut self, device: &str) -> Result<Predicate> {
+        let column = self.name("time or a sensor")?;
+        let (token, position) = self.advance("a comparison")?;
+        let op = match token {
+            Token::Symbol("<") => Comparison::Lt,
+            Token::Symbol("<=") => Comparison::LtEq,
+            Token::Symbol(">") => Comparison::Gt,
+            Token::Symbol(">=") => Comparison::GtEq,
+            Token::Symbol("=") | Token::Symbol("==") => Comparison::Eq,
+            Token::Symbol("!=") | Token::Symbol("<>") => Comparison::NotEq,
+            token => {
+                return UnexpectedToken {
+                    expected: "a comparison",
+                    found: token.to_string(),
+                    position,
+                }
+                .fail()
+            }
+        };
+        let (token, position) = self.advance("a value")?;
+        if column.eq_ignore_ascii_case("time") {
+            let time = match token {
+                Token::Number(n) => n.parse::<i64>().ok().context(InvalidNumber {
+                    text: n.clone(),
+                    position,
+                })?,
+                token => {
+                    return UnexpectedToken {
+                        expected: "an integer time",
+                        found: token.to_string(),
+                        position,
+                    }
+                    .fail()
+                }
+            };
+            return Ok(Predicate::Time(Filter::Time(op, time)));
+        }
+        let value = match token {
+            Token::Number(n) => match n.parse::<i64>() {
+                Ok(v) => Field::Int64(v),
+                Err(_) => Field::DOUBLE(n.parse::<f64>().ok().context(InvalidNumber {
+                    text: n.clone(),
+                    position,
+                })?),
+            },
+            Token::Text(text) => Field::TEXT(text.into_bytes()),
+            Token::Word(w) if w.eq_ignore_ascii_case("true") => Field::Boolean(true),
+            Token::Word(w) if w.eq_ignore_ascii_case("false") => Field::Boolean(false),
+            token => {
+                return UnexpectedToken {
+                    expected: "a value",
+                    found: token.to_string(),
+                    position,
+                }
+                .fail()
+            }
+        };
+        Ok(Predicate::Sensor(
+            relative(device, column),
+            Filter::Value(op, value),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_match() {
+        let query = Query::parse(
+            "SELECT s1, root.sg.d1.s2 from root.sg.d1 \
+             where time > 10 and (s1 >= 30 or not s3 = 'it''s')",
+        )
+        .unwrap();
+        assert_eq!(query.device(), "root.sg.d1");
+        assert_eq!(query.sensors().unwrap(), ["s1", "s2"]);
+        let predicate = query.predicate().unwrap();
+        assert_eq!(predicate.sensors(), ["s1", "s3"]);
+        assert!(predicate.time_filter().unwrap().satisfy_time(11));
+        assert!(!predicate.time_filter().unwrap().satisfy_time(10));
+
+        let (s1, s3) = (Field::Int32(31), Field::from("it's"));
+        let both = |sensor: &str| match sensor {
+            "s1" => Some(&s1),
+            "s3" => Some(&s3),
+            _ => None,
+        };
+        let only_s1 = |sensor: &str| Some(&s1).filter(|_| sensor == "s1");
+        let only_s3 = |sensor: &str| Some(&s3).filter(|_| sensor == "s3");
+        assert!(predicate.matches(11, &both));
+        assert!(!predicate.matches(10, &both));
+        assert!(predicate.matches(11, &only_s1));
+        assert!(!predicate.matches(11, &only_s3));
+
+        assert!(Query::parse("select * from root.sg.d1")
+            .unwrap()
+            .sensors()
+            .is_none());
+    }
+
+    #[test]
+    fn syntax_errors() {
+        let error = |query| Query::parse(query).unwrap_err().to_string();
+        assert_eq!(
+            error("select s1 root.sg.d1"),
+            "Expected from at position 10, found root.sg.d1"
+        );
+        assert_eq!(
+            error("select s1 from d where s1 >"),
+            "Expected a value at the end of the query"
+        );
+        assert_eq!(
+            error("select s1 from d where time > 1.5"),
+            "Invalid number 1.5 at position 30"
+        );
+        assert_eq!(
+            error("select s1 from d where s1 = 'a"),
+            "Unterminated string starting at position 28"
+        );
+    }
+}
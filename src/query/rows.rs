@@ -0,0 +1,326 @@
+use std::iter::Peekable;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+#[cfg(not(target_arch = "wasm32"))]
+use std::thread;
+
+use crate::chunk::reader::Error;
+use crate::encoding::decoder::Field;
+use crate::error::{self, TsFileError};
+use crate::file::options::TimestampPrecision;
+use crate::file::path::PathPattern;
+use crate::file::reader::{ChunkReader, FileReader, PageResult, SensorReader};
+use crate::query::filter::Filter;
+
+type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// A timestamp and the value of every column at it, `None` where a column has no point.
+pub type Row = (i64, Vec<Option<Field>>);
+
+/// The points of one timeseries in file order, decoded one page at a time.
+pub struct SeriesPoints {
+    reader: Option<Box<dyn SensorReader>>,
+    next_chunk: usize,
+    pages: Option<Box<dyn ChunkReader<Item = PageResult>>>,
+    points: Box<dyn Iterator<Item = Result<(i64, Field)>>>,
+}
+
+impl SeriesPoints {
+    /// The points of `reader`; no points for `None`.
+    pub fn new(reader: Option<Box<dyn SensorReader>>) -> Self {
+        Self {
+            reader,
+            next_chunk: 0,
+            pages: None,
+            points: Box::new(std::iter::empty()),
+        }
+    }
+
+    /// Points read by other means, e.g. merged from several files.
+    pub fn from_points(points: Box<dyn Iterator<Item = Result<(i64, Field)>>>) -> Self {
+        Self {
+            reader: None,
+            next_chunk: 0,
+            pages: None,
+            points,
+        }
+    }
+}
+
+impl Iterator for SeriesPoints {
+    type Item = Result<(i64, Field)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(point) = self.points.next() {
+                return Some(point);
+            }
+            match self.pages.as_mut().and_then(|pages| pages.next()) {
+                Some(page) => match page.and_then(|page| page.point_iter()) {
+                    Ok(points) => self.points = points,
+                    Err(e) => return Some(Err(e)),
+                },
+                None => {
+                    let reader = self.reader.as_ref()?;
+                    if self.next_chunk >= reader.number_of_chunks() {
+                        return None;
+                    }
+                    self.next_chunk += 1;
+                    match reader.get_chunk_reader(self.next_chunk - 1) {
+                        Ok(pages) => self.pages = Some(pages),
+                        Err(e) => return Some(Err(e)),
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The rows of a device: the points of its sensors joined on their timestamps, in time
+/// order. A sensor without a point at a row's timestamp has `None` in that row.
+pub struct DeviceRows {
+    sensors: Vec<String>,
+    precision: TimestampPrecision,
+    series: Vec<Peekable<SeriesPoints>>,
+}
+
+impl DeviceRows {
+    /// Rows of `sensors` of `device`, each sensor restricted to points matching `filter`.
+    /// Each sensor is looked up in the index on its own, so the chunks of the other sensors
+    /// are never read. Sensors the file does not contain are always `None`.
+    pub fn new<F: FileReader + ?Sized>(
+        reader: &F,
+        device: &str,
+        sensors: Vec<String>,
+        filter: Option<&Filter>,
+    ) -> error::Result<Self> {
+        let series = sensors
+            .iter()
+            .map(|sensor| {
+                let sensor_reader = reader.get_sensor_reader(device, sensor)?.map(|mut r| {
+                    if let Some(filter) = filter {
+                        r.set_filter(filter.clone());
+                    }
+                    r
+                });
+                Ok(SeriesPoints::new(sensor_reader))
+            })
+            .collect::<error::Result<_>>()?;
+        Ok(Self::from_series(
+            sensors,
+            series,
+            reader.timestamp_precision(),
+        ))
+    }
+
+    /// Rows of the timeseries `paths`, `(device, sensor)` pairs, with one `device.sensor`
+    /// column each.
+    pub fn of_paths<F: FileReader + ?Sized>(
+        reader: &F,
+        paths: &[(String, String)],
+        filter: Option<&Filter>,
+    ) -> error::Result<Self> {
+        let series = paths
+            .iter()
+            .map(|(device, sensor)| {
+                let sensor_reader = reader.get_sensor_reader(device, sensor)?.map(|mut r| {
+                    if let Some(filter) = filter {
+                        r.set_filter(filter.clone());
+                    }
+                    r
+                });
+                Ok(SeriesPoints::new(sensor_reader))
+            })
+            .collect::<error::Result<_>>()?;
+        Ok(Self::from_series(
+            columns_of(paths),
+            series,
+            reader.timestamp_precision(),
+        ))
+    }
+
+    /// Rows joining arbitrary series, e.g. of several devices, named by `columns`.
+    pub fn from_series(
+        columns: Vec<String>,
+        series: Vec<SeriesPoints>,
+        precision: TimestampPrecision,
+    ) -> Self {
+        Self {
+            sensors: columns,
+            precision,
+            series: series.into_iter().map(Iterator::peekable).collect(),
+        }
+    }
+
+    /// The sensors, or columns, in the order of the row values.
+    pub fn sensors(&self) -> &[String] {
+        &self.sensors
+    }
+
+    /// The unit of the row timestamps.
+    pub fn timestamp_precision(&self) -> TimestampPrecision {
+        self.precision
+    }
+}
+
+impl Iterator for DeviceRows {
+    type Item = Result<Row>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut time: Option<i64> = None;
+        for series in &mut self.series {
+            match series.peek() {
+                Some(Ok((t, _))) => time = Some(time.map_or(*t, |time| time.min(*t))),
+                Some(Err(_)) => {
+                    if let Some(Err(e)) = series.next() {
+                        return Some(Err(e));
+                    }
+                }
+                None => {}
+            }
+        }
+        let time = time?;
+        let values = self
+            .series
+            .iter_mut()
+            .map(|series| match series.peek() {
+                Some(Ok((t, _))) if *t == time => series.next().and_then(|p| p.ok()).map(|p| p.1),
+                _ => None,
+            })
+            .collect();
+        Some(Ok((time, values)))
+    }
+}
+
+/// The rows of `FileReader::stream`, decoded on a background thread. The channel between
+/// the thread and the consumer holds a bounded number of rows, so decoding pauses while the
+/// consumer falls behind and stops once the `RowChannel` is dropped. On wasm32, which has
+/// no threads, all rows are decoded before `spawn` returns.
+pub struct RowChannel {
+    columns: Vec<String>,
+    precision: TimestampPrecision,
+    receiver: Receiver<error::Result<Row>>,
+}
+
+impl RowChannel {
+    /// Starts decoding the rows of the timeseries matching `pattern`, restricted to
+    /// `filter`, keeping at most `capacity` decoded rows ahead of the consumer.
+    #[cfg_attr(target_arch = "wasm32", allow(unused_variables))]
+    pub fn spawn<F: FileReader + Send + Sync + 'static>(
+        reader: Arc<F>,
+        pattern: &PathPattern,
+        filter: Option<Filter>,
+        capacity: usize,
+    ) -> error::Result<Self> {
+        let paths = paths_of(reader.as_ref(), pattern)?;
+        let columns = columns_of(&paths);
+        let precision = reader.timestamp_precision();
+        #[cfg(not(target_arch = "wasm32"))]
+        let (sender, receiver) = mpsc::sync_channel(capacity.max(1));
+        // without threads every row is decoded before returning, so none may wait
+        #[cfg(target_arch = "wasm32")]
+        let (sender, receiver) = mpsc::channel();
+        let work = move || {
+            let rows = match DeviceRows::of_paths(reader.as_ref(), &paths, filter.as_ref()) {
+                Ok(rows) => rows,
+                Err(e) => {
+                    let _ = sender.send(Err(e));
+                    return;
+                }
+            };
+            for row in rows {
+                if sender.send(row.map_err(TsFileError::from)).is_err() {
+                    return;
+                }
+            }
+        };
+        #[cfg(not(target_arch = "wasm32"))]
+        thread::spawn(work);
+        #[cfg(target_arch = "wasm32")]
+        work();
+        Ok(Self {
+            columns,
+            precision,
+            receiver,
+        })
+    }
+
+    /// The `device.sensor` columns in the order of the row values.
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    /// The unit of the row timestamps.
+    pub fn timestamp_precision(&self) -> TimestampPrecision {
+        self.precision
+    }
+}
+
+impl Iterator for RowChannel {
+    type Item = error::Result<Row>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.receiver.recv().ok()
+    }
+}
+
+/// The `(device, sensor)` pairs of the timeseries matching `pattern`, in index order.
+pub(crate) fn paths_of<F: FileReader + ?Sized>(
+    reader: &F,
+    pattern: &PathPattern,
+) -> error::Result<Vec<(String, String)>> {
+    Ok(reader
+        .match_paths(pattern)?
+        .into_iter()
+        .map(|(device, meta)| {
+            let sensor = meta.measurement_id().to_string();
+            (device, sensor)
+        })
+        .collect())
+}
+
+fn columns_of(paths: &[(String, String)]) -> Vec<String> {
+    paths
+        .iter()
+        .map(|(device, sensor)| format!("{}.{}", device, sensor))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file::tsfile_search_reader::{test_file, TsFileSearchReader};
+
+    #[test]
+    fn row_channel_sends_columns_and_errors() {
+        let mut file = test_file(&[("root.sg.d1", "s1", &[1, 2]), ("root.sg.d1", "s2", &[2])]);
+        let reader = Arc::new(TsFileSearchReader::new(bytes::Bytes::from(file.clone())).unwrap());
+
+        let rows = RowChannel::spawn(reader.clone(), &PathPattern::new("root.sg.d2.*"), None, 1);
+        let rows = rows.unwrap();
+        assert!(rows.columns().is_empty());
+        assert_eq!(rows.count(), 0);
+
+        let rows = RowChannel::spawn(reader.clone(), &PathPattern::new("**"), None, 1).unwrap();
+        assert_eq!(rows.columns(), ["root.sg.d1.s1", "root.sg.d1.s2"]);
+        let rows: Vec<_> = rows.map(Result::unwrap).collect();
+        assert_eq!(
+            rows,
+            [
+                (1, vec![Some(Field::Int32(10)), None]),
+                (2, vec![Some(Field::Int32(20)), Some(Field::Int32(20))]),
+            ]
+        );
+
+        // overwrite the chunk header of s2
+        let s2 = reader
+            .get_sensor_reader("root.sg.d1", "s2")
+            .unwrap()
+            .unwrap();
+        let offset = s2.metadata()[0].offset_chunk_header() as usize;
+        file[offset..offset + 4].fill(0x7f);
+        let reader = Arc::new(TsFileSearchReader::new(bytes::Bytes::from(file)).unwrap());
+        let mut rows = RowChannel::spawn(reader, &PathPattern::new("**"), None, 1).unwrap();
+        assert!(rows.next().unwrap().is_err());
+    }
+}
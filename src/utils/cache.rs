@@ -0,0 +1,70 @@
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+
+/// A map keeping the `capacity` most recently used entries. A capacity of 0 keeps nothing.
+#[derive(Debug)]
+pub struct LruCache<K, V> {
+    capacity: usize,
+    entries: HashMap<K, (V, u64)>,
+    // last use of every key, oldest first
+    uses: BTreeMap<u64, K>,
+    clock: u64,
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> LruCache<K, V> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: HashMap::new(),
+            uses: BTreeMap::new(),
+            clock: 0,
+        }
+    }
+
+    pub fn get(&mut self, key: &K) -> Option<V> {
+        self.clock += 1;
+        let (value, used) = self.entries.get_mut(key)?;
+        self.uses.remove(used);
+        *used = self.clock;
+        self.uses.insert(self.clock, key.clone());
+        Some(value.clone())
+    }
+
+    /// Adds an entry, dropping the least recently used one if the cache is full.
+    pub fn insert(&mut self, key: K, value: V) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.clock += 1;
+        if let Some((_, used)) = self.entries.remove(&key) {
+            self.uses.remove(&used);
+        } else if self.entries.len() == self.capacity {
+            if let Some((_, oldest)) = self.uses.pop_first() {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.uses.insert(self.clock, key.clone());
+        self.entries.insert(key, (value, self.clock));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn least_recently_used_entry_is_dropped() {
+        let mut cache = LruCache::new(2);
+        cache.insert(1, "a");
+        cache.insert(2, "b");
+        assert_eq!(cache.get(&1), Some("a"));
+        cache.insert(3, "c");
+        assert_eq!(cache.get(&2), None);
+        assert_eq!(cache.get(&1), Some("a"));
+        assert_eq!(cache.get(&3), Some("c"));
+
+        let mut disabled = LruCache::new(0);
+        disabled.insert(1, "a");
+        assert_eq!(disabled.get(&1), None);
+    }
+}
@@ -1,9 +1,8 @@
 use byteorder::ReadBytesExt;
-use snafu::{ResultExt, Snafu};
+use snafu::{ensure, ResultExt, Snafu};
 use std::io;
-use std::io::{Cursor, Read};
+use std::io::{Cursor, Read, Write};
 use std::string::FromUtf8Error;
-use varint::VarintRead;
 
 #[derive(Debug, Snafu)]
 pub enum Error {
@@ -13,17 +12,80 @@ pub enum Error {
     ReadUTF8String { source: FromUtf8Error },
     #[snafu(display("Unable to read fixed length {} data: {}", len, source))]
     ReadFixedLengthData { len: usize, source: io::Error },
+    #[snafu(display("Length {} exceeds the {} remaining bytes", len, remaining))]
+    LengthExceedsData { len: i64, remaining: u64 },
 }
 
 type Result<T, E = Error> = std::result::Result<T, E>;
 
-pub trait VarIntReader: VarintRead {
-    fn read_varint_string(&mut self) -> Result<String> {
+pub trait VarIntReader: Read {
+    /// Bytes left to read, which bounds every length field read from the data.
+    fn remaining(&self) -> u64;
+
+    /// Reads an unsigned LEB128 varint of at most 32 bits.
+    fn read_unsigned_varint_32(&mut self) -> io::Result<u32> {
+        let mut value = 0u32;
+        for shift in (0..35).step_by(7) {
+            let byte = self.read_u8()?;
+            value |= ((byte & 0x7f) as u32) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "unsigned varint longer than 5 bytes",
+        ))
+    }
+
+    /// Reads a zigzag encoded varint.
+    fn read_varint(&mut self) -> Result<i32> {
         let varint = self.read_unsigned_varint_32().context(ReadUnsignedVarInt)?;
-        let mut len: usize = (varint >> 1) as usize;
+        let mut value = (varint >> 1) as i32;
         if (varint & 1) != 0 {
-            len = !len;
+            value = !value;
         }
+        Ok(value)
+    }
+
+    fn read_varint_string(&mut self) -> Result<String> {
+        let len = self.read_varint()?;
+        self.read_string(len)
+    }
+
+    /// Reads a device id of a version 4 file, a varint count of segments each stored as a
+    /// varint string or `-1` for a null segment, as the dotted name IoTDB shows for it.
+    fn read_device_id(&mut self) -> Result<String> {
+        let count = self.read_unsigned_varint_32().context(ReadUnsignedVarInt)?;
+        let remaining = self.remaining();
+        ensure!(
+            count as u64 <= remaining,
+            LengthExceedsData {
+                len: count as i64,
+                remaining
+            }
+        );
+        let mut segments = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            segments.push(match self.read_varint()? {
+                -1 => "null".to_string(),
+                len => self.read_string(len)?,
+            });
+        }
+        Ok(segments.join("."))
+    }
+
+    /// Reads a string of `len` bytes, a length read from the data.
+    fn read_string(&mut self, len: i32) -> Result<String> {
+        let remaining = self.remaining();
+        ensure!(
+            len >= 0 && len as u64 <= remaining,
+            LengthExceedsData {
+                len: len as i64,
+                remaining
+            }
+        );
+        let len = len as usize;
 
         let mut data: Vec<u8> = vec![0; len];
         self.read_exact(&mut data)
@@ -39,35 +101,42 @@ pub trait VarIntReader: VarintRead {
     }
 }
 
-pub trait PackWidthReader: Read {
-    fn read_pack_width_long(&mut self, pos: i32, width: i32) -> Result<i64> {
-        let mut data: Vec<u8> = vec![0; width as usize];
-        self.read_exact(&mut data).context(ReadFixedLengthData {
-            len: width as usize,
-        })?;
-
-        let mut temp: i32;
-        let mut value: i64 = 0;
-        for i in 0..width {
-            temp = (pos + width - 1 - i) / 8;
-            let mut offset = pos + width - 1 - i;
-            offset %= 8;
-            let byte = if ((0xff & data[temp as usize]) & (1 << (7 - offset))) != 0 {
-                1
-            } else {
-                0
-            };
-            let offset = i % 64;
-            value = if byte == 1 {
-                value | (1 << (offset))
-            } else {
-                value & (1 << (offset))
-            }
+impl<T: AsRef<[u8]>> VarIntReader for Cursor<T> {
+    fn remaining(&self) -> u64 {
+        (self.get_ref().as_ref().len() as u64).saturating_sub(self.position())
+    }
+}
+
+/// Writes the varints `VarIntReader` reads, for the writers of metadata and upgraded files.
+pub trait VarIntWriter: Write {
+    /// Writes an unsigned LEB128 varint.
+    fn write_unsigned_varint_32(&mut self, mut value: u32) -> io::Result<()> {
+        while value >= 0x80 {
+            self.write_all(&[(value as u8 & 0x7f) | 0x80])?;
+            value >>= 7;
         }
-        Ok(value)
+        self.write_all(&[value as u8])
+    }
+
+    /// Writes a zigzag encoded varint.
+    fn write_signed_varint_32(&mut self, value: i32) -> io::Result<()> {
+        self.write_unsigned_varint_32(((value << 1) ^ (value >> 31)) as u32)
     }
 }
 
-impl VarIntReader for Cursor<Vec<u8>> {}
+impl<W: Write + ?Sized> VarIntWriter for W {}
 
-impl PackWidthReader for Cursor<Vec<u8>> {}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn string_length_is_bounded_by_data() {
+        // zigzag varint 2^27, followed by two bytes
+        let mut cursor = Cursor::new(vec![0x80, 0x80, 0x80, 0x80, 0x01, b'a', b'b']);
+        assert!(matches!(
+            cursor.read_varint_string(),
+            Err(Error::LengthExceedsData { remaining: 2, .. })
+        ));
+    }
+}
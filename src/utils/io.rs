@@ -1,17 +1,55 @@
+// the buffered section reader is only used for local files
+#![cfg_attr(not(feature = "fs"), allow(dead_code))]
+
 use core::{cmp, fmt};
 use std::cell::RefCell;
-use std::io::{Cursor, Read, Result, Seek, SeekFrom};
+#[cfg(feature = "fs")]
+use std::fs::File;
+use std::io::{Error, Read, Result, Seek, SeekFrom};
 
+use crate::file::options::DEFAULT_BUFFER_SIZE;
 use crate::file::reader::{Length, TryClone};
 
-const DEFAULT_BUF_SIZE: usize = 8 * 1024;
-
 pub trait TsFileReader: Read + Seek + Length + TryClone {}
 
 impl<T: Read + Seek + Length + TryClone> TsFileReader for T {}
 
+/// Reads exactly `buf.len()` bytes at `offset` of `file`. On Unix and Windows this is a
+/// positional read, which needs no cloned handle and leaves other readers undisturbed.
+#[cfg(all(feature = "fs", unix))]
+pub fn read_exact_at(file: &File, buf: &mut [u8], offset: u64) -> Result<()> {
+    std::os::unix::fs::FileExt::read_exact_at(file, buf, offset)
+}
+
+#[cfg(all(feature = "fs", windows))]
+pub fn read_exact_at(file: &File, mut buf: &mut [u8], mut offset: u64) -> Result<()> {
+    use std::io::ErrorKind;
+    use std::os::windows::fs::FileExt;
+
+    while !buf.is_empty() {
+        match file.seek_read(buf, offset) {
+            Ok(0) => return Err(Error::from(ErrorKind::UnexpectedEof)),
+            Ok(n) => {
+                buf = &mut buf[n..];
+                offset += n as u64;
+            }
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(all(feature = "fs", not(any(unix, windows))))]
+pub fn read_exact_at(file: &File, buf: &mut [u8], offset: u64) -> Result<()> {
+    let mut file = file.try_clone()?;
+    file.seek(SeekFrom::Start(offset))?;
+    file.read_exact(buf)
+}
+
 pub struct FileSource<R: TsFileReader> {
-    reader: RefCell<R>,
+    // a failed clone is reported by the first read
+    reader: RefCell<Result<R>>,
     start: u64,
     end: u64,
     buf: Vec<u8>,
@@ -19,11 +57,6 @@ pub struct FileSource<R: TsFileReader> {
     buf_cap: usize,
 }
 
-pub trait Position {
-    /// Returns position in the stream.
-    fn pos(&self) -> u64;
-}
-
 impl<R: TsFileReader> fmt::Debug for FileSource<R> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("FileSource")
@@ -39,12 +72,16 @@ impl<R: TsFileReader> fmt::Debug for FileSource<R> {
 
 impl<R: TsFileReader> FileSource<R> {
     pub fn new(fd: &R, start: u64, length: usize) -> Self {
-        let reader = RefCell::new(fd.try_clone().unwrap());
+        Self::with_buffer_size(fd, start, length, DEFAULT_BUFFER_SIZE)
+    }
+
+    pub fn with_buffer_size(fd: &R, start: u64, length: usize, buffer_size: usize) -> Self {
+        let reader = RefCell::new(fd.try_clone());
         Self {
             reader,
             start,
             end: start + length as u64,
-            buf: vec![0_u8; DEFAULT_BUF_SIZE],
+            buf: vec![0_u8; buffer_size],
             buf_pos: 0,
             buf_cap: 0,
         }
@@ -58,6 +95,7 @@ impl<R: TsFileReader> FileSource<R> {
             // to tell the compiler that the pos..cap slice is always valid.
             debug_assert!(self.buf_pos == self.buf_cap);
             let mut reader = self.reader.borrow_mut();
+            let reader = cloned(&mut reader)?;
             reader.seek(SeekFrom::Start(self.start))?; // always seek to start before reading
             self.buf_cap = reader.read(&mut self.buf)?;
             self.buf_pos = 0;
@@ -71,6 +109,7 @@ impl<R: TsFileReader> FileSource<R> {
         self.buf_cap = 0;
         // read directly into param buffer
         let mut reader = self.reader.borrow_mut();
+        let reader = cloned(&mut reader)?;
         reader.seek(SeekFrom::Start(self.start))?; // always seek to start before reading
         let nread = reader.read(buf)?;
         self.start += nread as u64;
@@ -78,6 +117,12 @@ impl<R: TsFileReader> FileSource<R> {
     }
 }
 
+fn cloned<R>(reader: &mut Result<R>) -> Result<&mut R> {
+    reader
+        .as_mut()
+        .map_err(|e| Error::new(e.kind(), e.to_string()))
+}
+
 impl<R: TsFileReader> Read for FileSource<R> {
     fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
         let bytes_to_read = cmp::min(buf.len(), (self.end - self.start) as usize);
@@ -102,12 +147,6 @@ impl<R: TsFileReader> Read for FileSource<R> {
     }
 }
 
-impl<R: TsFileReader> Position for FileSource<R> {
-    fn pos(&self) -> u64 {
-        self.start
-    }
-}
-
 impl<R: TsFileReader> Length for FileSource<R> {
     fn len(&self) -> u64 {
         self.end - self.start
@@ -1,2 +1,4 @@
+pub mod cache;
 pub mod cursor;
 pub mod io;
+pub mod pool;